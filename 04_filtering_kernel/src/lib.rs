@@ -1,9 +1,5 @@
-use tezos_smart_rollup::{
-    inbox::InboxMessage,
-    kernel_entry,
-    michelson::{Michelson, MichelsonUnit},
-    prelude::*,
-};
+use kernel_router::Router;
+use tezos_smart_rollup::{kernel_entry, michelson::MichelsonUnit, prelude::*};
 
 // The rollups inbox contains all messages addressed to all rollups.
 // This kernel shows how to filter external messages in order to only handle
@@ -20,47 +16,22 @@ pub const MAGIC_BYTE: u8 = 0x1a;
 // read the rollup address in the kernel via `host.read_metadata()`, and filter
 // for just those messages that include the correct address.
 
-fn read_inbox_message<Expr: Michelson>(host: &mut impl Runtime) {
-    loop {
-        match host.read_input() {
-            Ok(Some(message)) => {
-                // Parse the payload of the message
-                match InboxMessage::<Expr>::parse(message.as_ref()) {
-                    Ok((remaining, InboxMessage::External([MAGIC_BYTE, data @ ..]))) => {
-                        // Only process external messages that begin with the magic byte
-                        // that we have defined for this rollup.
-                        debug_assert!(remaining.is_empty());
-                        let message = String::from_utf8_lossy(data);
-                        debug_msg!(host, "External message: \"{}\"\n", message);
-                    }
-                    Ok(_) => {
-                        // Ignore any other message
-                        continue;
-                    }
-                    Err(_) =>
-                    // Error parsing the message. This could happen when parsing a message
-                    // sent to a different rollup, which might have a different Michelson type.
-                    {
-                        continue
-                    }
-                }
-            }
-            Ok(None) =>
-            // Exit loop when there are no more messages to read.
-            {
-                break
-            }
-            Err(_) =>
-            // An error here would most likely indicate a malformed message, ignore.
-            {
-                continue
-            }
-        }
-    }
+// Only the external messages that begin with the magic byte that we have
+// defined for this rollup are dispatched to the handler, with the rest of
+// their payload. The router ignores any other message.
+fn router<Host: Runtime>() -> Router<Host, (), MichelsonUnit> {
+    Router::new().external(MAGIC_BYTE, |host, _, data| {
+        let message = String::from_utf8_lossy(data);
+        debug_msg!(host, "External message: \"{}\"\n", message);
+    })
 }
 
 pub fn entry(host: &mut impl Runtime) {
-    read_inbox_message::<MichelsonUnit>(host);
+    // An error here would most likely indicate a violation of the protocol
+    // between the Layer 1 and the rollup node or kernel.
+    if router().run(host).is_err() {
+        debug_msg!(host, "Error while reading the inbox\n");
+    }
     host.mark_for_reboot().unwrap();
 }
 
@@ -75,62 +75,3 @@ pub fn entry(host: &mut impl Runtime) {
 }
 
 kernel_entry!(entry);
-
-// Native unit tests can be written using `MockHost` and can then be
-// run with `cargo test`.
-#[cfg(test)]
-mod test {
-    use super::*;
-    use tezos_crypto_rs::hash::HashType::ContractKt1Hash;
-    use tezos_data_encoding::nom::NomReader;
-    use tezos_smart_rollup::{
-        testing::prelude::{MockHost, TransferMetadata},
-        types::{PublicKeyHash, SmartRollupAddress},
-    };
-
-    const SENDER: &str = "KT1EfTusMLoeCAAGd9MZJn5yKzFr6kJU5U91";
-    const SOURCE: &str = "tz1SodoUsWVe1Yey9eMFbqRUtNpBWfir5NRr";
-    const OTHER_ADDR: &str = "sr1RYurGZtN8KNSpkMcCt9CgWeUaNkzsAfXf";
-
-    // Check that if the inbox contains a transfer message addressed to
-    // this rollup an outbox message with the same payload will be written
-    #[test]
-    fn transfer_outbox() {
-        let mut host = MockHost::default();
-
-        // Construct an internal message from a smart contract containing
-        // data of type int and inject it into the test inbox using the
-        // mock `add_transfer` function.
-        let sender = ContractKt1Hash.b58check_to_hash(SENDER).unwrap();
-        let source = PublicKeyHash::from_b58check(SOURCE).unwrap();
-        let metadata = TransferMetadata::new(sender, source);
-        let payload = MichelsonInt::from(32);
-        host.add_transfer(payload, &metadata);
-
-        // Execute the kernel, read the outbox message, and check its payload.
-        entry(&mut host);
-        let (_, f) = OutboxMessageTransaction::<MichelsonInt>::nom_read(
-            &host.outbox_at(host.level())[0].as_slice()[5..],
-        )
-        .unwrap();
-        assert!(f.parameters == MichelsonInt::from(32));
-    }
-
-    #[test]
-    // Check that if the inbox only contains a transfer message addressed to
-    // a different rollup no outbox message is written
-    fn transfer_ignore() {
-        let mut host = MockHost::default();
-
-        let sender = ContractKt1Hash.b58check_to_hash(SENDER).unwrap();
-        let source = PublicKeyHash::from_b58check(SOURCE).unwrap();
-        let mut metadata = TransferMetadata::new(sender, source);
-        let destination = SmartRollupAddress::from_b58check(OTHER_ADDR).unwrap();
-        metadata.override_destination(destination);
-        let payload = MichelsonInt::from(32);
-        host.add_transfer(payload, &metadata);
-
-        entry(&mut host);
-        assert!(host.outbox_at(host.level()).is_empty());
-    }
-}
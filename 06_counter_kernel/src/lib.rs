@@ -1,43 +1,52 @@
 extern crate alloc;
 
+use kernel_router::{Dispatch, Router};
 use tezos_smart_rollup::{kernel_entry, prelude::*, storage::path::OwnedPath};
 
 mod counter;
 use counter::*;
 
-fn execute<Host: Runtime>(host: &mut Host, counter: Counter) -> Counter {
-    // Read the input
-    let input = host.read_input();
-
-    match input {
-        // If it's an error or no message then does nothing}
-        Err(_) | Ok(None) => counter,
-        Ok(Some(message)) => {
-            // If there is a message let's process it.
-            debug_msg!(host, "Hello message\n");
-            let data = message.as_ref();
-            match data {
-                [0x00, ..] => {
-                    debug_msg!(host, "Message from the kernel.\n");
-                    execute(host, counter)
-                }
-                [0x01, ..] => {
-                    debug_msg!(host, "Message from the user.\n");
-                    // Let's skip the first byte of the data to get what the user has sent.
-                    let user_message: Vec<&u8> = data.iter().skip(1).collect();
-                    // We are parsing the message from the user.
-                    // In the case of a good encoding we can process it.
-                    let user_message = UserAction::try_from(user_message);
-                    match user_message {
-                        Ok(user_message) => {
-                            let counter = transition(counter, user_message);
-                            execute(host, counter)
-                        }
-                        Err(_) => execute(host, counter),
-                    }
-                }
-                _ => execute(host, counter),
+fn kernel_message<Host: Runtime>(host: &mut Host) -> Option<UserAction> {
+    debug_msg!(host, "Hello message\n");
+    debug_msg!(host, "Message from the kernel.\n");
+    None
+}
+
+fn user_message<Host: Runtime>(host: &mut Host, data: &[u8]) -> Option<UserAction> {
+    debug_msg!(host, "Hello message\n");
+    debug_msg!(host, "Message from the user.\n");
+    // We are parsing the message from the user.
+    // In the case of a good encoding we can process it.
+    let user_message: Vec<&u8> = data.iter().collect();
+    UserAction::try_from(user_message).ok()
+}
+
+// The router strips the tag of the external messages,
+// so the handler receives what the user has sent.
+fn router<Host: Runtime>() -> Router<Host, Option<UserAction>> {
+    Router::new()
+        .start_of_level(|host, _| kernel_message(host))
+        .info_per_level(|host, _, _| kernel_message(host))
+        .end_of_level(|host, _| kernel_message(host))
+        .any_transfer(|host, _, _| kernel_message(host))
+        .any_external(|host, _, data| user_message(host, data))
+}
+
+fn execute<Host: Runtime>(
+    host: &mut Host,
+    router: &Router<Host, Option<UserAction>>,
+    mut counter: Counter,
+) -> Counter {
+    loop {
+        // Read the input and dispatch it
+        match router.dispatch(host) {
+            // If it's an error or no message then does nothing
+            Err(_) | Ok(None) => return counter,
+            // If there is an action let's process it.
+            Ok(Some((Dispatch::Handled(Some(user_action)), _))) => {
+                counter = transition(counter, user_action);
             }
+            Ok(Some(_)) => (),
         }
     }
 }
@@ -49,7 +58,7 @@ pub fn entry(host: &mut impl Runtime) {
         .and_then(Counter::try_from)
         .unwrap_or_default();
 
-    let counter = execute(host, counter);
+    let counter = execute(host, &router(), counter);
 
     let counter: [u8; 8] = counter.into();
     let _ = Runtime::store_write(host, &counter_path, &counter, 0);
@@ -63,6 +72,8 @@ kernel_entry!(entry);
 // 'load inputs'
 // 'step result'
 // 'show key /counter'
+// The scenarios of `scenarios/` are run natively with `cargo test`
+
 #[cfg(test)]
 mod test {
     use tezos_smart_rollup::{host::Runtime, storage::path::OwnedPath};
@@ -79,7 +90,7 @@ mod test {
         let counter_path: OwnedPath = "/counter".as_bytes().to_vec().try_into().unwrap();
         host.run_level(entry);
 
-        let counter = Runtime::store_read(&mut host, &counter_path, 0, 8)
+        let counter = Runtime::store_read(&host, &counter_path, 0, 8)
             .map_err(|_| "Runtime error".to_string())
             .and_then(Counter::try_from)
             .unwrap_or_default();
@@ -89,7 +100,7 @@ mod test {
         let action = UserAction::Increment;
         host.add_external(action);
         host.run_level(entry);
-        let counter = Runtime::store_read(&mut host, &counter_path, 0, 8)
+        let counter = Runtime::store_read(&host, &counter_path, 0, 8)
             .map_err(|_| "Runtime error".to_string())
             .and_then(Counter::try_from)
             .unwrap_or_default();
@@ -0,0 +1,9 @@
+use kernel_scenario::ScenarioHost;
+
+// Runs the scenarios of the scenarios directory against the kernel
+fn main() {
+    kernel_scenario::main(
+        |host: &mut ScenarioHost| counter_kernel::entry(host),
+        concat!(env!("CARGO_MANIFEST_DIR"), "/scenarios"),
+    );
+}
@@ -0,0 +1,432 @@
+//! Client of the tzwitter kernel
+//!
+//! Builds the messages understood by the kernel, signs them
+//! and encodes them as external messages of the rollup
+
+pub mod node;
+
+use ed25519_compact::{KeyPair, Seed};
+use tezos_crypto_rs::hash::{Ed25519Signature, PublicKeyEd25519, SeedEd25519};
+use thiserror::Error;
+use tzwitter_kernel::constants::MAGIC_BYTE;
+use tzwitter_kernel::core::hash::Blake2b;
+use tzwitter_kernel::core::public_key::PublicKey;
+use tzwitter_kernel::core::signature::Signature;
+
+pub use tzwitter_kernel::core::merkle::TweetProof;
+pub use tzwitter_kernel::core::message::{
+    AuthorizeSessionKey, Content, EditTweet, Inner, Message, MigrateAccount, PostPoll, PostTweet,
+    PostTweetRef, Quote, SetSigners, Transfer, Vote,
+};
+pub use tzwitter_kernel::core::multisig::Cosignature;
+pub use tzwitter_kernel::core::nonce::Nonce;
+pub use tzwitter_kernel::core::pause::PauseMode;
+pub use tzwitter_kernel::core::public_key_hash::PublicKeyHash;
+pub use tzwitter_kernel::core::session_key::SessionOperation;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Invalid secret key: {0}.")]
+    SecretKey(String),
+    #[error("Invalid public key.")]
+    PublicKey,
+    #[error("Invalid signature.")]
+    Signature,
+    #[error("Cannot serialize the message: {0}.")]
+    Serialization(String),
+    #[error("Request to {0} failed: {1}.")]
+    Http(String, String),
+    #[error("Invalid nonce returned by the node: {0}.")]
+    InvalidNonce(String),
+}
+
+/// Signs the messages of an account
+pub struct Signer {
+    key_pair: KeyPair,
+}
+
+impl Signer {
+    /// Creates a signer from a b58 encoded Ed25519 secret key (edsk...)
+    ///
+    /// The "unencrypted:" prefix of the octez-client secret keys is accepted
+    pub fn from_b58(secret_key: &str) -> Result<Self, Error> {
+        let secret_key = secret_key.trim();
+        let secret_key = secret_key
+            .strip_prefix("unencrypted:")
+            .unwrap_or(secret_key);
+        let seed = SeedEd25519::from_base58_check(secret_key)
+            .map_err(|e| Error::SecretKey(e.to_string()))?;
+        let seed = Seed::from_slice(seed.as_ref()).map_err(|e| Error::SecretKey(e.to_string()))?;
+        let key_pair = KeyPair::from_seed(seed);
+        Ok(Signer { key_pair })
+    }
+
+    /// Returns the public key of the signer
+    pub fn public_key(&self) -> Result<PublicKey, Error> {
+        let pkey =
+            PublicKeyEd25519::try_from(self.key_pair.pk.to_vec()).map_err(|_| Error::PublicKey)?;
+        Ok(PublicKey::Ed25519(pkey))
+    }
+
+    /// Returns the public key hash (tz1...) of the signer
+    pub fn public_key_hash(&self) -> Result<PublicKeyHash, Error> {
+        let public_key = self.public_key()?;
+        Ok(PublicKeyHash::from(&public_key))
+    }
+
+    /// Signs the content with the given nonce
+    ///
+    /// The signed data is the blake2b of Inner::hash, as the kernel verifies it
+    pub fn sign(&self, nonce: Nonce, content: Content) -> Result<Message, Error> {
+        self.sign_inner(Inner::new(nonce, content))
+    }
+
+    /// Signs the content as a session key of the account
+    ///
+    /// The nonce is the one of the account, not the one of the session key
+    pub fn sign_for(
+        &self,
+        account: PublicKeyHash,
+        nonce: Nonce,
+        content: Content,
+    ) -> Result<Message, Error> {
+        self.sign_inner(Inner::delegated(nonce, content, account))
+    }
+
+    fn sign_inner(&self, inner: Inner) -> Result<Message, Error> {
+        let signature = self.sign_hash(&inner.hash())?;
+        let public_key = self.public_key()?;
+        Ok(Message::new(public_key, signature, inner))
+    }
+
+    fn sign_hash(&self, hash: &Blake2b) -> Result<Signature, Error> {
+        let data = Blake2b::from(hash.as_ref());
+        let signature = self.key_pair.sk.sign(data.as_ref(), None);
+        let signature =
+            Ed25519Signature::try_from(signature.to_vec()).map_err(|_| Error::Signature)?;
+        Ok(Signature::Ed25519(signature))
+    }
+
+    /// Adds the signature of the signer to a message of a multisig account
+    pub fn cosign(&self, message: &mut Message) -> Result<(), Error> {
+        let signature = self.sign_hash(&message.hash())?;
+        let pkey = self.public_key()?;
+        message.add_cosignature(Cosignature { pkey, signature });
+        Ok(())
+    }
+
+    /// Signs a tweet authored by the signer
+    pub fn post_tweet(&self, nonce: Nonce, content: &str) -> Result<Message, Error> {
+        let author = self.public_key_hash()?;
+        let post_tweet = PostTweet {
+            author,
+            content: content.to_string(),
+        };
+        self.sign(nonce, Content::PostTweet(post_tweet))
+    }
+
+    /// Signs a new content for a tweet written by the signer
+    pub fn edit_tweet(&self, nonce: Nonce, tweet_id: u64, content: &str) -> Result<Message, Error> {
+        let edit_tweet = EditTweet {
+            tweet_id,
+            content: content.to_string(),
+        };
+        self.sign(nonce, Content::EditTweet(edit_tweet))
+    }
+
+    /// Signs a tweet with a poll, open to the votes until the closing level
+    pub fn post_poll(
+        &self,
+        nonce: Nonce,
+        content: &str,
+        options: &[&str],
+        closes_at_level: u32,
+    ) -> Result<Message, Error> {
+        let post_poll = PostPoll {
+            content: content.to_string(),
+            options: options.iter().map(|option| option.to_string()).collect(),
+            closes_at_level,
+        };
+        self.sign(nonce, Content::PostPoll(post_poll))
+    }
+
+    /// Signs a vote for an option of a poll
+    pub fn vote(&self, nonce: Nonce, tweet_id: u64, option: u32) -> Result<Message, Error> {
+        self.sign(nonce, Content::Vote(Vote { tweet_id, option }))
+    }
+
+    /// Signs a retweet of a tweet
+    pub fn retweet(&self, nonce: Nonce, tweet_id: u64) -> Result<Message, Error> {
+        self.sign(nonce, Content::Retweet(tweet_id))
+    }
+
+    /// Signs a new tweet quoting another one
+    pub fn quote_tweet(
+        &self,
+        nonce: Nonce,
+        tweet_id: u64,
+        content: &str,
+    ) -> Result<Message, Error> {
+        let quote = Quote {
+            tweet_id,
+            content: content.to_string(),
+        };
+        self.sign(nonce, Content::Quote(quote))
+    }
+
+    /// Signs the pause of the kernel, only the admin can pause it
+    pub fn pause(&self, nonce: Nonce, mode: PauseMode) -> Result<Message, Error> {
+        self.sign(nonce, Content::Pause(mode))
+    }
+
+    /// Signs the end of the pause of the kernel
+    pub fn unpause(&self, nonce: Nonce) -> Result<Message, Error> {
+        self.sign(nonce, Content::Unpause)
+    }
+
+    /// Signs a like of a tweet
+    pub fn like_tweet(&self, nonce: Nonce, tweet_id: u64) -> Result<Message, Error> {
+        self.sign(nonce, Content::LikeTweet(tweet_id))
+    }
+
+    /// Signs the transfer of a tweet to another account
+    pub fn transfer_tweet(
+        &self,
+        nonce: Nonce,
+        tweet_id: u64,
+        destination: PublicKeyHash,
+    ) -> Result<Message, Error> {
+        let transfer = Transfer {
+            destination,
+            tweet_id,
+        };
+        self.sign(nonce, Content::Transfer(transfer))
+    }
+
+    /// Signs the collect of a tweet
+    pub fn collect_tweet(&self, nonce: Nonce, tweet_id: u64) -> Result<Message, Error> {
+        self.sign(nonce, Content::Collect(tweet_id))
+    }
+
+    /// Signs the authorization of a session key until the expiry level
+    pub fn authorize_session_key(
+        &self,
+        nonce: Nonce,
+        key: PublicKeyHash,
+        expiry_level: u32,
+        allowed_ops: Vec<SessionOperation>,
+    ) -> Result<Message, Error> {
+        let authorize = AuthorizeSessionKey {
+            key,
+            expiry_level,
+            allowed_ops,
+        };
+        self.sign(nonce, Content::AuthorizeSessionKey(authorize))
+    }
+
+    /// Signs the revocation of a session key
+    pub fn revoke_session_key(&self, nonce: Nonce, key: PublicKeyHash) -> Result<Message, Error> {
+        self.sign(nonce, Content::RevokeSessionKey(key))
+    }
+
+    /// Signs the M-of-N signers that will control the account
+    pub fn set_signers(
+        &self,
+        nonce: Nonce,
+        threshold: u32,
+        signers: Vec<PublicKeyHash>,
+    ) -> Result<Message, Error> {
+        self.sign(
+            nonce,
+            Content::SetSigners(SetSigners { threshold, signers }),
+        )
+    }
+
+    /// Signs the migration of the account to a new key
+    pub fn migrate_account(
+        &self,
+        nonce: Nonce,
+        new_owner: PublicKeyHash,
+    ) -> Result<Message, Error> {
+        self.sign(nonce, Content::MigrateAccount(MigrateAccount { new_owner }))
+    }
+}
+
+/// Serializes the message to the json read by the kernel
+pub fn to_json(message: &Message) -> Result<String, Error> {
+    serde_json_wasm::to_string(message).map_err(|e| Error::Serialization(e.to_string()))
+}
+
+/// Encodes the message as the payload of an external message
+///
+/// 0x01 is the tag of the external messages, followed by the MAGIC_BYTE of tzwitter
+pub fn to_external_payload(message: &Message) -> Result<Vec<u8>, Error> {
+    let json = to_json(message)?;
+    let mut payload = vec![0x01, MAGIC_BYTE];
+    payload.extend_from_slice(json.as_bytes());
+    Ok(payload)
+}
+
+/// Encodes the message as the operation submitted to the sequencer or to octez-client
+///
+/// It is the external payload without the 0x01 tag, that is added by the inbox
+pub fn to_operation(message: &Message) -> Result<Vec<u8>, Error> {
+    let payload = to_external_payload(message)?;
+    Ok(payload[1..].to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        to_external_payload, to_json, to_operation, Content, Nonce, PostTweet, PublicKeyHash,
+        SessionOperation, Signer,
+    };
+    use tzwitter_kernel::constants::MAGIC_BYTE;
+
+    const SECRET_KEY: &str = "edsk3a5SDDdMWw3Q5hPiJwDXUosmZMTuKQkriPqY6UqtSfdLifpZbB";
+
+    /// Secret key of tz1Rf22vznYLW8bVH1A3M9VUS4DLyf58gjDA, used as a session key and as a signer
+    const CAROL_KEY: &str = "edsk3FSURxDvkUF3Dt9UCGH7Uza2nNcLfAjd7adKrShW4Yoap9FtrZ";
+
+    /// Fixture input_1 of the kernel
+    const INPUT_1: &str = "7b22706b6579223a7b2245643235353139223a226564706b75444d556d375935337770346778654c425875694168585a724c6e385842315238336b737676657348384c7038626d43664b227d2c227369676e6174757265223a7b2245643235353139223a226564736967746658484337537875433378754453423563624a426a786b514672656f6e38584368526750446f674547355662506542545250794341513156586a75734e4a375537456557674d44703679634159473334774851665667726d47454a6974227d2c22696e6e6572223a7b226e6f6e6365223a312c22636f6e74656e74223a7b22506f73745477656574223a7b22617574686f72223a7b22547a31223a22747a315146443957714c575a6d6d4175716e6e545050556a666175697459455764736876227d2c22636f6e74656e74223a2248656c6c6f20776f726c64227d7d7d7d";
+
+    /// Fixture input_like of the kernel
+    const INPUT_LIKE: &str = "7b22706b6579223a7b2245643235353139223a226564706b75444d556d375935337770346778654c425875694168585a724c6e385842315238336b737676657348384c7038626d43664b227d2c227369676e6174757265223a7b2245643235353139223a226564736967746b717577626a4a467a41464c7134345267527454564e777948774857624b386e47343855564b5069766b32635057505735345359335935534e4439786635463852795335424e665861595a4c453664776d554b70325541394275435a32227d2c22696e6e6572223a7b226e6f6e6365223a322c22636f6e74656e74223a7b224c696b655477656574223a307d7d7d";
+
+    /// Fixture input_transfer of the kernel
+    const INPUT_TRANSFER: &str = "7b22706b6579223a7b2245643235353139223a226564706b75444d556d375935337770346778654c425875694168585a724c6e385842315238336b737676657348384c7038626d43664b227d2c227369676e6174757265223a7b2245643235353139223a226564736967746a616a43534e5548464a6f6f775978756e566b5a53644478655a7459687a5756444d617359785365315a59625650444e4b4d4157574152454c52734244624242774d646f786f36676e36766639374e74413661745232637656746f7a37227d2c22696e6e6572223a7b226e6f6e6365223a322c22636f6e74656e74223a7b225472616e73666572223a7b2264657374696e6174696f6e223a7b22547a31223a22747a3154477536544e354753657a326e645858654458364c675544764c7a504c71675956227d2c2274776565745f6964223a307d7d7d7d";
+
+    /// Fixture input_authorize_session_key of the kernel
+    const INPUT_AUTHORIZE_SESSION_KEY: &str = "7b22706b6579223a7b2245643235353139223a226564706b75444d556d375935337770346778654c425875694168585a724c6e385842315238336b737676657348384c7038626d43664b227d2c227369676e6174757265223a7b2245643235353139223a226564736967746236383942775844644342433576413844744b53396e5548327347757656523254564153504144666f6e4879577a75454b556d4e426257357874416d4671696e59676a7a4c67505a685859624b4b66756263547476716373654c6b394a227d2c22696e6e6572223a7b226e6f6e6365223a312c22636f6e74656e74223a7b22417574686f72697a6553657373696f6e4b6579223a7b226b6579223a7b22547a31223a22747a3152663232767a6e594c57386256483141334d3956555334444c79663538676a4441227d2c226578706972795f6c6576656c223a31302c22616c6c6f7765645f6f7073223a5b22506f73745477656574222c224c696b655477656574225d7d7d7d7d";
+
+    /// Fixture input_session_like of the kernel
+    const INPUT_SESSION_LIKE: &str = "7b22706b6579223a7b2245643235353139223a226564706b7477474c4d68617456483872646a733244786e46794c466d6f563731636238354e45747a477077573475454d324a48354852227d2c227369676e6174757265223a7b2245643235353139223a226564736967745a6e45766967556b3634396b687a365a756253565774444c7432694a31667a474c34786b5268695270773768586445454645366f55746456464d593937377131616b3846575455766359624a733770787248594c6171357a6545384866227d2c22696e6e6572223a7b226e6f6e6365223a332c22636f6e74656e74223a7b224c696b655477656574223a307d2c226163636f756e74223a7b22547a31223a22747a315146443957714c575a6d6d4175716e6e545050556a666175697459455764736876227d7d7d";
+
+    /// Secret key of the cosigner tz1hEW4Bz2tLWBJbefwx6wCqdcX4CcjyLXdq
+    const COSIGNER_KEY: &str = "edsk3f2bMT6U6bcd2PfuGaoYbf5HptpA7dMB3Cp8zCBERnsVMaA7ok";
+
+    /// Fixture input_post_poll of the kernel
+    const INPUT_POST_POLL: &str = "7b22706b6579223a7b2245643235353139223a226564706b75444d556d375935337770346778654c425875694168585a724c6e385842315238336b737676657348384c7038626d43664b227d2c227369676e6174757265223a7b2245643235353139223a226564736967753242467a5651373943424253667571516e426e42457575544648713372354e69386a393466563477544e77743844365343783439556d567674566d77376d76416a656f7350615a48666b6841477a4d6e78394676716f78414733334d43227d2c22696e6e6572223a7b226e6f6e6365223a312c22636f6e74656e74223a7b22506f7374506f6c6c223a7b22636f6e74656e74223a224265737420636f6c6f723f222c226f7074696f6e73223a5b22526564222c22426c7565225d2c22636c6f7365735f61745f6c6576656c223a31307d7d7d7d";
+
+    /// Fixture input_multisig_two_signatures of the kernel
+    const INPUT_MULTISIG_TWO_SIGNATURES: &str = "7b22706b6579223a7b2245643235353139223a226564706b7477474c4d68617456483872646a733244786e46794c466d6f563731636238354e45747a477077573475454d324a48354852227d2c227369676e6174757265223a7b2245643235353139223a226564736967746251365175474847354c44354a72777237576f65556a5a35535675515732535a517639555a5766795547507a7a7739367935745046706f45665a457a36687a31685232546e33624d71394c643750427337687a6d58396831673734394d227d2c22696e6e6572223a7b226e6f6e6365223a322c22636f6e74656e74223a7b22506f73745477656574223a7b22617574686f72223a7b22547a31223a22747a315146443957714c575a6d6d4175716e6e545050556a666175697459455764736876227d2c22636f6e74656e74223a2248656c6c6f20776f726c64227d7d2c226163636f756e74223a7b22547a31223a22747a315146443957714c575a6d6d4175716e6e545050556a666175697459455764736876227d7d2c22636f7369676e617475726573223a5b7b22706b6579223a7b2245643235353139223a226564706b7653565a7652365a37534879474e557a3839484e614c655779775234516d444c5376545457634e4d3742597735696e655250227d2c227369676e6174757265223a7b2245643235353139223a226564736967753734386a34785a32514a31483369364d5570795976764e7a484c416d684d7647465375546845354b4c706863464e327037596d48476f61527a6977756e597a7035464d7a5873586955735a557331774d63756d435959586d4a4353596e227d7d5d7d";
+
+    #[test]
+    fn test_public_key_hash() {
+        let signer = Signer::from_b58(SECRET_KEY).unwrap();
+        let public_key_hash = signer.public_key_hash().unwrap();
+        assert_eq!(
+            "tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv",
+            public_key_hash.to_string()
+        );
+    }
+
+    #[test]
+    fn test_input_1() {
+        let signer = Signer::from_b58(SECRET_KEY).unwrap();
+        let message = signer.post_tweet(Nonce(1), "Hello world").unwrap();
+        let json = to_json(&message).unwrap();
+        assert_eq!(INPUT_1, hex::encode(json));
+    }
+
+    #[test]
+    fn test_input_like() {
+        let signer = Signer::from_b58(SECRET_KEY).unwrap();
+        let message = signer.like_tweet(Nonce(2), 0).unwrap();
+        let json = to_json(&message).unwrap();
+        assert_eq!(INPUT_LIKE, hex::encode(json));
+    }
+
+    #[test]
+    fn test_input_transfer() {
+        let signer = Signer::from_b58(SECRET_KEY).unwrap();
+        let destination = PublicKeyHash::from_b58("tz1TGu6TN5GSez2ndXXeDX6LgUDvLzPLqgYV").unwrap();
+        let message = signer.transfer_tweet(Nonce(2), 0, destination).unwrap();
+        let json = to_json(&message).unwrap();
+        assert_eq!(INPUT_TRANSFER, hex::encode(json));
+    }
+
+    #[test]
+    fn test_external_payload() {
+        let signer = Signer::from_b58(SECRET_KEY).unwrap();
+        let message = signer.post_tweet(Nonce(1), "Hello world").unwrap();
+        let payload = to_external_payload(&message).unwrap();
+        assert_eq!(
+            format!("01{:02x}{}", MAGIC_BYTE, INPUT_1),
+            hex::encode(payload)
+        );
+    }
+
+    #[test]
+    fn test_operation() {
+        let signer = Signer::from_b58(&format!("unencrypted:{}\n", SECRET_KEY)).unwrap();
+        let message = signer.post_tweet(Nonce(1), "Hello world").unwrap();
+        let operation = to_operation(&message).unwrap();
+        assert_eq!(
+            format!("{:02x}{}", MAGIC_BYTE, INPUT_1),
+            hex::encode(operation)
+        );
+    }
+
+    #[test]
+    fn test_signature_verified_by_kernel() {
+        let signer = Signer::from_b58(SECRET_KEY).unwrap();
+        let message = signer.like_tweet(Nonce(2), 0).unwrap();
+        let hash = message.hash();
+        let verification = message
+            .signature()
+            .verify(message.public_key(), hash.as_ref());
+        assert!(verification.is_ok());
+    }
+
+    #[test]
+    fn test_input_authorize_session_key() {
+        let signer = Signer::from_b58(SECRET_KEY).unwrap();
+        let key = PublicKeyHash::from_b58("tz1Rf22vznYLW8bVH1A3M9VUS4DLyf58gjDA").unwrap();
+        let allowed_ops = vec![SessionOperation::PostTweet, SessionOperation::LikeTweet];
+        let message = signer
+            .authorize_session_key(Nonce(1), key, 10, allowed_ops)
+            .unwrap();
+        let json = to_json(&message).unwrap();
+        assert_eq!(INPUT_AUTHORIZE_SESSION_KEY, hex::encode(json));
+    }
+
+    #[test]
+    fn test_input_session_like() {
+        let session_key = Signer::from_b58(CAROL_KEY).unwrap();
+        let account = PublicKeyHash::from_b58("tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv").unwrap();
+        let message = session_key
+            .sign_for(account, Nonce(3), Content::LikeTweet(0))
+            .unwrap();
+        let json = to_json(&message).unwrap();
+        assert_eq!(INPUT_SESSION_LIKE, hex::encode(json));
+    }
+
+    #[test]
+    fn test_input_multisig_two_signatures() {
+        let signer = Signer::from_b58(CAROL_KEY).unwrap();
+        let cosigner = Signer::from_b58(COSIGNER_KEY).unwrap();
+        let account = PublicKeyHash::from_b58("tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv").unwrap();
+        let post_tweet = PostTweet {
+            author: account.clone(),
+            content: "Hello world".to_string(),
+        };
+        let mut message = signer
+            .sign_for(account, Nonce(2), Content::PostTweet(post_tweet))
+            .unwrap();
+        cosigner.cosign(&mut message).unwrap();
+        let json = to_json(&message).unwrap();
+        assert_eq!(INPUT_MULTISIG_TWO_SIGNATURES, hex::encode(json));
+    }
+
+    #[test]
+    fn test_input_post_poll() {
+        let signer = Signer::from_b58(SECRET_KEY).unwrap();
+        let message = signer
+            .post_poll(Nonce(1), "Best color?", &["Red", "Blue"], 10)
+            .unwrap();
+        let json = to_json(&message).unwrap();
+        assert_eq!(INPUT_POST_POLL, hex::encode(json));
+    }
+}
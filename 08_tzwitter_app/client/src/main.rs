@@ -0,0 +1,303 @@
+use clap::{Parser, Subcommand};
+use std::ffi::OsString;
+use std::fs;
+use thiserror::Error;
+use tzwitter_client::node::Endpoint;
+use tzwitter_client::{
+    to_operation, AuthorizeSessionKey, Content, EditTweet, Error, Message, MigrateAccount, Nonce,
+    PauseMode, PostPoll, PostTweet, PublicKeyHash, Quote, SessionOperation, Signer, Transfer, Vote,
+};
+
+#[derive(Parser)]
+#[command(long_about = None)]
+pub struct Cli {
+    /// The secret key of the account (edsk...)
+    #[arg(
+        short,
+        long,
+        value_name = "SECRET_KEY",
+        conflicts_with = "secret_key_file"
+    )]
+    pub secret_key: Option<String>,
+
+    /// A file containing the secret key of the account
+    #[arg(long, value_name = "SECRET_KEY_FILE")]
+    pub secret_key_file: Option<OsString>,
+
+    /// The sequencer used to read the nonce and to submit the operation
+    #[arg(long, value_name = "SEQUENCER_URL", conflicts_with = "rollup_node")]
+    pub sequencer: Option<String>,
+
+    /// The rollup node used to read the nonce, the operation is printed as hex
+    #[arg(long, value_name = "ROLLUP_NODE_URL")]
+    pub rollup_node: Option<String>,
+
+    /// The nonce of the operation, read from the sequencer or the rollup node if omitted
+    #[arg(short, long, value_name = "NONCE")]
+    pub nonce: Option<u64>,
+
+    /// Sign as a session key of this account (tz1...)
+    #[arg(long, value_name = "ACCOUNT")]
+    pub account: Option<String>,
+
+    #[command(subcommand)]
+    pub command: Commands,
+}
+
+#[derive(Subcommand)]
+pub enum Commands {
+    /// Post a tweet
+    Post {
+        #[arg(short, long, value_name = "CONTENT")]
+        content: String,
+    },
+    /// Edit the content of a tweet
+    Edit {
+        #[arg(short, long, value_name = "TWEET_ID")]
+        tweet_id: u64,
+
+        #[arg(short, long, value_name = "CONTENT")]
+        content: String,
+    },
+    /// Post a tweet with a poll
+    Poll {
+        #[arg(short, long, value_name = "CONTENT")]
+        content: String,
+
+        /// The options of the poll, at least two
+        #[arg(short, long, value_name = "OPTION")]
+        option: Vec<String>,
+
+        /// The last level at which the accounts can vote
+        #[arg(long, value_name = "CLOSES_AT_LEVEL")]
+        closes_at_level: u32,
+    },
+    /// Vote for an option of a poll
+    Vote {
+        #[arg(short, long, value_name = "TWEET_ID")]
+        tweet_id: u64,
+
+        /// The index of the option, starting at 0
+        #[arg(short, long, value_name = "OPTION")]
+        option: u32,
+    },
+    /// Retweet a tweet
+    Retweet {
+        #[arg(short, long, value_name = "TWEET_ID")]
+        tweet_id: u64,
+    },
+    /// Post a tweet quoting another one
+    Quote {
+        #[arg(short, long, value_name = "TWEET_ID")]
+        tweet_id: u64,
+
+        #[arg(short, long, value_name = "CONTENT")]
+        content: String,
+    },
+    /// Like a tweet
+    Like {
+        #[arg(short, long, value_name = "TWEET_ID")]
+        tweet_id: u64,
+    },
+    /// Transfer a tweet to another account
+    Transfer {
+        #[arg(short, long, value_name = "TWEET_ID")]
+        tweet_id: u64,
+
+        #[arg(short, long, value_name = "DESTINATION")]
+        destination: String,
+    },
+    /// Collect a tweet as a NFT on the L1
+    Collect {
+        #[arg(short, long, value_name = "TWEET_ID")]
+        tweet_id: u64,
+    },
+    /// Authorize a session key to sign some operations for the account
+    Authorize {
+        #[arg(short, long, value_name = "SESSION_KEY")]
+        key: String,
+
+        /// The last level at which the session key can be used
+        #[arg(short, long, value_name = "EXPIRY_LEVEL")]
+        expiry_level: u32,
+
+        /// The allowed operations: PostTweet, LikeTweet, Transfer or Collect
+        #[arg(short, long, value_name = "OPERATION")]
+        allow: Vec<String>,
+    },
+    /// Revoke a session key of the account
+    Revoke {
+        #[arg(short, long, value_name = "SESSION_KEY")]
+        key: String,
+    },
+    /// Move the tweets, the likes and the nonce of the account to a new key
+    Migrate {
+        #[arg(long, value_name = "NEW_OWNER")]
+        new_owner: String,
+    },
+    /// Pause the processing of the user messages, signed by the admin
+    Pause {
+        /// Reject the messages, or Defer them until the kernel is unpaused
+        #[arg(short, long, value_name = "MODE", default_value = "Reject")]
+        mode: String,
+    },
+    /// Resume the processing of the user messages, signed by the admin
+    Unpause,
+}
+
+#[derive(Debug, Error)]
+enum ClientError {
+    #[error("Unable to read the secret key file: {0}.")]
+    SecretKeyFile(std::io::Error),
+    #[error("A secret key or a secret key file is required.")]
+    MissingSecretKey,
+    #[error("A nonce, a sequencer or a rollup node is required.")]
+    MissingNonce,
+    #[error("Invalid destination: {0}.")]
+    InvalidDestination(String),
+    #[error("Invalid account: {0}.")]
+    InvalidAccount(String),
+    #[error("Invalid session key: {0}.")]
+    InvalidSessionKey(String),
+    #[error("Invalid new owner: {0}.")]
+    InvalidNewOwner(String),
+    #[error("Invalid operation: {0}.")]
+    InvalidOperation(String),
+    #[error("Invalid pause mode: {0}.")]
+    InvalidPauseMode(String),
+    #[error("{0}")]
+    Client(#[from] Error),
+}
+
+fn main() -> Result<(), ClientError> {
+    let cli = Cli::parse();
+
+    let secret_key = match (cli.secret_key, cli.secret_key_file) {
+        (Some(secret_key), _) => secret_key,
+        (None, Some(file)) => fs::read_to_string(file).map_err(ClientError::SecretKeyFile)?,
+        (None, None) => return Err(ClientError::MissingSecretKey),
+    };
+    let signer = Signer::from_b58(&secret_key)?;
+
+    let endpoint = match (cli.sequencer, cli.rollup_node) {
+        (Some(url), _) => Some(Endpoint::Sequencer(url)),
+        (None, Some(url)) => Some(Endpoint::RollupNode(url)),
+        (None, None) => None,
+    };
+
+    // A session key uses the nonce of the account it signs for
+    let account = match cli.account {
+        Some(account) => Some(
+            PublicKeyHash::from_b58(&account).map_err(|_| ClientError::InvalidAccount(account))?,
+        ),
+        None => None,
+    };
+    let nonce = match (cli.nonce, &endpoint) {
+        (Some(nonce), _) => Nonce(nonce),
+        (None, Some(endpoint)) => {
+            let public_key_hash = match &account {
+                Some(account) => account.clone(),
+                None => signer.public_key_hash()?,
+            };
+            endpoint.read_nonce(&public_key_hash)?.next()
+        }
+        (None, None) => return Err(ClientError::MissingNonce),
+    };
+
+    let content = match cli.command {
+        Commands::Post { content } => {
+            let author = account.clone().unwrap_or(signer.public_key_hash()?);
+            Content::PostTweet(PostTweet { author, content })
+        }
+        Commands::Edit { tweet_id, content } => Content::EditTweet(EditTweet { tweet_id, content }),
+        Commands::Poll {
+            content,
+            option,
+            closes_at_level,
+        } => Content::PostPoll(PostPoll {
+            content,
+            options: option,
+            closes_at_level,
+        }),
+        Commands::Vote { tweet_id, option } => Content::Vote(Vote { tweet_id, option }),
+        Commands::Retweet { tweet_id } => Content::Retweet(tweet_id),
+        Commands::Quote { tweet_id, content } => Content::Quote(Quote { tweet_id, content }),
+        Commands::Like { tweet_id } => Content::LikeTweet(tweet_id),
+        Commands::Transfer {
+            tweet_id,
+            destination,
+        } => {
+            let destination = PublicKeyHash::from_b58(&destination)
+                .map_err(|_| ClientError::InvalidDestination(destination))?;
+            Content::Transfer(Transfer {
+                destination,
+                tweet_id,
+            })
+        }
+        Commands::Collect { tweet_id } => Content::Collect(tweet_id),
+        Commands::Authorize {
+            key,
+            expiry_level,
+            allow,
+        } => {
+            let key =
+                PublicKeyHash::from_b58(&key).map_err(|_| ClientError::InvalidSessionKey(key))?;
+            let allowed_ops = allow
+                .into_iter()
+                .map(|name| {
+                    SessionOperation::all()
+                        .into_iter()
+                        .find(|operation| operation.name() == name)
+                        .ok_or(ClientError::InvalidOperation(name))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            Content::AuthorizeSessionKey(AuthorizeSessionKey {
+                key,
+                expiry_level,
+                allowed_ops,
+            })
+        }
+        Commands::Revoke { key } => {
+            let key =
+                PublicKeyHash::from_b58(&key).map_err(|_| ClientError::InvalidSessionKey(key))?;
+            Content::RevokeSessionKey(key)
+        }
+        Commands::Migrate { new_owner } => {
+            let new_owner = PublicKeyHash::from_b58(&new_owner)
+                .map_err(|_| ClientError::InvalidNewOwner(new_owner))?;
+            Content::MigrateAccount(MigrateAccount { new_owner })
+        }
+        Commands::Pause { mode } => {
+            let mode = [PauseMode::Reject, PauseMode::Defer]
+                .into_iter()
+                .find(|pause_mode| pause_mode.name() == mode)
+                .ok_or(ClientError::InvalidPauseMode(mode))?;
+            Content::Pause(mode)
+        }
+        Commands::Unpause => Content::Unpause,
+    };
+    let message = match account {
+        Some(account) => signer.sign_for(account, nonce, content)?,
+        None => signer.sign(nonce, content)?,
+    };
+    submit(&endpoint, message)
+}
+
+/// Submits the operation to the sequencer, or prints the octez-client command
+fn submit(endpoint: &Option<Endpoint>, message: Message) -> Result<(), ClientError> {
+    let operation = to_operation(&message)?;
+
+    let submitted = match endpoint {
+        Some(endpoint) => endpoint.submit_operation(&operation)?,
+        None => None,
+    };
+    match submitted {
+        Some(response) => println!("{}", response),
+        None => println!(
+            "octez-client send smart rollup message \"hex:[ \\\"{}\\\" ]\" from <account>",
+            hex::encode(operation)
+        ),
+    }
+
+    Ok(())
+}
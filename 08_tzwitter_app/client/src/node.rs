@@ -0,0 +1,200 @@
+//! Access to the state of the rollup and submission of the operations
+
+use crate::{Error, Nonce, PublicKeyHash};
+
+/// The node exposing the durable storage of the kernel
+pub enum Endpoint {
+    /// A sequencer, exposing GET /state/value and POST /operations
+    Sequencer(String),
+    /// A rollup node, exposing the durable storage of the PVM
+    RollupNode(String),
+}
+
+impl Endpoint {
+    /// Reads the value stored at the given path of the durable storage
+    pub fn read_value(&self, path: &str) -> Result<Option<Vec<u8>>, Error> {
+        match self {
+            Endpoint::Sequencer(url) => {
+                let url = format!("{}/state/value", url.trim_end_matches('/'));
+                let response = ureq::get(&url).query("path", path).call();
+                let body = match response {
+                    Ok(response) => response.into_string(),
+                    Err(ureq::Error::Status(404, _)) => return Ok(None),
+                    Err(err) => return Err(Error::Http(url, err.to_string())),
+                };
+                let body = body.map_err(|err| Error::Http(url, err.to_string()))?;
+                let value = hex::decode(body.trim()).map_err(|_| Error::InvalidNonce(body))?;
+                Ok(Some(value))
+            }
+            Endpoint::RollupNode(url) => {
+                let url = format!(
+                    "{}/global/block/head/durable/wasm_2_0_0/value",
+                    url.trim_end_matches('/')
+                );
+                let body = ureq::get(&url)
+                    .query("key", path)
+                    .call()
+                    .map_err(|err| Error::Http(url.clone(), err.to_string()))?
+                    .into_string()
+                    .map_err(|err| Error::Http(url, err.to_string()))?;
+                // The rollup node returns the hex encoded value as a json string, or null
+                match body.trim() {
+                    "null" => Ok(None),
+                    value => {
+                        let value = hex::decode(value.trim_matches('"'))
+                            .map_err(|_| Error::InvalidNonce(body.clone()))?;
+                        Ok(Some(value))
+                    }
+                }
+            }
+        }
+    }
+
+    /// Reads the nonce of the account at /accounts/{tz1...}/nonce
+    ///
+    /// An account without nonce has the default one
+    pub fn read_nonce(&self, public_key_hash: &PublicKeyHash) -> Result<Nonce, Error> {
+        let path = format!("/accounts/{}/nonce", public_key_hash.to_string());
+        match self.read_value(&path)? {
+            None => Ok(Nonce::default()),
+            Some(value) => {
+                let value: [u8; 8] = value
+                    .try_into()
+                    .map_err(|value| Error::InvalidNonce(hex::encode(value)))?;
+                Ok(Nonce(u64::from_be_bytes(value)))
+            }
+        }
+    }
+
+    /// Submits the operation to the sequencer
+    ///
+    /// The rollup node does not accept operations, they have to be sent with octez-client
+    pub fn submit_operation(&self, operation: &[u8]) -> Result<Option<String>, Error> {
+        match self {
+            Endpoint::Sequencer(url) => {
+                let url = format!("{}/operations", url.trim_end_matches('/'));
+                let body = format!("{{\"data\":\"{}\"}}", hex::encode(operation));
+                let response = ureq::post(&url)
+                    .set("Content-Type", "application/json")
+                    .send_string(&body)
+                    .map_err(|err| Error::Http(url.clone(), err.to_string()))?
+                    .into_string()
+                    .map_err(|err| Error::Http(url, err.to_string()))?;
+                Ok(Some(response))
+            }
+            Endpoint::RollupNode(_) => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::mpsc::{channel, Receiver};
+    use std::thread;
+
+    use super::Endpoint;
+    use crate::{Nonce, PublicKeyHash};
+
+    /// Starts a stub HTTP server answering one request
+    ///
+    /// Returns the url of the server and the received request (request line and body)
+    fn stub_server(status: u16, body: &'static str) -> (String, Receiver<(String, String)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let (sender, receiver) = channel();
+
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut content_length = 0;
+            loop {
+                let mut header = String::new();
+                reader.read_line(&mut header).unwrap();
+                let header = header.trim().to_lowercase();
+                if header.is_empty() {
+                    break;
+                }
+                if let Some(length) = header.strip_prefix("content-length:") {
+                    content_length = length.trim().parse().unwrap();
+                }
+            }
+            let mut request_body = vec![0; content_length];
+            reader.read_exact(&mut request_body).unwrap();
+
+            let mut stream = stream;
+            let response = format!(
+                "HTTP/1.1 {} STUB\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            stream.write_all(response.as_bytes()).unwrap();
+            let request_body = String::from_utf8(request_body).unwrap();
+            sender
+                .send((request_line.trim().to_string(), request_body))
+                .unwrap();
+        });
+
+        (url, receiver)
+    }
+
+    fn alice() -> PublicKeyHash {
+        PublicKeyHash::from_b58("tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv").unwrap()
+    }
+
+    #[test]
+    fn test_read_nonce_from_sequencer() {
+        let (url, request) = stub_server(200, "0000000000000003");
+        let nonce = Endpoint::Sequencer(url).read_nonce(&alice()).unwrap();
+        let (request_line, _) = request.recv().unwrap();
+
+        assert!(nonce == Nonce(3));
+        assert_eq!(
+            "GET /state/value?path=%2Faccounts%2Ftz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv%2Fnonce HTTP/1.1",
+            request_line
+        );
+    }
+
+    #[test]
+    fn test_read_missing_nonce_from_sequencer() {
+        let (url, _) = stub_server(404, "");
+        let nonce = Endpoint::Sequencer(url).read_nonce(&alice()).unwrap();
+        assert!(nonce == Nonce::default());
+    }
+
+    #[test]
+    fn test_read_nonce_from_rollup_node() {
+        let (url, request) = stub_server(200, "\"0000000000000005\"");
+        let nonce = Endpoint::RollupNode(url).read_nonce(&alice()).unwrap();
+        let (request_line, _) = request.recv().unwrap();
+
+        assert!(nonce == Nonce(5));
+        assert!(request_line
+            .starts_with("GET /global/block/head/durable/wasm_2_0_0/value?key=%2Faccounts%2F"));
+    }
+
+    #[test]
+    fn test_read_missing_nonce_from_rollup_node() {
+        let (url, _) = stub_server(200, "null");
+        let nonce = Endpoint::RollupNode(url).read_nonce(&alice()).unwrap();
+        assert!(nonce == Nonce::default());
+    }
+
+    #[test]
+    fn test_submit_operation_to_sequencer() {
+        let (url, request) = stub_server(200, "Operation submitted");
+        let response = Endpoint::Sequencer(url)
+            .submit_operation(&[0x74, 0x7b, 0x7d])
+            .unwrap();
+        let (request_line, body) = request.recv().unwrap();
+
+        assert_eq!(Some("Operation submitted".to_string()), response);
+        assert_eq!("POST /operations HTTP/1.1", request_line);
+        assert_eq!("{\"data\":\"747b7d\"}", body);
+    }
+}
@@ -18,10 +18,17 @@ which ligo > /dev/null || (echo "wasm-strip should be installed" && echo "https:
 
 # deploying the layer 1 contract
 MICHELSON=$(ligo compile contract smart_contract/dummy-fa2.jsligo)
-STORAGE=$(ligo compile storage smart_contract/dummy-fa2.jsligo initial_storage)
+ADMIN=$(octez-client show address $account_alias | grep Hash | awk '{ print $2}')
+STORAGE=$(ligo compile storage smart_contract/dummy-fa2.jsligo "initial_storage(\"$ADMIN\" as address)")
 
 export TZWITTER_L1_CONTRACT=$(octez-client originate contract tzwitter transferring 0 from $account_alias running "$MICHELSON" --init "$STORAGE" --burn-cap 1.0 --force | grep "New contract" | awk '{ print $3}')
 
+# deploying the registry of the state commitments
+MICHELSON=$(ligo compile contract smart_contract/registry.jsligo)
+STORAGE=$(ligo compile storage smart_contract/registry.jsligo initial_storage)
+
+TZWITTER_L1_REGISTRY=$(octez-client originate contract tzwitter-registry transferring 0 from $account_alias running "$MICHELSON" --init "$STORAGE" --burn-cap 1.0 --force | grep "New contract" | awk '{ print $3}')
+
 # Compiling the kernel
 cargo build --release --target wasm32-unknown-unknown --manifest-path kernel/Cargo.toml
 
@@ -57,10 +64,14 @@ SOR_ADDR=$(octez-client originate smart rollup from $account_alias \
   with kernel "${KERNEL_INSTALLER}" \
   --burn-cap 999 | grep "Address:" | awk '{print $2}')
 
+# The L1 contract sends the deposits to the rollup and only accepts its mints
+octez-client transfer 0 from $account_alias to tzwitter --entrypoint setRollup --arg "\"${SOR_ADDR}\"" --burn-cap 1.0
+
 # # Setting up the rollup data directory
 octez-smart-rollup-node-alpha init operator config for "${SOR_ADDR}" with operators "$account_alias" --data-dir rollup
 
 # # Print the function to run it
 echo Rollup Address: $SOR_ADDR
 echo Command to run to start your rollup:
-echo "octez-smart-rollup-node-alpha run operator for $SOR_ADDR with operators ${account_alias} --data-dir rollup"
\ No newline at end of file
+echo "octez-smart-rollup-node-alpha run operator for $SOR_ADDR with operators ${account_alias} --data-dir rollup"
+echo "To commit the state, set /config/registry to $TZWITTER_L1_REGISTRY and /config/commitment_period in the durable storage"
\ No newline at end of file
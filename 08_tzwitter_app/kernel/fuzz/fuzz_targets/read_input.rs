@@ -0,0 +1,37 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use tezos_data_encoding::enc::{BinResult, BinWriter};
+use tezos_smart_rollup::testing::prelude::MockHost;
+use tzwitter_kernel::constants::MAGIC_BYTE;
+use tzwitter_kernel::core::error::ReadInputError;
+use tzwitter_kernel::core::message::Input;
+use tzwitter_kernel::read_input;
+
+/// The payload of an external message
+struct Payload(Vec<u8>);
+
+impl BinWriter for Payload {
+    fn bin_write(&self, output: &mut Vec<u8>) -> BinResult {
+        output.extend_from_slice(&self.0);
+        Ok(())
+    }
+}
+
+// The data is sent as is, and behind the magic byte to reach the parsing of the messages
+fuzz_target!(|data: &[u8]| {
+    let mut host = MockHost::default();
+    host.add_external(Payload(data.to_vec()));
+    host.add_external(Payload([&[MAGIC_BYTE], data].concat()));
+
+    loop {
+        match read_input(&mut host) {
+            Ok((Input::Message(message), _)) => {
+                let _ = message.hash();
+            }
+            Ok(_) => {}
+            Err(ReadInputError::EndOfInbox | ReadInputError::Runtime(_)) => break,
+            Err(_) => {}
+        }
+    }
+});
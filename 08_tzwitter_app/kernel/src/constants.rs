@@ -6,3 +6,78 @@ pub const L1_TOKEN_CONTRACT_ADDRESS: &str = env!("TZWITTER_L1_CONTRACT");
 pub const L1_TOKEN_CONTRACT_ADDRESS: &str = "KT1RycYvM4EVs6BAXWEsGXaAaRqiMP53KT4w";
 
 pub const L1_TOKEN_CONTRACT_ENTRYPOINT: &str = "mint";
+
+pub const L1_REGISTRY_CONTRACT_ENTRYPOINT: &str = "commit";
+
+/// Default number of levels between two commitments of the state to the L1 registry
+///
+/// A period of 0 disables the commitments, they are also disabled until /config/registry is set
+pub const DEFAULT_COMMITMENT_PERIOD: u32 = 0;
+
+/// Number of levels after which a collect that has not been minted on the L1 can be retried
+///
+/// An outbox message can be executed as long as its level is one of the last
+/// max_active_outbox_levels (20_160) levels of the last cemented commitment.
+/// Cementing a commitment takes a challenge window (80_640 levels on mainnet).
+pub const COLLECT_TIMEOUT: u32 = 20_160 + 80_640;
+
+/// Default number of levels of a rate limit window
+///
+/// The rate limits are disabled until a window is set in /config/rate_limit/window
+pub const DEFAULT_RATE_LIMIT_WINDOW: u32 = 0;
+
+/// Default number of posts allowed per account during a rate limit window
+pub const DEFAULT_MAX_POSTS: u64 = 20;
+
+/// Default number of likes allowed per account during a rate limit window
+pub const DEFAULT_MAX_LIKES: u64 = 200;
+
+/// Posting a tweet is free by default
+pub const DEFAULT_POSTING_FEE: u64 = 0;
+
+/// The nonces are strictly ordered by default
+pub const DEFAULT_NONCE_WINDOW: u64 = 1;
+
+/// Maximum size of the nonce window, the used nonces are tracked in an u64 bitmap
+pub const MAX_NONCE_WINDOW: u64 = 64;
+
+/// Number of items migrated by the kernel before rebooting, when the storage schema changes
+pub const MIGRATION_BATCH_SIZE: u64 = 1_000;
+
+/// Content of a tweet hidden by the moderation
+pub const REDACTED_CONTENT: &str = "[redacted]";
+
+/// Maximum number of events appended to the event log during one level
+pub const MAX_EVENTS_PER_LEVEL: u64 = 1_000;
+
+/// Maximum number of deferred messages replayed at the start of a level, after a pause
+pub const MAX_REPLAYED_MESSAGES_PER_LEVEL: u64 = 100;
+
+/// Maximum number of messages waiting in the deferred queue during a pause
+pub const MAX_DEFERRED_MESSAGES: u64 = 10_000;
+
+/// Number of levels of the DAC pages: 3 levels of hashes pages and the content pages
+pub const MAX_DAC_LEVELS: usize = 4;
+
+/// Maximum size in bytes of a tweet body revealed from the preimages
+pub const MAX_TWEET_BODY_SIZE: usize = 32 * 1024;
+
+/// Maximum number of signers of a multisig account
+pub const MAX_SIGNERS: usize = 16;
+
+/// Number of levels during which a like counts in the trending tweets
+pub const TRENDING_WINDOW: u32 = 1_000;
+
+/// Number of tweets kept in the trending top
+pub const TRENDING_TOP_SIZE: usize = 10;
+
+/// Number of most liked tweets kept as candidates of the trending top
+///
+/// The candidates below the top replace the leaders whose likes leave the window
+pub const TRENDING_CANDIDATES: usize = 4 * TRENDING_TOP_SIZE;
+
+/// Maximum number of options of a poll
+pub const MAX_POLL_OPTIONS: usize = 10;
+
+/// Height of the merkle tree over the tweets, it commits to 2^32 tweets
+pub const MERKLE_TREE_DEPTH: usize = 32;
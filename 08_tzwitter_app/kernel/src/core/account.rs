@@ -20,4 +20,9 @@ impl Account {
             ..self
         }
     }
+
+    /// Returns the same account with the given nonce
+    pub fn with_nonce(self, nonce: Nonce) -> Account {
+        Account { nonce, ..self }
+    }
 }
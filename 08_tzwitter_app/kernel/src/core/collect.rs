@@ -0,0 +1,30 @@
+use crate::constants::COLLECT_TIMEOUT;
+
+/// The different states of a collect
+///
+/// A collect is requested by the owner of the tweet,
+/// then its outbox message is written,
+/// then the L1 contract confirms the mint with a transfer to the rollup
+#[derive(Debug, PartialEq, Eq)]
+pub enum CollectState {
+    /// The tweet has not been collected
+    NotCollected,
+    /// The outbox message is waiting in the outbox queue
+    Queued,
+    /// The outbox message has been written at the given outbox level and index
+    Written { level: u32, index: u64 },
+    /// The L1 contract has minted the tweet
+    Finalized,
+}
+
+impl CollectState {
+    /// Returns true if the outbox message can no longer be executed at the given level
+    pub fn is_expired(&self, current_level: u32) -> bool {
+        match self {
+            CollectState::Written { level, .. } => {
+                current_level > level.saturating_add(COLLECT_TIMEOUT)
+            }
+            _ => false,
+        }
+    }
+}
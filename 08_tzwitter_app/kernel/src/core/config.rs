@@ -0,0 +1,31 @@
+/// The rate limits applied to every account
+///
+/// A window of 0 levels disables the rate limits
+pub struct RateLimit {
+    /// Number of levels of a window
+    pub window: u32,
+    /// Maximum number of posts of an account during a window
+    pub max_posts: u64,
+    /// Maximum number of likes of an account during a window
+    pub max_likes: u64,
+}
+
+/// The configuration of the kernel
+///
+/// It can be set in the durable storage by the installer,
+/// otherwise the default values are used
+pub struct Config {
+    pub rate_limit: RateLimit,
+    /// Fee in mutez debited from the balance of an account for each post
+    pub posting_fee: u64,
+    /// Number of nonces accepted ahead of the nonce of an account
+    ///
+    /// With a window of 1, the nonces have to be used in order
+    pub nonce_window: u64,
+    /// Number of levels between two commitments of the state to the L1 registry
+    pub commitment_period: u32,
+    /// Address of the L1 registry of the state commitments
+    ///
+    /// There is no default registry, the commitments are disabled without it
+    pub registry: Option<String>,
+}
@@ -11,6 +11,8 @@ pub enum ReadInputError {
     SerdeJson(serde_json_wasm::de::Error),
     /// There is an error runtime
     Runtime(tezos_smart_rollup::host::RuntimeError),
+    /// The payload of a transfer from the L1 contract is not a tweet id
+    InvalidTransferPayload,
 }
 
 /// Represents all the error of the kernel
@@ -32,6 +34,38 @@ pub enum Error {
     BigIntError,
     BinError(tezos_data_encoding::enc::BinError),
     EntrypointError(tezos_smart_rollup::types::EntrypointError),
+    TweetNotCollected,
+    CollectNotExpired,
+    PostQuotaExceeded,
+    LikeQuotaExceeded,
+    InsufficientBalance,
+    NotAdmin,
+    AccountBanned,
+    InvalidRootHash,
+    TweetBodyTooLarge,
+    PreimageNotRevealed,
+    EventLogFull,
+    SessionKeyNotAuthorized,
+    SessionKeyExpired,
+    OperationNotAllowed,
+    AccountAlreadyExists,
+    AccountMigrated,
+    InvalidSigners,
+    NotEnoughSignatures,
+    NotAuthor,
+    TweetNotEditable,
+    InvalidPoll,
+    NotAPoll,
+    PollClosed,
+    InvalidPollOption,
+    AlreadyVoted,
+    TweetAlreadyRetweeted,
+    UnknownSchemaVersion,
+    UnknownTweetOwner,
+    KernelPaused,
+    MessageDeferred,
+    DeferredQueueFull,
+    MerkleTreeFull,
 }
 
 impl ToString for Error {
@@ -52,6 +86,44 @@ impl ToString for Error {
             Error::BigIntError => "Cannot deserialize big int",
             Error::BinError(_) => "Cannot serialize michelson to binary",
             Error::EntrypointError(_) => "Not a correct entrypoint",
+            Error::TweetNotCollected => "The tweet is not being collected",
+            Error::CollectNotExpired => "The outbox message of the collect can still be executed",
+            Error::PostQuotaExceeded => "The account has reached its quota of posts",
+            Error::LikeQuotaExceeded => "The account has reached its quota of likes",
+            Error::InsufficientBalance => "The balance of the account cannot pay the posting fee",
+            Error::NotAdmin => "Only the admin can moderate the content",
+            Error::AccountBanned => "The account is banned",
+            Error::InvalidRootHash => "The root hash is not a valid preimage hash",
+            Error::TweetBodyTooLarge => "The revealed body of the tweet is too large",
+            Error::PreimageNotRevealed => "Cannot reveal the body of the tweet",
+            Error::EventLogFull => "Too many operations in this level, retry at the next one",
+            Error::SessionKeyNotAuthorized => "The key is not a session key of the account",
+            Error::SessionKeyExpired => "The session key has expired",
+            Error::OperationNotAllowed => "The session key is not allowed to sign this operation",
+            Error::AccountAlreadyExists => "The new owner already has an account",
+            Error::AccountMigrated => "The account has been migrated to a new owner",
+            Error::InvalidSigners => "The threshold cannot be reached by the signers",
+            Error::NotEnoughSignatures => "The message is not signed by enough signers",
+            Error::NotAuthor => "Only the author of the tweet can edit it",
+            Error::TweetNotEditable => {
+                "Hidden tweets, polls and tweets posted by reference cannot be edited"
+            }
+            Error::InvalidPoll => "A poll needs 2 to 10 options and a closing level in the future",
+            Error::NotAPoll => "The tweet is not a poll",
+            Error::PollClosed => "The poll is closed",
+            Error::InvalidPollOption => "The option does not exist",
+            Error::AlreadyVoted => "You can only vote once",
+            Error::TweetAlreadyRetweeted => "The tweet has already been retweeted by this account",
+            Error::UnknownSchemaVersion => "The durable storage has been written by a newer kernel",
+            Error::UnknownTweetOwner => "The owner of a transferred tweet is unknown",
+            Error::KernelPaused => "The kernel is paused, retry later",
+            Error::MessageDeferred => {
+                "The kernel is paused, the message will be processed after the pause"
+            }
+            Error::DeferredQueueFull => {
+                "Too many messages are waiting for the end of the pause, retry later"
+            }
+            Error::MerkleTreeFull => "The merkle tree of the tweets is full",
         };
         err.to_string()
     }
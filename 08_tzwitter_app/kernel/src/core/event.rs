@@ -0,0 +1,367 @@
+use tezos_crypto_rs::hash::ContractTz1Hash;
+
+use crate::core::error::*;
+use crate::core::public_key_hash::PublicKeyHash;
+
+/// Events appended to /events/{level}/{index} by every successful operation
+///
+/// Indexers can follow this prefix instead of diffing the durable storage
+pub enum Event {
+    TweetPosted {
+        tweet_id: u64,
+        author: PublicKeyHash,
+    },
+    TweetLiked {
+        tweet_id: u64,
+        account: PublicKeyHash,
+    },
+    TweetTransferred {
+        tweet_id: u64,
+        from: PublicKeyHash,
+        to: PublicKeyHash,
+    },
+    TweetCollected {
+        tweet_id: u64,
+        owner: PublicKeyHash,
+    },
+    TweetReclaimed {
+        tweet_id: u64,
+        owner: PublicKeyHash,
+    },
+    TweetHidden {
+        tweet_id: u64,
+    },
+    AccountBanned(PublicKeyHash),
+    AccountUnbanned(PublicKeyHash),
+    SessionKeyAuthorized {
+        account: PublicKeyHash,
+        key: PublicKeyHash,
+    },
+    SessionKeyRevoked {
+        account: PublicKeyHash,
+        key: PublicKeyHash,
+    },
+    AccountMigrated {
+        from: PublicKeyHash,
+        to: PublicKeyHash,
+    },
+    SignersSet {
+        account: PublicKeyHash,
+        threshold: u32,
+    },
+    TweetEdited {
+        tweet_id: u64,
+    },
+    PollVoted {
+        tweet_id: u64,
+        account: PublicKeyHash,
+        option: u32,
+    },
+    PollClosed {
+        tweet_id: u64,
+    },
+    TweetRetweeted {
+        tweet_id: u64,
+        account: PublicKeyHash,
+    },
+    TweetQuoted {
+        tweet_id: u64,
+        quoted: u64,
+        author: PublicKeyHash,
+    },
+    KernelPaused,
+    KernelUnpaused,
+}
+
+/// Tag of the tz1 public key hashes, as in the binary encoding of Tezos
+const TZ1_TAG: u8 = 0x00;
+
+fn encode_public_key_hash(output: &mut Vec<u8>, public_key_hash: &PublicKeyHash) {
+    match public_key_hash {
+        PublicKeyHash::Tz1(tz1) => {
+            output.push(TZ1_TAG);
+            output.extend_from_slice(tz1.as_ref());
+        }
+    }
+}
+
+/// Reads the values of an encoded event, or of an other binary record of the kernel
+pub(crate) struct Decoder<'a> {
+    pub(crate) bytes: &'a [u8],
+}
+
+impl<'a> Decoder<'a> {
+    fn take(&mut self, size: usize) -> Result<&'a [u8]> {
+        if self.bytes.len() < size {
+            return Err(Error::StateDeserializarion);
+        }
+        let (value, rest) = self.bytes.split_at(size);
+        self.bytes = rest;
+        Ok(value)
+    }
+
+    pub(crate) fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let bytes = self.take(4)?;
+        let bytes: [u8; 4] = bytes.try_into().map_err(|_| Error::StateDeserializarion)?;
+        Ok(u32::from_be_bytes(bytes))
+    }
+
+    pub(crate) fn u64(&mut self) -> Result<u64> {
+        let bytes = self.take(8)?;
+        let bytes: [u8; 8] = bytes.try_into().map_err(|_| Error::StateDeserializarion)?;
+        Ok(u64::from_be_bytes(bytes))
+    }
+
+    fn public_key_hash(&mut self) -> Result<PublicKeyHash> {
+        match self.u8()? {
+            TZ1_TAG => {
+                let hash = self.take(20)?;
+                let tz1 =
+                    ContractTz1Hash::try_from(hash).map_err(|_| Error::StateDeserializarion)?;
+                Ok(PublicKeyHash::Tz1(tz1))
+            }
+            _ => Err(Error::StateDeserializarion),
+        }
+    }
+}
+
+impl Event {
+    /// Binary encoding of the event
+    ///
+    /// One byte for the tag of the event, then its fields:
+    /// the ids as u64, the thresholds and the options as u32 in big endian, the tz1 as 21 bytes
+    pub fn encode(&self) -> Vec<u8> {
+        let mut output = Vec::new();
+        match self {
+            Event::TweetPosted { tweet_id, author } => {
+                output.push(0x00);
+                output.extend_from_slice(&tweet_id.to_be_bytes());
+                encode_public_key_hash(&mut output, author);
+            }
+            Event::TweetLiked { tweet_id, account } => {
+                output.push(0x01);
+                output.extend_from_slice(&tweet_id.to_be_bytes());
+                encode_public_key_hash(&mut output, account);
+            }
+            Event::TweetTransferred { tweet_id, from, to } => {
+                output.push(0x02);
+                output.extend_from_slice(&tweet_id.to_be_bytes());
+                encode_public_key_hash(&mut output, from);
+                encode_public_key_hash(&mut output, to);
+            }
+            Event::TweetCollected { tweet_id, owner } => {
+                output.push(0x03);
+                output.extend_from_slice(&tweet_id.to_be_bytes());
+                encode_public_key_hash(&mut output, owner);
+            }
+            Event::TweetReclaimed { tweet_id, owner } => {
+                output.push(0x04);
+                output.extend_from_slice(&tweet_id.to_be_bytes());
+                encode_public_key_hash(&mut output, owner);
+            }
+            Event::TweetHidden { tweet_id } => {
+                output.push(0x05);
+                output.extend_from_slice(&tweet_id.to_be_bytes());
+            }
+            Event::AccountBanned(public_key_hash) => {
+                output.push(0x06);
+                encode_public_key_hash(&mut output, public_key_hash);
+            }
+            Event::AccountUnbanned(public_key_hash) => {
+                output.push(0x07);
+                encode_public_key_hash(&mut output, public_key_hash);
+            }
+            Event::SessionKeyAuthorized { account, key } => {
+                output.push(0x08);
+                encode_public_key_hash(&mut output, account);
+                encode_public_key_hash(&mut output, key);
+            }
+            Event::SessionKeyRevoked { account, key } => {
+                output.push(0x09);
+                encode_public_key_hash(&mut output, account);
+                encode_public_key_hash(&mut output, key);
+            }
+            Event::AccountMigrated { from, to } => {
+                output.push(0x0A);
+                encode_public_key_hash(&mut output, from);
+                encode_public_key_hash(&mut output, to);
+            }
+            Event::SignersSet { account, threshold } => {
+                output.push(0x0B);
+                encode_public_key_hash(&mut output, account);
+                output.extend_from_slice(&threshold.to_be_bytes());
+            }
+            Event::TweetEdited { tweet_id } => {
+                output.push(0x0C);
+                output.extend_from_slice(&tweet_id.to_be_bytes());
+            }
+            Event::PollVoted {
+                tweet_id,
+                account,
+                option,
+            } => {
+                output.push(0x0D);
+                output.extend_from_slice(&tweet_id.to_be_bytes());
+                encode_public_key_hash(&mut output, account);
+                output.extend_from_slice(&option.to_be_bytes());
+            }
+            Event::PollClosed { tweet_id } => {
+                output.push(0x0E);
+                output.extend_from_slice(&tweet_id.to_be_bytes());
+            }
+            Event::TweetRetweeted { tweet_id, account } => {
+                output.push(0x0F);
+                output.extend_from_slice(&tweet_id.to_be_bytes());
+                encode_public_key_hash(&mut output, account);
+            }
+            Event::TweetQuoted {
+                tweet_id,
+                quoted,
+                author,
+            } => {
+                output.push(0x10);
+                output.extend_from_slice(&tweet_id.to_be_bytes());
+                output.extend_from_slice(&quoted.to_be_bytes());
+                encode_public_key_hash(&mut output, author);
+            }
+            Event::KernelPaused => output.push(0x11),
+            Event::KernelUnpaused => output.push(0x12),
+        }
+        output
+    }
+
+    /// Decode an event encoded by Event::encode
+    pub fn decode(bytes: &[u8]) -> Result<Event> {
+        let mut decoder = Decoder { bytes };
+        let event = match decoder.u8()? {
+            0x00 => Event::TweetPosted {
+                tweet_id: decoder.u64()?,
+                author: decoder.public_key_hash()?,
+            },
+            0x01 => Event::TweetLiked {
+                tweet_id: decoder.u64()?,
+                account: decoder.public_key_hash()?,
+            },
+            0x02 => Event::TweetTransferred {
+                tweet_id: decoder.u64()?,
+                from: decoder.public_key_hash()?,
+                to: decoder.public_key_hash()?,
+            },
+            0x03 => Event::TweetCollected {
+                tweet_id: decoder.u64()?,
+                owner: decoder.public_key_hash()?,
+            },
+            0x04 => Event::TweetReclaimed {
+                tweet_id: decoder.u64()?,
+                owner: decoder.public_key_hash()?,
+            },
+            0x05 => Event::TweetHidden {
+                tweet_id: decoder.u64()?,
+            },
+            0x06 => Event::AccountBanned(decoder.public_key_hash()?),
+            0x07 => Event::AccountUnbanned(decoder.public_key_hash()?),
+            0x08 => Event::SessionKeyAuthorized {
+                account: decoder.public_key_hash()?,
+                key: decoder.public_key_hash()?,
+            },
+            0x09 => Event::SessionKeyRevoked {
+                account: decoder.public_key_hash()?,
+                key: decoder.public_key_hash()?,
+            },
+            0x0A => Event::AccountMigrated {
+                from: decoder.public_key_hash()?,
+                to: decoder.public_key_hash()?,
+            },
+            0x0B => Event::SignersSet {
+                account: decoder.public_key_hash()?,
+                threshold: decoder.u32()?,
+            },
+            0x0C => Event::TweetEdited {
+                tweet_id: decoder.u64()?,
+            },
+            0x0D => Event::PollVoted {
+                tweet_id: decoder.u64()?,
+                account: decoder.public_key_hash()?,
+                option: decoder.u32()?,
+            },
+            0x0E => Event::PollClosed {
+                tweet_id: decoder.u64()?,
+            },
+            0x0F => Event::TweetRetweeted {
+                tweet_id: decoder.u64()?,
+                account: decoder.public_key_hash()?,
+            },
+            0x10 => Event::TweetQuoted {
+                tweet_id: decoder.u64()?,
+                quoted: decoder.u64()?,
+                author: decoder.public_key_hash()?,
+            },
+            0x11 => Event::KernelPaused,
+            0x12 => Event::KernelUnpaused,
+            _ => return Err(Error::StateDeserializarion),
+        };
+        match decoder.bytes {
+            [] => Ok(event),
+            _ => Err(Error::StateDeserializarion),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Event;
+    use crate::core::public_key_hash::PublicKeyHash;
+
+    #[test]
+    fn test_transferred_roundtrip() {
+        let from = PublicKeyHash::from_b58("tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv").unwrap();
+        let to = PublicKeyHash::from_b58("tz1TGu6TN5GSez2ndXXeDX6LgUDvLzPLqgYV").unwrap();
+        let event = Event::TweetTransferred {
+            tweet_id: 3,
+            from,
+            to,
+        };
+
+        let bytes = event.encode();
+        assert_eq!(1 + 8 + 21 + 21, bytes.len());
+
+        match Event::decode(&bytes).unwrap() {
+            Event::TweetTransferred { tweet_id, from, to } => {
+                assert_eq!(3, tweet_id);
+                assert_eq!("tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv", from.to_string());
+                assert_eq!("tz1TGu6TN5GSez2ndXXeDX6LgUDvLzPLqgYV", to.to_string());
+            }
+            _ => panic!("Wrong event"),
+        }
+    }
+
+    #[test]
+    fn test_signers_set_roundtrip() {
+        let account = PublicKeyHash::from_b58("tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv").unwrap();
+        let bytes = Event::SignersSet {
+            account,
+            threshold: 2,
+        }
+        .encode();
+        assert_eq!(1 + 21 + 4, bytes.len());
+
+        match Event::decode(&bytes).unwrap() {
+            Event::SignersSet { threshold, .. } => assert_eq!(2, threshold),
+            _ => panic!("Wrong event"),
+        }
+    }
+
+    #[test]
+    fn test_decode_invalid_event() {
+        assert!(Event::decode(&[0xFF]).is_err());
+        assert!(Event::decode(&[0x05, 0x00]).is_err());
+
+        let mut bytes = Event::TweetHidden { tweet_id: 1 }.encode();
+        bytes.push(0x00);
+        assert!(Event::decode(&bytes).is_err());
+    }
+}
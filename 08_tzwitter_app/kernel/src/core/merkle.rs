@@ -0,0 +1,154 @@
+use serde::{Deserialize, Serialize};
+
+use crate::constants::MERKLE_TREE_DEPTH;
+use crate::core::error::*;
+use crate::core::hash::Blake2b;
+use crate::core::public_key_hash::PublicKeyHash;
+use crate::core::tweet::Tweet;
+
+/// A node of the merkle tree of the tweets
+pub type Node = [u8; 32];
+
+/// Prefixes of the hashed data, a leaf cannot be taken for an inner node
+const LEAF_TAG: u8 = 0x00;
+const NODE_TAG: u8 = 0x01;
+
+fn digest(data: &[u8]) -> Node {
+    let mut node = [0; 32];
+    node.copy_from_slice(Blake2b::from(data).as_ref());
+    node
+}
+
+/// Decode a node read from the durable storage
+pub fn decode_node(bytes: &[u8]) -> Result<Node> {
+    bytes.try_into().map_err(|_| Error::StateDeserializarion)
+}
+
+/// Hash of a tweet record, the leaf of the tweet in the tree
+///
+/// The leaf commits to the id, the likes, the retweets, the author, the owner and the content of the tweet
+/// A tweet without known owner commits to an empty owner
+pub fn leaf(tweet_id: &u64, tweet: &Tweet, owner: Option<&PublicKeyHash>) -> Node {
+    let author = tweet.author.to_string();
+    let owner = owner.map(|owner| owner.to_string()).unwrap_or_default();
+    let mut data = vec![LEAF_TAG];
+    data.extend_from_slice(&tweet_id.to_be_bytes());
+    data.extend_from_slice(&tweet.likes.to_be_bytes());
+    data.extend_from_slice(&tweet.retweets.to_be_bytes());
+    for field in [
+        author.as_bytes(),
+        owner.as_bytes(),
+        tweet.content.as_bytes(),
+    ] {
+        data.extend_from_slice(&(field.len() as u32).to_be_bytes());
+        data.extend_from_slice(field);
+    }
+    digest(&data)
+}
+
+/// Hash of an inner node of the tree
+pub fn node(left: &Node, right: &Node) -> Node {
+    let mut data = Vec::with_capacity(65);
+    data.push(NODE_TAG);
+    data.extend_from_slice(left);
+    data.extend_from_slice(right);
+    digest(&data)
+}
+
+/// The roots of the empty subtrees, from the leaves to the root
+///
+/// An absent leaf is 32 zero bytes
+pub fn empty_nodes() -> Vec<Node> {
+    let mut nodes = vec![[0; 32]];
+    for height in 0..MERKLE_TREE_DEPTH {
+        let empty = nodes[height];
+        nodes.push(node(&empty, &empty));
+    }
+    nodes
+}
+
+/// Compute the root of a tree from a leaf and its siblings, from the leaves to the root
+pub fn root_from_path(index: u64, leaf: Node, siblings: &[Node]) -> Node {
+    siblings
+        .iter()
+        .enumerate()
+        .fold(leaf, |hash, (height, sibling)| {
+            match (index >> height) & 1 {
+                0 => node(&hash, sibling),
+                _ => node(sibling, &hash),
+            }
+        })
+}
+
+/// Proof that a tweet is committed by the merkle root of the tweets
+#[derive(Serialize, Deserialize)]
+pub struct TweetProof {
+    pub tweet_id: u64,
+    pub tweet: Tweet,
+    pub owner: Option<PublicKeyHash>,
+    /// The siblings of the path of the tweet, from the leaves to the root
+    pub siblings: Vec<Node>,
+}
+
+impl TweetProof {
+    /// Compute the root committing to the tweet of the proof
+    pub fn root(&self) -> Node {
+        let leaf = leaf(&self.tweet_id, &self.tweet, self.owner.as_ref());
+        root_from_path(self.tweet_id, leaf, &self.siblings)
+    }
+
+    /// Checks that the tweet of the proof is committed by the given root
+    pub fn verify(&self, root: &Node) -> bool {
+        self.siblings.len() == MERKLE_TREE_DEPTH && &self.root() == root
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{empty_nodes, leaf, node, TweetProof};
+    use crate::constants::MERKLE_TREE_DEPTH;
+    use crate::core::message::PostTweet;
+    use crate::core::public_key_hash::PublicKeyHash;
+    use crate::core::tweet::Tweet;
+
+    fn tweet(content: &str) -> Tweet {
+        let author = PublicKeyHash::from_b58("tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv").unwrap();
+        Tweet::from(PostTweet {
+            author,
+            content: content.to_string(),
+        })
+    }
+
+    #[test]
+    fn test_proof_of_two_tweets() {
+        let empty = empty_nodes();
+        let first = leaf(&0, &tweet("Hello"), None);
+        let second = leaf(&1, &tweet("World"), None);
+        let root = (1..MERKLE_TREE_DEPTH).fold(node(&first, &second), |hash, height| {
+            node(&hash, &empty[height])
+        });
+
+        let mut siblings = vec![first];
+        siblings.extend_from_slice(&empty[1..MERKLE_TREE_DEPTH]);
+        let proof = TweetProof {
+            tweet_id: 1,
+            tweet: tweet("World"),
+            owner: None,
+            siblings,
+        };
+        assert!(proof.verify(&root));
+
+        let forged = TweetProof {
+            tweet: tweet("Forged").like(),
+            ..proof
+        };
+        assert!(!forged.verify(&root));
+    }
+
+    #[test]
+    fn test_empty_tree() {
+        let empty = empty_nodes();
+        assert_eq!(MERKLE_TREE_DEPTH + 1, empty.len());
+        assert_eq!(node(&empty[0], &empty[0]), empty[1]);
+    }
+}
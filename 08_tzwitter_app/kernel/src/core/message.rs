@@ -1,51 +1,172 @@
 use crate::core::hash::Blake2b;
+use crate::core::multisig::Cosignature;
 use crate::core::nonce::Nonce;
+use crate::core::pause::PauseMode;
 use crate::core::public_key::PublicKey;
 use crate::core::public_key_hash::PublicKeyHash;
+use crate::core::session_key::SessionOperation;
 use crate::core::signature::Signature;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
 pub struct PostTweet {
     pub author: PublicKeyHash, // define a new type for public key
     pub content: String,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
+pub struct PostTweetRef {
+    pub author: PublicKeyHash,
+    /// Hex encoded root hash of the preimages of the body
+    pub root_hash: String,
+}
+
+#[derive(Deserialize, Serialize)]
 pub struct Transfer {
-    pub tweet_id: u64,
     pub destination: PublicKeyHash,
+    pub tweet_id: u64,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct AuthorizeSessionKey {
+    /// The public key hash of the session key
+    pub key: PublicKeyHash,
+    /// The last level at which the session key can be used
+    pub expiry_level: u32,
+    pub allowed_ops: Vec<SessionOperation>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct MigrateAccount {
+    /// The public key hash of the key controlling the account from now on
+    pub new_owner: PublicKeyHash,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct SetSigners {
+    /// The number of signatures required to send a message for the account
+    pub threshold: u32,
+    pub signers: Vec<PublicKeyHash>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct EditTweet {
+    pub tweet_id: u64,
+    /// The new content of the tweet
+    pub content: String,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
+pub struct Quote {
+    /// The id of the quoted tweet
+    pub tweet_id: u64,
+    pub content: String,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct PostPoll {
+    pub content: String,
+    pub options: Vec<String>,
+    /// The last level at which the accounts can vote
+    pub closes_at_level: u32,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct Vote {
+    pub tweet_id: u64,
+    /// The index of the chosen option
+    pub option: u32,
+}
+
+#[derive(Deserialize, Serialize)]
 pub enum Content {
     PostTweet(PostTweet),
+    PostTweetRef(PostTweetRef),
     LikeTweet(u64),
     Transfer(Transfer),
     Collect(u64),
+    Reclaim(u64),
+    HideTweet(u64),
+    BanAccount(PublicKeyHash),
+    UnbanAccount(PublicKeyHash),
+    AuthorizeSessionKey(AuthorizeSessionKey),
+    RevokeSessionKey(PublicKeyHash),
+    MigrateAccount(MigrateAccount),
+    SetSigners(SetSigners),
+    EditTweet(EditTweet),
+    PostPoll(PostPoll),
+    Vote(Vote),
+    Retweet(u64),
+    Quote(Quote),
+    Pause(PauseMode),
+    Unpause,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
 pub struct Inner {
     nonce: Nonce,
     pub content: Content,
+    /// The account on behalf of which a session key signs the message
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub account: Option<PublicKeyHash>,
 }
 
 impl Inner {
+    /// Creates the inner of a message
+    pub fn new(nonce: Nonce, content: Content) -> Self {
+        Inner {
+            nonce,
+            content,
+            account: None,
+        }
+    }
+
+    /// Creates the inner of a message signed by a session key of the account
+    pub fn delegated(nonce: Nonce, content: Content, account: PublicKeyHash) -> Self {
+        Inner {
+            nonce,
+            content,
+            account: Some(account),
+        }
+    }
+
     /// Returns the nonce of the inner
     pub fn nonce(&self) -> &Nonce {
         &self.nonce
     }
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
 pub struct Message {
     pkey: PublicKey,
     signature: Signature,
     pub inner: Inner,
+    /// The signatures of the other signers of a multisig account
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    cosignatures: Vec<Cosignature>,
 }
 
 impl Message {
+    /// Creates a message signed by the given public key
+    pub fn new(pkey: PublicKey, signature: Signature, inner: Inner) -> Self {
+        Message {
+            pkey,
+            signature,
+            inner,
+            cosignatures: Vec::new(),
+        }
+    }
+
+    /// Adds the signature of another signer of a multisig account
+    pub fn add_cosignature(&mut self, cosignature: Cosignature) {
+        self.cosignatures.push(cosignature);
+    }
+
+    /// Returns the signatures of the other signers
+    pub fn cosignatures(&self) -> &[Cosignature] {
+        &self.cosignatures
+    }
+
     /// Returns the public key of the message
     pub fn public_key(&self) -> &PublicKey {
         &self.pkey
@@ -67,35 +188,130 @@ impl Message {
     }
 }
 
+/// Represents the inputs of the inbox handled by the kernel
+pub enum Input {
+    /// A message signed by a user
+    Message(Message),
+    /// The start of a new level
+    StartOfLevel,
+    /// A transfer from the L1 token contract, confirming that the tweet has been minted
+    Minted(u64),
+    /// A transfer from the L1 token contract, funding the balance of an account
+    Deposit {
+        public_key_hash: PublicKeyHash,
+        amount: u64,
+    },
+}
+
 impl Inner {
     /// Hash of the message
     /// This hash is what the client should signed
     pub fn hash(&self) -> Blake2b {
         // The nonce, and content should be hashed
-        let Inner { nonce, content } = &self;
-        match &content {
+        let Inner {
+            nonce,
+            content,
+            account,
+        } = &self;
+        let string = match &content {
             Content::PostTweet(PostTweet { author, content }) => {
-                let string = format!("{}{}{}", nonce.to_string(), author.to_string(), content);
-                Blake2b::from(string.as_bytes())
+                format!("{}{}{}", nonce.to_string(), author.to_string(), content)
+            }
+            Content::PostTweetRef(PostTweetRef { author, root_hash }) => format!(
+                "{}PostTweetRef{}{}",
+                nonce.to_string(),
+                author.to_string(),
+                root_hash
+            ),
+            Content::LikeTweet(tweet_id) => format!("{}{}", nonce.to_string(), tweet_id),
+            Content::Transfer(transfer) => format!(
+                "{}{}{}",
+                nonce.to_string(),
+                transfer.destination.to_string(),
+                transfer.tweet_id
+            ),
+            Content::Collect(tweet_id) => format!("{}{}", nonce.to_string(), tweet_id),
+            Content::Reclaim(tweet_id) => format!("{}Reclaim{}", nonce.to_string(), tweet_id),
+            Content::HideTweet(tweet_id) => format!("{}HideTweet{}", nonce.to_string(), tweet_id),
+            Content::BanAccount(public_key_hash) => format!(
+                "{}BanAccount{}",
+                nonce.to_string(),
+                public_key_hash.to_string()
+            ),
+            Content::UnbanAccount(public_key_hash) => format!(
+                "{}UnbanAccount{}",
+                nonce.to_string(),
+                public_key_hash.to_string()
+            ),
+            Content::AuthorizeSessionKey(AuthorizeSessionKey {
+                key,
+                expiry_level,
+                allowed_ops,
+            }) => {
+                let allowed_ops: Vec<&str> = allowed_ops.iter().map(|op| op.name()).collect();
+                format!(
+                    "{}AuthorizeSessionKey{}{}{}",
+                    nonce.to_string(),
+                    key.to_string(),
+                    expiry_level,
+                    allowed_ops.join(",")
+                )
+            }
+            Content::RevokeSessionKey(key) => {
+                format!("{}RevokeSessionKey{}", nonce.to_string(), key.to_string())
             }
-            Content::LikeTweet(tweet_id) => {
-                let string = format!("{}{}", nonce.to_string(), tweet_id);
-                Blake2b::from(string.as_bytes())
+            Content::MigrateAccount(MigrateAccount { new_owner }) => format!(
+                "{}MigrateAccount{}",
+                nonce.to_string(),
+                new_owner.to_string()
+            ),
+            Content::EditTweet(EditTweet { tweet_id, content }) => {
+                format!("{}EditTweet{}:{}", nonce.to_string(), tweet_id, content)
             }
-            Content::Transfer(transfer) => {
-                let string = format!(
-                    "{}{}{}",
+            Content::PostPoll(PostPoll {
+                content,
+                options,
+                closes_at_level,
+            }) => {
+                // The strings are prefixed by their length, the options could contain any separator
+                let options: Vec<String> = options
+                    .iter()
+                    .map(|option| format!("{}:{}", option.len(), option))
+                    .collect();
+                format!(
+                    "{}PostPoll{}:{}:{}{}",
                     nonce.to_string(),
-                    transfer.destination.to_string(),
-                    transfer.tweet_id
-                );
-                Blake2b::from(string.as_bytes())
+                    closes_at_level,
+                    content.len(),
+                    content,
+                    options.concat()
+                )
             }
-            Content::Collect(tweet_id) => {
-                let string = format!("{}{}", nonce.to_string(), tweet_id);
-                Blake2b::from(string.as_bytes())
+            Content::Vote(Vote { tweet_id, option }) => {
+                format!("{}Vote{}:{}", nonce.to_string(), tweet_id, option)
             }
-        }
+            Content::Pause(mode) => format!("{}Pause{}", nonce.to_string(), mode.name()),
+            Content::Unpause => format!("{}Unpause", nonce.to_string()),
+            Content::Retweet(tweet_id) => format!("{}Retweet{}", nonce.to_string(), tweet_id),
+            Content::Quote(Quote { tweet_id, content }) => {
+                format!("{}Quote{}:{}", nonce.to_string(), tweet_id, content)
+            }
+            Content::SetSigners(SetSigners { threshold, signers }) => {
+                let signers: Vec<String> = signers.iter().map(|s| s.to_string()).collect();
+                format!(
+                    "{}SetSigners{}{}",
+                    nonce.to_string(),
+                    threshold,
+                    signers.join(",")
+                )
+            }
+        };
+        // A message signed by a session key is bound to the account it acts for
+        let string = match account {
+            None => string,
+            Some(account) => format!("{}{}", account.to_string(), string),
+        };
+        Blake2b::from(string.as_bytes())
     }
 }
 
@@ -103,7 +319,7 @@ impl Inner {
 mod tests {
     use std::num::ParseIntError;
 
-    use super::{Content, Inner, PostTweet};
+    use super::{Content, EditTweet, Inner, PostTweet};
     use crate::core::{message::Message, nonce::Nonce, public_key::PublicKey};
 
     #[test]
@@ -119,12 +335,29 @@ mod tests {
                 author,
                 content: "Hello world".to_string(),
             }),
+            account: None,
         };
 
         let hash = inner.hash();
         assert_eq!(expected, hash.to_string());
     }
 
+    #[test]
+    fn test_edit_tweet_hash_separates_id_and_content() {
+        let edit = |tweet_id: u64, content: &str| Inner {
+            nonce: Nonce::default().next(),
+            content: Content::EditTweet(EditTweet {
+                tweet_id,
+                content: content.to_string(),
+            }),
+            account: None,
+        };
+
+        let hash_1 = edit(1, "2x").hash();
+        let hash_12 = edit(12, "x").hash();
+        assert_ne!(hash_1.to_string(), hash_12.to_string());
+    }
+
     fn decode_hex(s: &str) -> Result<Vec<u8>, ParseIntError> {
         (0..s.len())
             .step_by(2)
@@ -0,0 +1,212 @@
+use crate::core::error::*;
+use crate::core::event::Decoder;
+use crate::core::message::Content;
+
+/// The names of the operations, indexed by their tag in the metrics
+pub const OPERATIONS: [&str; 20] = [
+    "PostTweet",
+    "PostTweetRef",
+    "LikeTweet",
+    "Transfer",
+    "Collect",
+    "Reclaim",
+    "HideTweet",
+    "BanAccount",
+    "UnbanAccount",
+    "AuthorizeSessionKey",
+    "RevokeSessionKey",
+    "MigrateAccount",
+    "SetSigners",
+    "EditTweet",
+    "PostPoll",
+    "Vote",
+    "Retweet",
+    "Quote",
+    "Pause",
+    "Unpause",
+];
+
+/// Returns the tag of the operation of a content
+pub fn operation_tag(content: &Content) -> u8 {
+    match content {
+        Content::PostTweet(_) => 0,
+        Content::PostTweetRef(_) => 1,
+        Content::LikeTweet(_) => 2,
+        Content::Transfer(_) => 3,
+        Content::Collect(_) => 4,
+        Content::Reclaim(_) => 5,
+        Content::HideTweet(_) => 6,
+        Content::BanAccount(_) => 7,
+        Content::UnbanAccount(_) => 8,
+        Content::AuthorizeSessionKey(_) => 9,
+        Content::RevokeSessionKey(_) => 10,
+        Content::MigrateAccount(_) => 11,
+        Content::SetSigners(_) => 12,
+        Content::EditTweet(_) => 13,
+        Content::PostPoll(_) => 14,
+        Content::Vote(_) => 15,
+        Content::Retweet(_) => 16,
+        Content::Quote(_) => 17,
+        Content::Pause(_) => 18,
+        Content::Unpause => 19,
+    }
+}
+
+/// The metrics of the messages of one kind of operation
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct OperationMetrics {
+    pub tag: u8,
+    /// The number of messages, including the failed ones
+    pub count: u64,
+    pub failed: u64,
+    /// The bytes written to the durable storage while processing the messages
+    pub bytes_written: u64,
+    /// The size of the values added to the durable storage minus the size of the removed ones
+    pub storage_growth: i64,
+}
+
+impl OperationMetrics {
+    /// Returns the name of the operation, None for a tag unknown to this kernel
+    pub fn name(&self) -> Option<&'static str> {
+        OPERATIONS.get(self.tag as usize).copied()
+    }
+
+    fn merge(&mut self, other: &OperationMetrics) {
+        self.count += other.count;
+        self.failed += other.failed;
+        self.bytes_written += other.bytes_written;
+        self.storage_growth += other.storage_growth;
+    }
+}
+
+/// The metrics recorded at /metrics/{level} when the kernel is built with the metrics feature
+///
+/// The ticks are not observable from the kernel,
+/// they are estimated from the number of operations and the benchmarks of the stages
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct LevelMetrics {
+    /// The bytes written during the level, by the operations and the start of level stages
+    pub bytes_written: u64,
+    pub storage_growth: i64,
+    /// The metrics of each operation processed during the level, ordered by tag
+    pub operations: Vec<OperationMetrics>,
+}
+
+impl LevelMetrics {
+    /// Returns true if nothing has been recorded
+    pub fn is_empty(&self) -> bool {
+        self.bytes_written == 0 && self.storage_growth == 0 && self.operations.is_empty()
+    }
+
+    /// Returns the metrics of an operation, created on its first message
+    pub fn operation(&mut self, tag: u8) -> &mut OperationMetrics {
+        let index = match self.operations.binary_search_by_key(&tag, |op| op.tag) {
+            Ok(index) => index,
+            Err(index) => {
+                let metrics = OperationMetrics {
+                    tag,
+                    ..OperationMetrics::default()
+                };
+                self.operations.insert(index, metrics);
+                index
+            }
+        };
+        &mut self.operations[index]
+    }
+
+    /// Adds the metrics of another run of the kernel at the same level
+    pub fn merge(&mut self, other: &LevelMetrics) {
+        self.bytes_written += other.bytes_written;
+        self.storage_growth += other.storage_growth;
+        for metrics in &other.operations {
+            self.operation(metrics.tag).merge(metrics);
+        }
+    }
+
+    /// Encodes the metrics, the integers are in big endian
+    ///
+    /// bytes_written (u64), storage_growth (i64), number of operations (u8),
+    /// then for each operation: tag (u8), count, failed, bytes_written (u64) and storage_growth (i64)
+    pub fn encode(&self) -> Vec<u8> {
+        let mut output = Vec::with_capacity(17 + self.operations.len() * 33);
+        output.extend_from_slice(&self.bytes_written.to_be_bytes());
+        output.extend_from_slice(&self.storage_growth.to_be_bytes());
+        output.push(self.operations.len() as u8);
+        for metrics in &self.operations {
+            output.push(metrics.tag);
+            output.extend_from_slice(&metrics.count.to_be_bytes());
+            output.extend_from_slice(&metrics.failed.to_be_bytes());
+            output.extend_from_slice(&metrics.bytes_written.to_be_bytes());
+            output.extend_from_slice(&metrics.storage_growth.to_be_bytes());
+        }
+        output
+    }
+
+    /// Decode the metrics encoded by LevelMetrics::encode
+    pub fn decode(bytes: &[u8]) -> Result<LevelMetrics> {
+        let mut decoder = Decoder { bytes };
+        let bytes_written = decoder.u64()?;
+        let storage_growth = decoder.u64()? as i64;
+        let operations = (0..decoder.u8()?)
+            .map(|_| {
+                Ok(OperationMetrics {
+                    tag: decoder.u8()?,
+                    count: decoder.u64()?,
+                    failed: decoder.u64()?,
+                    bytes_written: decoder.u64()?,
+                    storage_growth: decoder.u64()? as i64,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        match decoder.bytes {
+            [] => Ok(LevelMetrics {
+                bytes_written,
+                storage_growth,
+                operations,
+            }),
+            _ => Err(Error::StateDeserializarion),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LevelMetrics;
+
+    #[test]
+    fn test_roundtrip() {
+        let mut metrics = LevelMetrics {
+            bytes_written: 120,
+            storage_growth: -8,
+            ..LevelMetrics::default()
+        };
+        metrics.operation(2).count = 3;
+        metrics.operation(0).storage_growth = -40;
+
+        let bytes = metrics.encode();
+        assert_eq!(17 + 2 * 33, bytes.len());
+        let decoded = LevelMetrics::decode(&bytes).unwrap();
+        assert_eq!(metrics, decoded);
+        assert_eq!(Some("PostTweet"), decoded.operations[0].name());
+        assert_eq!(Some("LikeTweet"), decoded.operations[1].name());
+        assert!(LevelMetrics::decode(&bytes[1..]).is_err());
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut metrics = LevelMetrics::default();
+        metrics.operation(1).count = 1;
+        let mut other = LevelMetrics {
+            bytes_written: 10,
+            ..LevelMetrics::default()
+        };
+        other.operation(1).count = 2;
+        other.operation(0).failed = 1;
+
+        metrics.merge(&other);
+        assert_eq!(10, metrics.bytes_written);
+        assert_eq!(2, metrics.operations.len());
+        assert_eq!(1, metrics.operations[0].failed);
+        assert_eq!(3, metrics.operations[1].count);
+    }
+}
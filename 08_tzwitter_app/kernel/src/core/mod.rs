@@ -1,10 +1,22 @@
 pub mod account;
+pub mod collect;
+pub mod config;
 pub mod error;
+pub mod event;
 pub mod hash;
+pub mod merkle;
 pub mod message;
+pub mod metrics;
+pub mod multisig;
 pub mod nonce;
+pub mod outbox;
+pub mod pause;
+pub mod poll;
 pub mod public_key;
 pub mod public_key_hash;
+pub mod quota;
 pub mod receipt;
+pub mod session_key;
 pub mod signature;
+pub mod trending;
 pub mod tweet;
@@ -0,0 +1,73 @@
+use serde::{Deserialize, Serialize};
+
+use crate::core::public_key::PublicKey;
+use crate::core::public_key_hash::PublicKeyHash;
+use crate::core::signature::Signature;
+
+/// An additional signature of a message sent by a multisig account
+#[derive(Deserialize, Serialize)]
+pub struct Cosignature {
+    pub pkey: PublicKey,
+    pub signature: Signature,
+}
+
+/// The M-of-N set of keys controlling a multisig account
+pub struct Signers {
+    pub threshold: u32,
+    pub signers: Vec<PublicKeyHash>,
+}
+
+impl Signers {
+    /// Checks if the public key hash is one of the signers
+    pub fn contains(&self, public_key_hash: &PublicKeyHash) -> bool {
+        let public_key_hash = public_key_hash.to_string();
+        self.signers
+            .iter()
+            .any(|signer| signer.to_string() == public_key_hash)
+    }
+
+    /// The threshold should be reachable by distinct signers
+    pub fn is_valid(&self, max_signers: usize) -> bool {
+        let mut signers: Vec<String> = self.signers.iter().map(|s| s.to_string()).collect();
+        signers.sort_unstable();
+        signers.dedup();
+        self.threshold > 0
+            && signers.len() == self.signers.len()
+            && signers.len() <= max_signers
+            && self.threshold as usize <= signers.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Signers;
+    use crate::core::public_key_hash::PublicKeyHash;
+
+    fn signers(threshold: u32, signers: &[&str]) -> Signers {
+        let signers = signers
+            .iter()
+            .map(|signer| PublicKeyHash::from_b58(signer).unwrap())
+            .collect();
+        Signers { threshold, signers }
+    }
+
+    const ALICE: &str = "tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv";
+    const BOB: &str = "tz1hEW4Bz2tLWBJbefwx6wCqdcX4CcjyLXdq";
+
+    #[test]
+    fn test_valid_threshold() {
+        assert!(signers(1, &[ALICE]).is_valid(16));
+        assert!(signers(2, &[ALICE, BOB]).is_valid(16));
+        assert!(!signers(0, &[ALICE, BOB]).is_valid(16));
+        assert!(!signers(3, &[ALICE, BOB]).is_valid(16));
+        assert!(!signers(2, &[ALICE, ALICE]).is_valid(16));
+        assert!(!signers(1, &[ALICE, BOB]).is_valid(1));
+    }
+
+    #[test]
+    fn test_contains() {
+        let signers = signers(1, &[ALICE]);
+        assert!(signers.contains(&PublicKeyHash::from_b58(ALICE).unwrap()));
+        assert!(!signers.contains(&PublicKeyHash::from_b58(BOB).unwrap()));
+    }
+}
@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Deserialize, Serialize, PartialEq, Eq, Default)]
+#[derive(Deserialize, Serialize, PartialEq, Eq, Default, Debug)]
 pub struct Nonce(pub u64);
 
 impl Nonce {
@@ -9,8 +9,87 @@ impl Nonce {
     }
 }
 
+/// The nonces used ahead of the nonce of an account, when the nonce window is enabled
+///
+/// The bit i is set when the nonce `nonce + 1 + i` has been used
+#[derive(Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct UsedNonces(pub u64);
+
+impl UsedNonces {
+    /// Marks a nonce as used
+    ///
+    /// The nonce has to be in [current + 1, current + window] and not already used
+    /// The nonce of the account moves forward as long as the next nonces are used
+    ///
+    /// Returns the new nonce of the account and the nonces still used ahead of it
+    pub fn accept(&self, current: &Nonce, nonce: &Nonce, window: u64) -> Option<(Nonce, Self)> {
+        if nonce.0 <= current.0 || nonce.0 - current.0 > window {
+            return None;
+        }
+        let bit = 1u64.checked_shl((nonce.0 - current.0 - 1) as u32)?;
+        if self.0 & bit != 0 {
+            return None;
+        }
+
+        let mut used = self.0 | bit;
+        let mut current = current.0;
+        while used & 1 == 1 {
+            used >>= 1;
+            current += 1;
+        }
+        Some((Nonce(current), UsedNonces(used)))
+    }
+}
+
 impl ToString for Nonce {
     fn to_string(&self) -> String {
         format!("{:08X}", self.0)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Nonce, UsedNonces};
+
+    #[test]
+    fn test_strict_order() {
+        let used = UsedNonces::default();
+        assert_eq!(
+            Some((Nonce(4), UsedNonces(0))),
+            used.accept(&Nonce(3), &Nonce(4), 1)
+        );
+        assert_eq!(None, used.accept(&Nonce(3), &Nonce(5), 1));
+        assert_eq!(None, used.accept(&Nonce(3), &Nonce(3), 1));
+    }
+
+    #[test]
+    fn test_out_of_order() {
+        let used = UsedNonces::default();
+        let (nonce, used) = used.accept(&Nonce(0), &Nonce(3), 4).unwrap();
+        assert_eq!(Nonce(0), nonce);
+        assert_eq!(UsedNonces(0b100), used);
+        let (nonce, used) = used.accept(&nonce, &Nonce(1), 4).unwrap();
+        assert_eq!(Nonce(1), nonce);
+        assert_eq!(UsedNonces(0b10), used);
+        let (nonce, used) = used.accept(&nonce, &Nonce(2), 4).unwrap();
+        assert_eq!(Nonce(3), nonce);
+        assert_eq!(UsedNonces(0), used);
+    }
+
+    #[test]
+    fn test_replay_in_window() {
+        let (nonce, used) = UsedNonces::default()
+            .accept(&Nonce(0), &Nonce(2), 4)
+            .unwrap();
+        assert_eq!(None, used.accept(&nonce, &Nonce(2), 4));
+        assert_eq!(None, used.accept(&nonce, &Nonce(5), 4));
+    }
+
+    #[test]
+    fn test_largest_window() {
+        let (nonce, used) = UsedNonces::default()
+            .accept(&Nonce(0), &Nonce(64), 64)
+            .unwrap();
+        assert_eq!((Nonce(0), UsedNonces(1 << 63)), (nonce, used));
+    }
+}
@@ -0,0 +1,30 @@
+use crate::core::public_key_hash::PublicKeyHash;
+
+/// An outbox message that has not been written to the outbox yet
+///
+/// When the outbox of the current level is full
+/// the message is kept in the durable storage until there is enough room
+pub struct PendingOutput {
+    /// The serialized outbox message
+    pub payload: Vec<u8>,
+    /// The owner and the tweet being collected, if the message is a collect
+    pub collect: Option<(PublicKeyHash, u64)>,
+}
+
+impl PendingOutput {
+    /// Creates an outbox message that does not update the state once written
+    pub fn new(payload: Vec<u8>) -> PendingOutput {
+        PendingOutput {
+            payload,
+            collect: None,
+        }
+    }
+
+    /// Creates an outbox message collecting the given tweet
+    pub fn collect(payload: Vec<u8>, owner: PublicKeyHash, tweet_id: u64) -> PendingOutput {
+        PendingOutput {
+            payload,
+            collect: Some((owner, tweet_id)),
+        }
+    }
+}
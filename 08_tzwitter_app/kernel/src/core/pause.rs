@@ -0,0 +1,37 @@
+use serde::{Deserialize, Serialize};
+
+/// How the kernel handles the user messages while it is paused
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PauseMode {
+    /// The messages are skipped with a paused receipt
+    Reject,
+    /// The messages are queued, then replayed once the kernel is unpaused
+    Defer,
+}
+
+impl PauseMode {
+    /// Returns the name of the mode, as in the messages
+    pub fn name(&self) -> &'static str {
+        match self {
+            PauseMode::Reject => "Reject",
+            PauseMode::Defer => "Defer",
+        }
+    }
+
+    /// Returns the byte of the mode in the durable storage
+    pub fn to_byte(&self) -> u8 {
+        match self {
+            PauseMode::Reject => 0x01,
+            PauseMode::Defer => 0x02,
+        }
+    }
+
+    /// Returns the mode of a byte written by PauseMode::to_byte
+    pub fn from_byte(byte: u8) -> Option<PauseMode> {
+        match byte {
+            0x01 => Some(PauseMode::Reject),
+            0x02 => Some(PauseMode::Defer),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,56 @@
+/// A poll attached to a tweet
+pub struct Poll {
+    pub options: Vec<String>,
+    /// The last level at which the accounts can vote
+    pub closes_at_level: u32,
+}
+
+impl Poll {
+    /// A poll has at least two non empty options and closes in the future
+    pub fn is_valid(&self, level: u32, max_options: usize) -> bool {
+        self.options.len() >= 2
+            && self.options.len() <= max_options
+            && self.options.iter().all(|option| !option.is_empty())
+            && self.closes_at_level >= level
+    }
+
+    /// Checks if the accounts can still vote at the given level
+    pub fn is_open(&self, level: u32) -> bool {
+        level <= self.closes_at_level
+    }
+}
+
+/// The results of a poll, frozen at the start of the level following its closing level
+pub struct PollResults {
+    /// The number of votes of each option
+    pub tallies: Vec<u64>,
+    pub votes: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Poll;
+
+    fn poll(options: &[&str], closes_at_level: u32) -> Poll {
+        Poll {
+            options: options.iter().map(|option| option.to_string()).collect(),
+            closes_at_level,
+        }
+    }
+
+    #[test]
+    fn test_valid_poll() {
+        assert!(poll(&["yes", "no"], 10).is_valid(10, 4));
+        assert!(!poll(&["yes"], 10).is_valid(10, 4));
+        assert!(!poll(&["yes", ""], 10).is_valid(10, 4));
+        assert!(!poll(&["a", "b", "c"], 10).is_valid(10, 2));
+        assert!(!poll(&["yes", "no"], 9).is_valid(10, 4));
+    }
+
+    #[test]
+    fn test_open_until_closing_level() {
+        let poll = poll(&["yes", "no"], 10);
+        assert!(poll.is_open(10));
+        assert!(!poll.is_open(11));
+    }
+}
@@ -0,0 +1,49 @@
+use crate::core::config::RateLimit;
+use crate::core::error::*;
+
+/// The number of posts and likes of an account during a window of levels
+#[derive(Default)]
+pub struct Quota {
+    pub window: u32,
+    pub posts: u64,
+    pub likes: u64,
+}
+
+impl Quota {
+    /// Returns the quota of the window of the given level
+    ///
+    /// The counters are reset when a new window starts
+    pub fn at_level(self, rate_limit: &RateLimit, level: u32) -> Quota {
+        let window = level.checked_div(rate_limit.window).unwrap_or_default();
+        if window == self.window {
+            self
+        } else {
+            Quota {
+                window,
+                ..Quota::default()
+            }
+        }
+    }
+
+    /// Count a new post
+    pub fn post(self, rate_limit: &RateLimit) -> Result<Quota> {
+        if rate_limit.window != 0 && self.posts >= rate_limit.max_posts {
+            return Err(Error::PostQuotaExceeded);
+        }
+        Ok(Quota {
+            posts: self.posts + 1,
+            ..self
+        })
+    }
+
+    /// Count a new like
+    pub fn like(self, rate_limit: &RateLimit) -> Result<Quota> {
+        if rate_limit.window != 0 && self.likes >= rate_limit.max_likes {
+            return Err(Error::LikeQuotaExceeded);
+        }
+        Ok(Quota {
+            likes: self.likes + 1,
+            ..self
+        })
+    }
+}
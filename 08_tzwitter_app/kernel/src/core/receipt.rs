@@ -3,11 +3,12 @@ use crate::core::hash::*;
 
 /// definition of a receipt
 ///
-/// For now we don't need to know the cause of the error
-/// Because the receipt is only used in the front-end application to give user feedbacks
+/// The receipt is used in the front-end application to give user feedbacks
+/// When the message has failed, the receipt contains the cause of the error
 pub struct Receipt {
     hash: Blake2b,
     success: bool,
+    error: Option<String>,
 }
 
 impl Receipt {
@@ -15,6 +16,16 @@ impl Receipt {
         Receipt {
             hash,
             success: result.is_ok(),
+            error: result.as_ref().err().map(Error::to_string),
+        }
+    }
+
+    /// Creates a receipt from its stored fields
+    pub fn from_state(hash: Blake2b, success: bool, error: Option<String>) -> Receipt {
+        Receipt {
+            hash,
+            success,
+            error,
         }
     }
 
@@ -27,4 +38,9 @@ impl Receipt {
     pub fn success(&self) -> bool {
         self.success
     }
+
+    /// Returns the cause of the error if the message has failed
+    pub fn error(&self) -> Option<&String> {
+        self.error.as_ref()
+    }
 }
@@ -0,0 +1,101 @@
+use serde::{Deserialize, Serialize};
+
+use crate::core::message::Content;
+
+/// Operations that an account can delegate to a session key
+///
+/// The management of the account (session keys, moderation) is never delegated
+#[derive(Deserialize, Serialize, PartialEq, Eq, Clone, Copy)]
+pub enum SessionOperation {
+    PostTweet,
+    LikeTweet,
+    Transfer,
+    Collect,
+}
+
+impl SessionOperation {
+    /// Name of the operation, used in the hash of the messages and in the storage
+    pub fn name(&self) -> &'static str {
+        match self {
+            SessionOperation::PostTweet => "PostTweet",
+            SessionOperation::LikeTweet => "LikeTweet",
+            SessionOperation::Transfer => "Transfer",
+            SessionOperation::Collect => "Collect",
+        }
+    }
+
+    /// All the operations that can be delegated
+    pub fn all() -> [SessionOperation; 4] {
+        [
+            SessionOperation::PostTweet,
+            SessionOperation::LikeTweet,
+            SessionOperation::Transfer,
+            SessionOperation::Collect,
+        ]
+    }
+
+    /// Returns the operation of a content, None if it cannot be delegated
+    pub fn of(content: &Content) -> Option<SessionOperation> {
+        match content {
+            Content::PostTweet(_)
+            | Content::PostTweetRef(_)
+            | Content::EditTweet(_)
+            | Content::PostPoll(_)
+            | Content::Retweet(_)
+            | Content::Quote(_) => Some(SessionOperation::PostTweet),
+            Content::LikeTweet(_) | Content::Vote(_) => Some(SessionOperation::LikeTweet),
+            Content::Transfer(_) => Some(SessionOperation::Transfer),
+            Content::Collect(_) | Content::Reclaim(_) => Some(SessionOperation::Collect),
+            _ => None,
+        }
+    }
+}
+
+/// A key authorized to sign some operations for an account
+pub struct SessionKey {
+    pub expiry_level: u32,
+    pub allowed_ops: Vec<SessionOperation>,
+}
+
+impl SessionKey {
+    /// The session key cannot be used after its expiry level
+    pub fn is_expired(&self, level: u32) -> bool {
+        level > self.expiry_level
+    }
+
+    /// Checks if the session key can sign the given content
+    pub fn allows(&self, content: &Content) -> bool {
+        match SessionOperation::of(content) {
+            Some(operation) => self.allowed_ops.contains(&operation),
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SessionKey, SessionOperation};
+    use crate::core::message::Content;
+
+    #[test]
+    fn test_transfer_not_allowed_by_default() {
+        let session_key = SessionKey {
+            expiry_level: 10,
+            allowed_ops: vec![SessionOperation::PostTweet, SessionOperation::LikeTweet],
+        };
+        assert!(session_key.allows(&Content::LikeTweet(0)));
+        assert!(!session_key.allows(&Content::Collect(0)));
+        assert!(!session_key.allows(&Content::Reclaim(0)));
+        assert!(!session_key.allows(&Content::HideTweet(0)));
+    }
+
+    #[test]
+    fn test_expiry() {
+        let session_key = SessionKey {
+            expiry_level: 10,
+            allowed_ops: Vec::new(),
+        };
+        assert!(!session_key.is_expired(10));
+        assert!(session_key.is_expired(11));
+    }
+}
@@ -1,10 +1,10 @@
 use crate::core::error::*;
 use crate::core::hash::Blake2b;
 use crate::core::public_key::PublicKey;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use tezos_crypto_rs::hash::Ed25519Signature;
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
 pub enum Signature {
     Ed25519(Ed25519Signature),
 }
@@ -0,0 +1,107 @@
+use crate::core::error::*;
+
+/// The most liked tweets of the trending window, ordered by likes
+///
+/// The top is updated incrementally: a tweet that falls out of it
+/// is only considered again at its next like
+///
+/// The ranking is therefore approximate: the kernel keeps more candidates than
+/// the top shows, but a tweet can still be missing from the top once more
+/// candidates than that have left the window
+#[derive(Default, Debug, PartialEq, Eq)]
+pub struct TopTweets(Vec<(u64, u64)>);
+
+impl TopTweets {
+    /// Returns the tweet ids and their likes in the window, the most liked first
+    pub fn entries(&self) -> &[(u64, u64)] {
+        &self.0
+    }
+
+    /// Sets the likes of a tweet in the window
+    ///
+    /// Only the size most liked tweets are kept
+    /// A tweet without any like in the window leaves the top
+    pub fn update(&mut self, tweet_id: u64, likes: u64, size: usize) {
+        self.0.retain(|(id, _)| *id != tweet_id);
+        if likes > 0 {
+            self.0.push((tweet_id, likes));
+        }
+        // The oldest tweet comes first among the tweets with the same likes
+        self.0.sort_unstable_by(|(id_a, likes_a), (id_b, likes_b)| {
+            likes_b.cmp(likes_a).then(id_a.cmp(id_b))
+        });
+        self.0.truncate(size);
+    }
+
+    /// Keeps only the size most liked tweets
+    pub fn truncate(&mut self, size: usize) {
+        self.0.truncate(size);
+    }
+
+    /// Encodes the top as a sequence of tweet ids and likes, as u64 in big endian
+    pub fn encode(&self) -> Vec<u8> {
+        let mut output = Vec::with_capacity(self.0.len() * 16);
+        for (tweet_id, likes) in &self.0 {
+            output.extend_from_slice(&tweet_id.to_be_bytes());
+            output.extend_from_slice(&likes.to_be_bytes());
+        }
+        output
+    }
+
+    /// Decode a top encoded by TopTweets::encode
+    pub fn decode(bytes: &[u8]) -> Result<TopTweets> {
+        if bytes.len() % 16 != 0 {
+            return Err(Error::StateDeserializarion);
+        }
+        let entries = bytes
+            .chunks_exact(16)
+            .map(|entry| {
+                let (tweet_id, likes) = entry.split_at(8);
+                let tweet_id: [u8; 8] = tweet_id.try_into().unwrap_or_default();
+                let likes: [u8; 8] = likes.try_into().unwrap_or_default();
+                (u64::from_be_bytes(tweet_id), u64::from_be_bytes(likes))
+            })
+            .collect();
+        Ok(TopTweets(entries))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TopTweets;
+
+    #[test]
+    fn test_update_keeps_the_most_liked() {
+        let mut top = TopTweets::default();
+        top.update(1, 3, 2);
+        top.update(2, 5, 2);
+        top.update(3, 1, 2);
+        assert_eq!(&[(2, 5), (1, 3)], top.entries());
+
+        top.update(3, 4, 2);
+        assert_eq!(&[(2, 5), (3, 4)], top.entries());
+    }
+
+    #[test]
+    fn test_update_removes_expired_likes() {
+        let mut top = TopTweets::default();
+        top.update(1, 3, 2);
+        top.update(2, 3, 2);
+        assert_eq!(&[(1, 3), (2, 3)], top.entries());
+
+        top.update(1, 0, 2);
+        assert_eq!(&[(2, 3)], top.entries());
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let mut top = TopTweets::default();
+        top.update(7, 2, 10);
+        top.update(3, 9, 10);
+
+        let bytes = top.encode();
+        assert_eq!(32, bytes.len());
+        assert_eq!(top, TopTweets::decode(&bytes).unwrap());
+        assert!(TopTweets::decode(&bytes[1..]).is_err());
+    }
+}
@@ -1,14 +1,18 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 use crate::core::public_key_hash::PublicKeyHash;
 
 use super::message::PostTweet;
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize)]
 pub struct Tweet {
     pub author: PublicKeyHash,
     pub content: String,
     pub likes: u64,
+    /// The number of retweets and quotes of the tweet
+    pub retweets: u64,
+    /// The id of the tweet quoted by this one
+    pub quoted: Option<u64>,
 }
 
 impl From<PostTweet> for Tweet {
@@ -18,10 +22,19 @@ impl From<PostTweet> for Tweet {
             author,
             content,
             likes: 0,
+            retweets: 0,
+            quoted: None,
         }
     }
 }
 
+/// A previous version of an edited tweet
+pub struct TweetVersion {
+    pub content: String,
+    /// The level of the edit that replaced this version
+    pub level: u32,
+}
+
 impl Tweet {
     pub fn like(self) -> Self {
         Self {
@@ -29,4 +42,11 @@ impl Tweet {
             ..self
         }
     }
+
+    pub fn retweet(self) -> Self {
+        Self {
+            retweets: self.retweets + 1,
+            ..self
+        }
+    }
 }
@@ -1,50 +1,246 @@
-use crate::core::message::{Content, Message};
+use crate::core::event::Event;
+use crate::core::message::{Content, Inner, Input, Message};
+use crate::core::metrics::operation_tag;
 use crate::core::public_key_hash::PublicKeyHash;
 use crate::core::receipt::Receipt;
 
 // src/lib.rs
-use storage::{read_account, store_account, store_receipt};
+use storage::{
+    append_event, read_account, store_account, store_balance, store_quota, store_receipt,
+};
 use tezos_smart_rollup::{kernel_entry, prelude::*};
 
-mod constants;
-mod core;
+pub mod constants;
+pub mod core;
+mod metrics;
+mod migration;
+#[cfg(test)]
+mod proptests;
+pub mod query;
 mod stages;
 mod storage;
 
+use crate::constants::MAX_REPLAYED_MESSAGES_PER_LEVEL;
 use crate::core::error::*;
 use stages::{
-    create_tweet, like_tweet, read_input, transfer_tweet, verify_nonce, verify_signature,
-    withdraw_tweet,
+    admit_message, authorize_session_key, ban_account, check_posting_fee, check_rate_limit,
+    close_polls, commit_state, create_poll, create_tweet, create_tweet_ref, defer_message, deposit,
+    edit_tweet, expire_trending, finalize_collect, flush_outbox_queue, hide_tweet, like_tweet,
+    migrate_account, next_deferred_message, pause_kernel, quote_tweet, read_input, reclaim_tweet,
+    retweet, revoke_session_key, set_signers, transfer_tweet, unban_account, unpause_kernel,
+    verify_event_log, verify_nonce, verify_not_banned, verify_not_migrated, verify_signature, vote,
+    withdraw_tweet, Admission,
 };
 
 /// A step is processing only one message from the inbox
 ///
+/// The signature of the message has already been verified,
+/// by the account, its signers or one of its session keys
+///
 /// It will execute several sub steps:
-/// - verify the signature of the message
+/// - verify that the account has not been migrated
 /// - verify the nonce of the message
+/// - verify that the account is not banned
+/// - verify that the event log of the level is not full
+/// - check the rate limits and the posting fee
 /// - handle the message
-fn step<R: Runtime>(host: &mut R, message: Message, level: u32) -> Result<()> {
-    let public_key = message.public_key();
-    let public_key_hash = PublicKeyHash::from(public_key);
-    debug_msg!(host, "Message is deserialized\n");
-
-    let inner = verify_signature(message)?;
+/// - count the operation in the quota and debit the posting fee
+/// - append the event of the operation to /events/{level}/{index}
+fn step<R: Runtime>(
+    host: &mut R,
+    public_key_hash: PublicKeyHash,
+    inner: Inner,
+    level: u32,
+) -> Result<()> {
     debug_msg!(host, "Signature is correct\n");
 
+    // A migrated account is controlled by its new owner
+    verify_not_migrated(host, &public_key_hash)?;
+
     // Verify the nonce
     let account = read_account(host, public_key_hash)?;
-    let content = verify_nonce(inner, account.nonce())?;
-    let account = account.increment_nonce();
+    let (content, account) = verify_nonce(host, inner, account)?;
     let _ = store_account(host, &account)?;
 
+    // Moderation
+    verify_not_banned(host, &account, &content)?;
+
+    // The event log of the level is bounded
+    verify_event_log(host, level)?;
+
+    // Spam protection, the quota and the fee are spent once the message has been handled
+    let quota = check_rate_limit(host, level, &account, &content)?;
+    let balance = check_posting_fee(host, &account, &content)?;
+
     // Interpret the message
-    match content {
-        Content::PostTweet(post_tweet) => create_tweet(host, &account, post_tweet)?,
-        Content::LikeTweet(tweet_id) => like_tweet(host, &account, &tweet_id)?,
-        Content::Transfer(transfer) => transfer_tweet(host, &account, &transfer)?,
-        Content::Collect(twwet_id) => withdraw_tweet(host, level, &account, &twwet_id)?,
+    let owner = account.public_key_hash.clone();
+    let event = match content {
+        Content::PostTweet(post_tweet) => {
+            let tweet_id = create_tweet(host, &account, post_tweet)?;
+            Event::TweetPosted {
+                tweet_id,
+                author: owner,
+            }
+        }
+        Content::PostTweetRef(post_tweet_ref) => {
+            let tweet_id = create_tweet_ref(host, &account, post_tweet_ref)?;
+            Event::TweetPosted {
+                tweet_id,
+                author: owner,
+            }
+        }
+        Content::PostPoll(post_poll) => {
+            let tweet_id = create_poll(host, level, &account, post_poll)?;
+            Event::TweetPosted {
+                tweet_id,
+                author: owner,
+            }
+        }
+        Content::Vote(ballot) => {
+            vote(host, level, &account, &ballot)?;
+            Event::PollVoted {
+                tweet_id: ballot.tweet_id,
+                account: owner,
+                option: ballot.option,
+            }
+        }
+        Content::Retweet(tweet_id) => {
+            retweet(host, &account, &tweet_id)?;
+            Event::TweetRetweeted {
+                tweet_id,
+                account: owner,
+            }
+        }
+        Content::Quote(quote) => {
+            let quoted = quote.tweet_id;
+            let tweet_id = quote_tweet(host, &account, quote)?;
+            Event::TweetQuoted {
+                tweet_id,
+                quoted,
+                author: owner,
+            }
+        }
+        Content::EditTweet(edit) => {
+            edit_tweet(host, level, &account, &edit)?;
+            Event::TweetEdited {
+                tweet_id: edit.tweet_id,
+            }
+        }
+        Content::LikeTweet(tweet_id) => {
+            like_tweet(host, level, &account, &tweet_id)?;
+            Event::TweetLiked {
+                tweet_id,
+                account: owner,
+            }
+        }
+        Content::Transfer(transfer) => {
+            let destination = transfer_tweet(host, &account, &transfer)?;
+            Event::TweetTransferred {
+                tweet_id: transfer.tweet_id,
+                from: owner,
+                to: destination,
+            }
+        }
+        Content::Collect(tweet_id) => {
+            withdraw_tweet(host, level, &account, &tweet_id)?;
+            Event::TweetCollected { tweet_id, owner }
+        }
+        Content::Reclaim(tweet_id) => {
+            reclaim_tweet(host, level, &account, &tweet_id)?;
+            Event::TweetReclaimed { tweet_id, owner }
+        }
+        Content::HideTweet(tweet_id) => {
+            hide_tweet(host, &account, &tweet_id)?;
+            Event::TweetHidden { tweet_id }
+        }
+        Content::BanAccount(public_key_hash) => {
+            ban_account(host, &account, &public_key_hash)?;
+            Event::AccountBanned(public_key_hash)
+        }
+        Content::UnbanAccount(public_key_hash) => {
+            unban_account(host, &account, &public_key_hash)?;
+            Event::AccountUnbanned(public_key_hash)
+        }
+        Content::AuthorizeSessionKey(authorize) => {
+            let key = authorize.key.clone();
+            authorize_session_key(host, &account, authorize)?;
+            Event::SessionKeyAuthorized {
+                account: owner,
+                key,
+            }
+        }
+        Content::RevokeSessionKey(key) => {
+            revoke_session_key(host, &account, &key)?;
+            Event::SessionKeyRevoked {
+                account: owner,
+                key,
+            }
+        }
+        Content::SetSigners(set) => {
+            let threshold = set.threshold;
+            set_signers(host, &account, set)?;
+            Event::SignersSet {
+                account: owner,
+                threshold,
+            }
+        }
+        Content::MigrateAccount(migrate) => {
+            migrate_account(host, &account, &migrate)?;
+            Event::AccountMigrated {
+                from: owner,
+                to: migrate.new_owner,
+            }
+        }
+        Content::Pause(mode) => {
+            pause_kernel(host, &account, &mode)?;
+            Event::KernelPaused
+        }
+        Content::Unpause => {
+            unpause_kernel(host, &account)?;
+            Event::KernelUnpaused
+        }
     };
 
+    if let Some(quota) = quota {
+        store_quota(host, &account.public_key_hash, &quota)?;
+    }
+    if let Some(balance) = balance {
+        store_balance(host, &account.public_key_hash, &balance)?;
+    }
+
+    // Record the operation for the indexers
+    append_event(host, &level, &event)
+}
+
+/// Verify the signature of a message, then process it
+///
+/// The signers and the session keys of the account may have changed since the message was deferred
+fn verify_and_step<R: Runtime>(host: &mut R, message: Message, level: u32) -> Result<()> {
+    let (public_key_hash, inner) = verify_signature(host, level, message)?;
+    step(host, public_key_hash, inner, level)
+}
+
+/// Replay the messages deferred during a pause
+///
+/// At most MAX_REPLAYED_MESSAGES_PER_LEVEL messages are replayed per level,
+/// the receipt of each replayed message replaces its deferred receipt
+fn replay_deferred<R: Runtime>(host: &mut R, level: u32) -> Result<()> {
+    for _ in 0..MAX_REPLAYED_MESSAGES_PER_LEVEL {
+        let message = match next_deferred_message(host)? {
+            Some(message) => message,
+            None => return Ok(()),
+        };
+        let hash = message.hash();
+        let tag = operation_tag(&message.inner.content);
+        let result = metrics::measure(tag, || verify_and_step(host, message, level));
+
+        let receipt = Receipt::new(hash, &result);
+        let _ = store_receipt(host, &receipt)?;
+
+        if let Err(Error::Runtime(err)) = result {
+            return Err(Error::Runtime(err));
+        }
+    }
     Ok(())
 }
 
@@ -55,19 +251,76 @@ fn step<R: Runtime>(host: &mut R, message: Message, level: u32) -> Result<()> {
 /// Then all the errors, will be stored in a receipt
 /// Continue until the inbox is emptied
 ///
+/// At the start of each level, the queued outbox messages are written,
+/// the results of the polls closed at the previous level are frozen
+/// and the likes leaving the trending window are removed
+/// Every commitment period, the state is committed to the L1 registry
+/// Then the messages deferred during a pause are replayed
+///
+/// While the kernel is paused, the messages of the users get a paused receipt
+/// and are either skipped or copied to the deferred queue, the messages of the admin are processed
+/// When the L1 contract confirms a mint, the collect is finalized
+/// When the L1 contract sends a deposit, the balance of the account is credited
+///
+/// With the metrics feature, each message is measured under the tag of its operation
+///
 /// This function stop its execution when a RuntimeError happens
 ///
 /// TODO: it can count ticks and reboot the kernel between two inbox message
 fn execute<R: Runtime>(host: &mut R) -> Result<()> {
-    let message = read_input(host);
-    match message {
+    let input = read_input(host);
+    if let Ok((_, level)) = &input {
+        metrics::set_level(*level);
+    }
+    match input {
         Err(ReadInputError::EndOfInbox) => Ok(()),
         Err(ReadInputError::Runtime(err)) => Err(Error::Runtime(err)),
         Err(_) => execute(host),
-        Ok((message, level)) => {
+        Ok((Input::StartOfLevel, level)) => {
+            let results = [
+                flush_outbox_queue(host, level),
+                close_polls(host, level),
+                expire_trending(host, level),
+                commit_state(host, level),
+                replay_deferred(host, level),
+            ];
+            let runtime_error = results.into_iter().find_map(|result| match result {
+                Err(Error::Runtime(err)) => Some(err),
+                _ => None,
+            });
+            match runtime_error {
+                Some(err) => Err(Error::Runtime(err)),
+                None => execute(host),
+            }
+        }
+        Ok((Input::Minted(tweet_id), _)) => match finalize_collect(host, &tweet_id) {
+            Err(Error::Runtime(err)) => Err(Error::Runtime(err)),
+            _ => execute(host),
+        },
+        Ok((
+            Input::Deposit {
+                public_key_hash,
+                amount,
+            },
+            _,
+        )) => match deposit(host, &public_key_hash, amount) {
+            Err(Error::Runtime(err)) => Err(Error::Runtime(err)),
+            _ => execute(host),
+        },
+        Ok((Input::Message(message), level)) => {
             // If the message is processed we can extract the hash of the message
             let hash = message.hash();
-            let result = step(host, message, level);
+            let tag = operation_tag(&message.inner.content);
+            let result = metrics::measure(tag, || match admit_message(host, level, &message) {
+                Ok(Admission::Process(public_key_hash)) => {
+                    step(host, public_key_hash, message.inner, level)
+                }
+                Ok(Admission::Reject) => Err(Error::KernelPaused),
+                Ok(Admission::Defer) => {
+                    defer_message(host, &message).and(Err(Error::MessageDeferred))
+                }
+                Err(err) => Err(err),
+            });
 
             let receipt = Receipt::new(hash, &result);
             let _ = store_receipt(host, &receipt)?;
@@ -83,26 +336,63 @@ fn execute<R: Runtime>(host: &mut R) -> Result<()> {
 
 pub fn entry<R: Runtime>(host: &mut R) {
     debug_msg!(host, "Hello Kernel\n");
+    // The inbox is read once the durable storage has the layout of this kernel
+    match migration::migrate(host) {
+        Ok(true) => {}
+        Ok(false) => {
+            debug_msg!(host, "Migrating the storage, rebooting\n");
+            return;
+        }
+        Err(err) => {
+            debug_msg!(host, "{}", &err.to_string());
+            return;
+        }
+    }
     match execute(host) {
         Ok(_) => {}
         Err(err) => debug_msg!(host, "{}", &err.to_string()),
     }
+    if let Err(err) = metrics::flush(host) {
+        debug_msg!(host, "{}", &err.to_string());
+    }
 }
 
 kernel_entry!(entry);
 
+#[cfg(feature = "fuzzing")]
+pub use stages::read_input;
+
 #[cfg(test)]
 mod tests {
 
+    use tezos_crypto_rs::hash::HashType::ContractKt1Hash;
     use tezos_data_encoding::enc::BinWriter;
-    use tezos_smart_rollup::{prelude::*, storage::path::RefPath, testing::prelude::MockHost};
+    use tezos_smart_rollup::{
+        dac::prepare_preimages,
+        michelson::{MichelsonBytes, MichelsonContract, MichelsonInt, MichelsonPair},
+        prelude::*,
+        storage::path::RefPath,
+        testing::prelude::{MockHost, TransferMetadata},
+        types::{self, Contract},
+    };
 
     use crate::{
-        constants::MAGIC_BYTE,
-        core::message::Message,
-        stages::read_input,
-        step,
-        storage::{exists, read_u64},
+        constants::{
+            COLLECT_TIMEOUT, L1_TOKEN_CONTRACT_ADDRESS, MAGIC_BYTE, MAX_DEFERRED_MESSAGES,
+            MAX_EVENTS_PER_LEVEL, TRENDING_CANDIDATES, TRENDING_TOP_SIZE, TRENDING_WINDOW,
+        },
+        core::error::Error,
+        core::event::Event,
+        core::message::{Input, Message},
+        core::trending::TopTweets,
+        entry,
+        query::{
+            get_events, get_merkle_root, get_poll_results, get_top_tweets, get_tweet_history,
+            get_tweet_proof, HostReader,
+        },
+        stages::{close_polls, expire_trending, read_input, L1Message},
+        storage::{add_trending_like, exists, read_bytes, read_u32, read_u64, store_top_tweets},
+        verify_and_step,
     };
 
     /// Assert a path exists in the storage
@@ -161,160 +451,1451 @@ mod tests {
         .into()
     }
 
-    fn input_like_2() -> BinInput {
-        "7b22706b6579223a7b2245643235353139223a226564706b75444d556d375935337770346778654c425875694168585a724c6e385842315238336b737676657348384c7038626d43664b227d2c227369676e6174757265223a7b2245643235353139223a22656473696774775a6d6376566470575361696836646a5057526172645668723154614b32786275646a7937686d7a6a65456e4b77766747346d50676455573478764254714452584e5348596f6a5973395a796d5968565469586d667a67323778624846227d2c22696e6e6572223a7b226e6f6e6365223a332c22636f6e74656e74223a7b224c696b655477656574223a307d7d7d".into()
-    }
-
     fn input_transfer() -> BinInput {
         "7b22706b6579223a7b2245643235353139223a226564706b75444d556d375935337770346778654c425875694168585a724c6e385842315238336b737676657348384c7038626d43664b227d2c227369676e6174757265223a7b2245643235353139223a226564736967746a616a43534e5548464a6f6f775978756e566b5a53644478655a7459687a5756444d617359785365315a59625650444e4b4d4157574152454c52734244624242774d646f786f36676e36766639374e74413661745232637656746f7a37227d2c22696e6e6572223a7b226e6f6e6365223a322c22636f6e74656e74223a7b225472616e73666572223a7b2264657374696e6174696f6e223a7b22547a31223a22747a3154477536544e354753657a326e645858654458364c675544764c7a504c71675956227d2c2274776565745f6964223a307d7d7d7d".into()
     }
 
+    /// Collect the tweet 0 with the nonce 2
+    fn input_collect() -> BinInput {
+        "7b22706b6579223a7b2245643235353139223a226564706b75444d556d375935337770346778654c425875694168585a724c6e385842315238336b737676657348384c7038626d43664b227d2c227369676e6174757265223a7b2245643235353139223a226564736967746b717577626a4a467a41464c7134345267527454564e777948774857624b386e47343855564b5069766b32635057505735345359335935534e4439786635463852795335424e665861595a4c453664776d554b70325541394275435a32227d2c22696e6e6572223a7b226e6f6e6365223a322c22636f6e74656e74223a7b22436f6c6c656374223a307d7d7d".into()
+    }
+
+    /// Collect the tweet 0 with the nonce 3
+    fn input_collect_2() -> BinInput {
+        "7b22706b6579223a7b2245643235353139223a226564706b75444d556d375935337770346778654c425875694168585a724c6e385842315238336b737676657348384c7038626d43664b227d2c227369676e6174757265223a7b2245643235353139223a22656473696774775a6d6376566470575361696836646a5057526172645668723154614b32786275646a7937686d7a6a65456e4b77766747346d50676455573478764254714452584e5348596f6a5973395a796d5968565469586d667a67323778624846227d2c22696e6e6572223a7b226e6f6e6365223a332c22636f6e74656e74223a7b22436f6c6c656374223a307d7d7d".into()
+    }
+
+    /// Reclaim the tweet 0 with the nonce 3
+    fn input_reclaim() -> BinInput {
+        "7b22706b6579223a7b2245643235353139223a226564706b75444d556d375935337770346778654c425875694168585a724c6e385842315238336b737676657348384c7038626d43664b227d2c227369676e6174757265223a7b2245643235353139223a226564736967745a54484a34416a6f6264534b76465043766259376874316d4b77726b5366514c4a4b354c395472614370557762686f4366536239334d424b5a675279314c4359784b447965766661687a766d797a4870576737776f75786d3773325871227d2c22696e6e6572223a7b226e6f6e6365223a332c22636f6e74656e74223a7b225265636c61696d223a307d7d7d".into()
+    }
+
+    /// Reclaim the tweet 0 with the nonce 4
+    fn input_reclaim_2() -> BinInput {
+        "7b22706b6579223a7b2245643235353139223a226564706b75444d556d375935337770346778654c425875694168585a724c6e385842315238336b737676657348384c7038626d43664b227d2c227369676e6174757265223a7b2245643235353139223a2265647369677473344a556f42385a4578326d7236477134665361464c45486157485965564e6f6936424d615462573235464c6b75475769487452317a5076545a58343248666344363531526d46396839666d36414a687a4a44783472624d54686b7363227d2c22696e6e6572223a7b226e6f6e6365223a342c22636f6e74656e74223a7b225265636c61696d223a307d7d7d".into()
+    }
+
+    /// Store an u64 in the storage
+    fn set_u64<R: Runtime>(host: &mut R, path: &str, value: u64) {
+        let path = RefPath::assert_from(path.as_bytes());
+        host.store_write(&path, &value.to_be_bytes(), 0).unwrap();
+    }
+
+    /// Store a string in the storage
+    fn set_string<R: Runtime>(host: &mut R, path: &str, value: &str) {
+        let path = RefPath::assert_from(path.as_bytes());
+        host.store_write(&path, value.as_bytes(), 0).unwrap();
+    }
+
+    /// Assert a string value in the storage
+    fn assert_string<R: Runtime>(host: &mut R, path: &str, expected: &str) {
+        let path = RefPath::assert_from(path.as_bytes());
+        let value = read_bytes(host, &path).unwrap().unwrap();
+        assert_eq!(expected.as_bytes(), value.as_slice());
+    }
+
+    /// Store an u32 in the storage
+    fn set_u32<R: Runtime>(host: &mut R, path: &str, value: u32) {
+        let path = RefPath::assert_from(path.as_bytes());
+        host.store_write(&path, &value.to_be_bytes(), 0).unwrap();
+    }
+
+    /// Add a transfer from the L1 token contract to the inbox
+    fn add_l1_message(host: &mut MockHost, message: L1Message) {
+        let mut bytes = vec![0x05];
+        message.bin_write(&mut bytes).unwrap();
+
+        let sender = ContractKt1Hash
+            .b58check_to_hash(L1_TOKEN_CONTRACT_ADDRESS)
+            .unwrap();
+        let source =
+            types::PublicKeyHash::from_b58check("tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv").unwrap();
+        let metadata = TransferMetadata::new(sender, source);
+        host.add_transfer(MichelsonBytes(bytes), &metadata);
+    }
+
+    /// The admin hides the tweet 0 with the nonce 1
+    fn input_admin_hide() -> BinInput {
+        "7b22706b6579223a7b2245643235353139223a226564706b7653565a7652365a37534879474e557a3839484e614c655779775234516d444c5376545457634e4d3742597735696e655250227d2c227369676e6174757265223a7b2245643235353139223a226564736967747637416e52457273465a7277754151633379666a514878487646397933514a555a596463533758695734455175594236754d56763469755357503256354d6d525158366578385443385939365a3858786442414c525651775751786978227d2c22696e6e6572223a7b226e6f6e6365223a312c22636f6e74656e74223a7b22486964655477656574223a307d7d7d".into()
+    }
+
+    /// tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv tries to hide the tweet 0 with the nonce 3
+    fn input_hide() -> BinInput {
+        "7b22706b6579223a7b2245643235353139223a226564706b75444d556d375935337770346778654c425875694168585a724c6e385842315238336b737676657348384c7038626d43664b227d2c227369676e6174757265223a7b2245643235353139223a2265647369677462555550675279544e6a413455413471436b696e61554c5a72354d51414844616f35575271485a554b41697735516d5452745356355879316576365a365559764859435551313331597975635731515463487a706544704e4d617a4165227d2c22696e6e6572223a7b226e6f6e6365223a332c22636f6e74656e74223a7b22486964655477656574223a307d7d7d".into()
+    }
+
+    /// Post a tweet with the root hash of the body "Hello world " repeated 1000 times and the nonce 1
+    fn input_post_ref() -> BinInput {
+        "7b22706b6579223a7b2245643235353139223a226564706b75444d556d375935337770346778654c425875694168585a724c6e385842315238336b737676657348384c7038626d43664b227d2c227369676e6174757265223a7b2245643235353139223a226564736967745a385a75455a36594b74476851504a4e6b35727a475063475551765637726a6d4473505251466f456f32647a44694671643767507a534a7147344e7a52423259336f6974396a67444348506f4e464a41714b724b536167573547577058227d2c22696e6e6572223a7b226e6f6e6365223a312c22636f6e74656e74223a7b22506f73745477656574526566223a7b22617574686f72223a7b22547a31223a22747a315146443957714c575a6d6d4175716e6e545050556a666175697459455764736876227d2c22726f6f745f68617368223a22303036643865333331363035663065653034313930376231653661626533353866653030363532633835643433653061383136653735303062346437353439366638227d7d7d7d".into()
+    }
+
+    /// Post a tweet with the root hash of the body "Hello world " repeated 4000 times and the nonce 1
+    fn input_post_ref_too_large() -> BinInput {
+        "7b22706b6579223a7b2245643235353139223a226564706b75444d556d375935337770346778654c425875694168585a724c6e385842315238336b737676657348384c7038626d43664b227d2c227369676e6174757265223a7b2245643235353139223a226564736967753537445643614a354b717a704b4450566a7439565a4235514853557957336b463934347946664d46664a4d6f58424d5a465268783241333536514e71556b51555a3135683471383264314c533231316b674a35787258734e6573317168227d2c22696e6e6572223a7b226e6f6e6365223a312c22636f6e74656e74223a7b22506f73745477656574526566223a7b22617574686f72223a7b22547a31223a22747a315146443957714c575a6d6d4175716e6e545050556a666175697459455764736876227d2c22726f6f745f68617368223a22303037393031653162346561346437303736613639613364356163336234666630623730623135613036393166333565636536646234313130333835613366346666227d7d7d7d".into()
+    }
+
+    /// Post a tweet with the hash of a preimage that is not a DAC page and the nonce 1
+    fn input_post_ref_invalid_page() -> BinInput {
+        "7b22706b6579223a7b2245643235353139223a226564706b75444d556d375935337770346778654c425875694168585a724c6e385842315238336b737676657348384c7038626d43664b227d2c227369676e6174757265223a7b2245643235353139223a2265647369677466586b704837396f644c754e4c4d524c70654e4e656e7a763874747758516363525738426b624739673335627a7855717a6f726853414b57373832704339756358514c4c514d4d684b674551713254743374565471336157316d77696b227d2c22696e6e6572223a7b226e6f6e6365223a312c22636f6e74656e74223a7b22506f73745477656574526566223a7b22617574686f72223a7b22547a31223a22747a315146443957714c575a6d6d4175716e6e545050556a666175697459455764736876227d2c22726f6f745f68617368223a22303061386437666237346133373231393831326664373362666565386331356534336434666338623236353536316636636139326566353064616565626431333837227d7d7d7d".into()
+    }
+
+    /// tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv authorizes the session key tz1Rf22vznYLW8bVH1A3M9VUS4DLyf58gjDA
+    /// to post and like until the level 10, with the nonce 1
+    fn input_authorize_session_key() -> BinInput {
+        "7b22706b6579223a7b2245643235353139223a226564706b75444d556d375935337770346778654c425875694168585a724c6e385842315238336b737676657348384c7038626d43664b227d2c227369676e6174757265223a7b2245643235353139223a226564736967746236383942775844644342433576413844744b53396e5548327347757656523254564153504144666f6e4879577a75454b556d4e426257357874416d4671696e59676a7a4c67505a685859624b4b66756263547476716373654c6b394a227d2c22696e6e6572223a7b226e6f6e6365223a312c22636f6e74656e74223a7b22417574686f72697a6553657373696f6e4b6579223a7b226b6579223a7b22547a31223a22747a3152663232767a6e594c57386256483141334d3956555334444c79663538676a4441227d2c226578706972795f6c6576656c223a31302c22616c6c6f7765645f6f7073223a5b22506f73745477656574222c224c696b655477656574225d7d7d7d7d".into()
+    }
+
+    /// The session key posts "Hello world" for tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv with the nonce 2
+    fn input_session_post() -> BinInput {
+        "7b22706b6579223a7b2245643235353139223a226564706b7477474c4d68617456483872646a733244786e46794c466d6f563731636238354e45747a477077573475454d324a48354852227d2c227369676e6174757265223a7b2245643235353139223a226564736967746251365175474847354c44354a72777237576f65556a5a35535675515732535a517639555a5766795547507a7a7739367935745046706f45665a457a36687a31685232546e33624d71394c643750427337687a6d58396831673734394d227d2c22696e6e6572223a7b226e6f6e6365223a322c22636f6e74656e74223a7b22506f73745477656574223a7b22617574686f72223a7b22547a31223a22747a315146443957714c575a6d6d4175716e6e545050556a666175697459455764736876227d2c22636f6e74656e74223a2248656c6c6f20776f726c64227d7d2c226163636f756e74223a7b22547a31223a22747a315146443957714c575a6d6d4175716e6e545050556a666175697459455764736876227d7d7d".into()
+    }
+
+    /// The session key transfers the tweet 0 to tz1hEW4Bz2tLWBJbefwx6wCqdcX4CcjyLXdq with the nonce 3
+    fn input_session_transfer() -> BinInput {
+        "7b22706b6579223a7b2245643235353139223a226564706b7477474c4d68617456483872646a733244786e46794c466d6f563731636238354e45747a477077573475454d324a48354852227d2c227369676e6174757265223a7b2245643235353139223a2265647369677463556a4735676b58445a6350373233314c746864786f6b336d37545173734a396e4c4b395761536154726e546671353737475732484e5659657a635a69536d584163576155744534787744656a5053776447454b37664c4d4d6e795473227d2c22696e6e6572223a7b226e6f6e6365223a332c22636f6e74656e74223a7b225472616e73666572223a7b2264657374696e6174696f6e223a7b22547a31223a22747a3168455734427a32744c57424a6265667778367743716463583443636a794c586471227d2c2274776565745f6964223a307d7d2c226163636f756e74223a7b22547a31223a22747a315146443957714c575a6d6d4175716e6e545050556a666175697459455764736876227d7d7d".into()
+    }
+
+    /// The session key likes the tweet 0 with the nonce 3
+    fn input_session_like() -> BinInput {
+        "7b22706b6579223a7b2245643235353139223a226564706b7477474c4d68617456483872646a733244786e46794c466d6f563731636238354e45747a477077573475454d324a48354852227d2c227369676e6174757265223a7b2245643235353139223a226564736967745a6e45766967556b3634396b687a365a756253565774444c7432694a31667a474c34786b5268695270773768586445454645366f55746456464d593937377131616b3846575455766359624a733770787248594c6171357a6545384866227d2c22696e6e6572223a7b226e6f6e6365223a332c22636f6e74656e74223a7b224c696b655477656574223a307d2c226163636f756e74223a7b22547a31223a22747a315146443957714c575a6d6d4175716e6e545050556a666175697459455764736876227d7d7d".into()
+    }
+
+    /// tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv revokes the session key with the nonce 2
+    fn input_revoke_session_key() -> BinInput {
+        "7b22706b6579223a7b2245643235353139223a226564706b75444d556d375935337770346778654c425875694168585a724c6e385842315238336b737676657348384c7038626d43664b227d2c227369676e6174757265223a7b2245643235353139223a226564736967746f32517876714532784c4848656d79426a393339745a66545142766d673855424264436b72454b327775656e635a633338516964666b7431344339584272666475325a665a577679366178797a384c5736696566484a68644d4c587436227d2c22696e6e6572223a7b226e6f6e6365223a322c22636f6e74656e74223a7b225265766f6b6553657373696f6e4b6579223a7b22547a31223a22747a3152663232767a6e594c57386256483141334d3956555334444c79663538676a4441227d7d7d7d".into()
+    }
+
+    /// tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv migrates to tz1Rf22vznYLW8bVH1A3M9VUS4DLyf58gjDA with the nonce 3
+    fn input_migrate_account() -> BinInput {
+        "7b22706b6579223a7b2245643235353139223a226564706b75444d556d375935337770346778654c425875694168585a724c6e385842315238336b737676657348384c7038626d43664b227d2c227369676e6174757265223a7b2245643235353139223a226564736967747a53583939485178796f425233684164366e6f736a477237414b47327a7333695a44735239587a76554c7433534d464a3547427a62517062656241376f6f52774d67684441676875756b4e686434334d75717279325961465639503472227d2c22696e6e6572223a7b226e6f6e6365223a332c22636f6e74656e74223a7b224d6967726174654163636f756e74223a7b226e65775f6f776e6572223a7b22547a31223a22747a3152663232767a6e594c57386256483141334d3956555334444c79663538676a4441227d7d7d7d7d".into()
+    }
+
+    /// tz1Rf22vznYLW8bVH1A3M9VUS4DLyf58gjDA posts "Hello world" with the nonce 4
+    fn input_post_after_migration() -> BinInput {
+        "7b22706b6579223a7b2245643235353139223a226564706b7477474c4d68617456483872646a733244786e46794c466d6f563731636238354e45747a477077573475454d324a48354852227d2c227369676e6174757265223a7b2245643235353139223a226564736967745a586d5972794c33393374665134513844544c6d7a5a356f31656947394757434c767a57314657766b4c575151324b5475523662433641315534446e735235484257475546595853443334414d343236786f676d6b6b58554145324e6d227d2c22696e6e6572223a7b226e6f6e6365223a342c22636f6e74656e74223a7b22506f73745477656574223a7b22617574686f72223a7b22547a31223a22747a3152663232767a6e594c57386256483141334d3956555334444c79663538676a4441227d2c22636f6e74656e74223a2248656c6c6f20776f726c64227d7d7d7d".into()
+    }
+
+    /// tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv migrates to tz1hEW4Bz2tLWBJbefwx6wCqdcX4CcjyLXdq with the nonce 1
+    fn input_migrate_to_admin() -> BinInput {
+        "7b22706b6579223a7b2245643235353139223a226564706b75444d556d375935337770346778654c425875694168585a724c6e385842315238336b737676657348384c7038626d43664b227d2c227369676e6174757265223a7b2245643235353139223a2265647369677531786f57447771424c6676786552325a435a39446a45346b347a416b7353626f623451466376627447576869435a436373375970413476704363336d6a686d375734564d346653544c556f414250657341454878674e4c724b56374150227d2c22696e6e6572223a7b226e6f6e6365223a312c22636f6e74656e74223a7b224d6967726174654163636f756e74223a7b226e65775f6f776e6572223a7b22547a31223a22747a3168455734427a32744c57424a6265667778367743716463583443636a794c586471227d7d7d7d7d".into()
+    }
+
+    /// tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv becomes a 2-of-3 multisig account
+    /// with tz1hEW4Bz2tLWBJbefwx6wCqdcX4CcjyLXdq and tz1Rf22vznYLW8bVH1A3M9VUS4DLyf58gjDA, with the nonce 1
+    fn input_set_signers() -> BinInput {
+        "7b22706b6579223a7b2245643235353139223a226564706b75444d556d375935337770346778654c425875694168585a724c6e385842315238336b737676657348384c7038626d43664b227d2c227369676e6174757265223a7b2245643235353139223a2265647369677466706a4659324252547074734a5131484878637131694d39387239446d5a726451716a664a794d31703643555a5578365a31374a536a4e7a774d435631396f334777375966446b3433717137324b706736544576786769546f34685862227d2c22696e6e6572223a7b226e6f6e6365223a312c22636f6e74656e74223a7b225365745369676e657273223a7b227468726573686f6c64223a322c227369676e657273223a5b7b22547a31223a22747a315146443957714c575a6d6d4175716e6e545050556a666175697459455764736876227d2c7b22547a31223a22747a3168455734427a32744c57424a6265667778367743716463583443636a794c586471227d2c7b22547a31223a22747a3152663232767a6e594c57386256483141334d3956555334444c79663538676a4441227d5d7d7d7d7d".into()
+    }
+
+    /// The multisig account posts "Hello world" with only its own signature and the nonce 2
+    fn input_multisig_one_signature() -> BinInput {
+        "7b22706b6579223a7b2245643235353139223a226564706b75444d556d375935337770346778654c425875694168585a724c6e385842315238336b737676657348384c7038626d43664b227d2c227369676e6174757265223a7b2245643235353139223a226564736967745a6647345a51346263746f65427a3166437053745141525473695154466974567067756652786d366b365a743478596e3432675647694d447634426236376331536d6f793270514b376569666533387148327455756f69627344597a6d227d2c22696e6e6572223a7b226e6f6e6365223a322c22636f6e74656e74223a7b22506f73745477656574223a7b22617574686f72223a7b22547a31223a22747a315146443957714c575a6d6d4175716e6e545050556a666175697459455764736876227d2c22636f6e74656e74223a2248656c6c6f20776f726c64227d7d7d7d".into()
+    }
+
+    /// The multisig account posts "Hello world" signed by tz1Rf22vznYLW8bVH1A3M9VUS4DLyf58gjDA
+    /// and cosigned by tz1hEW4Bz2tLWBJbefwx6wCqdcX4CcjyLXdq, with the nonce 2
+    fn input_multisig_two_signatures() -> BinInput {
+        "7b22706b6579223a7b2245643235353139223a226564706b7477474c4d68617456483872646a733244786e46794c466d6f563731636238354e45747a477077573475454d324a48354852227d2c227369676e6174757265223a7b2245643235353139223a226564736967746251365175474847354c44354a72777237576f65556a5a35535675515732535a517639555a5766795547507a7a7739367935745046706f45665a457a36687a31685232546e33624d71394c643750427337687a6d58396831673734394d227d2c22696e6e6572223a7b226e6f6e6365223a322c22636f6e74656e74223a7b22506f73745477656574223a7b22617574686f72223a7b22547a31223a22747a315146443957714c575a6d6d4175716e6e545050556a666175697459455764736876227d2c22636f6e74656e74223a2248656c6c6f20776f726c64227d7d2c226163636f756e74223a7b22547a31223a22747a315146443957714c575a6d6d4175716e6e545050556a666175697459455764736876227d7d2c22636f7369676e617475726573223a5b7b22706b6579223a7b2245643235353139223a226564706b7653565a7652365a37534879474e557a3839484e614c655779775234516d444c5376545457634e4d3742597735696e655250227d2c227369676e6174757265223a7b2245643235353139223a226564736967753734386a34785a32514a31483369364d5570795976764e7a484c416d684d7647465375546845354b4c706863464e327037596d48476f61527a6977756e597a7035464d7a5873586955735a557331774d63756d435959586d4a4353596e227d7d5d7d".into()
+    }
+
+    /// Same message, cosigned a second time by tz1Rf22vznYLW8bVH1A3M9VUS4DLyf58gjDA
+    fn input_multisig_same_signer() -> BinInput {
+        "7b22706b6579223a7b2245643235353139223a226564706b7477474c4d68617456483872646a733244786e46794c466d6f563731636238354e45747a477077573475454d324a48354852227d2c227369676e6174757265223a7b2245643235353139223a226564736967746251365175474847354c44354a72777237576f65556a5a35535675515732535a517639555a5766795547507a7a7739367935745046706f45665a457a36687a31685232546e33624d71394c643750427337687a6d58396831673734394d227d2c22696e6e6572223a7b226e6f6e6365223a322c22636f6e74656e74223a7b22506f73745477656574223a7b22617574686f72223a7b22547a31223a22747a315146443957714c575a6d6d4175716e6e545050556a666175697459455764736876227d2c22636f6e74656e74223a2248656c6c6f20776f726c64227d7d2c226163636f756e74223a7b22547a31223a22747a315146443957714c575a6d6d4175716e6e545050556a666175697459455764736876227d7d2c22636f7369676e617475726573223a5b7b22706b6579223a7b2245643235353139223a226564706b7477474c4d68617456483872646a733244786e46794c466d6f563731636238354e45747a477077573475454d324a48354852227d2c227369676e6174757265223a7b2245643235353139223a226564736967746251365175474847354c44354a72777237576f65556a5a35535675515732535a517639555a5766795547507a7a7739367935745046706f45665a457a36687a31685232546e33624d71394c643750427337687a6d58396831673734394d227d7d5d7d".into()
+    }
+
+    /// tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv posts "Tweet 2" with the nonce 2
+    fn input_post_nonce_2() -> BinInput {
+        "7b22706b6579223a7b2245643235353139223a226564706b75444d556d375935337770346778654c425875694168585a724c6e385842315238336b737676657348384c7038626d43664b227d2c227369676e6174757265223a7b2245643235353139223a22656473696775355576536b4843366e546f323778774b4237645a7565697652634658664b5162416b4642544347566f616436577a71414d517963513145354531516238716d354a4572666362685252714e53586b38334b6e464b576e68715a78446868227d2c22696e6e6572223a7b226e6f6e6365223a322c22636f6e74656e74223a7b22506f73745477656574223a7b22617574686f72223a7b22547a31223a22747a315146443957714c575a6d6d4175716e6e545050556a666175697459455764736876227d2c22636f6e74656e74223a2254776565742032227d7d7d7d".into()
+    }
+
+    /// tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv posts "Tweet 3" with the nonce 3
+    fn input_post_nonce_3() -> BinInput {
+        "7b22706b6579223a7b2245643235353139223a226564706b75444d556d375935337770346778654c425875694168585a724c6e385842315238336b737676657348384c7038626d43664b227d2c227369676e6174757265223a7b2245643235353139223a22656473696774745938694a3445385874415862716b534b67627a6e53616b4666484b47546b79516b31625a664148693470464d645777707038387671684651614e397161555863526378767a6e3776426f324a4347426f344e374562586933734e346e227d2c22696e6e6572223a7b226e6f6e6365223a332c22636f6e74656e74223a7b22506f73745477656574223a7b22617574686f72223a7b22547a31223a22747a315146443957714c575a6d6d4175716e6e545050556a666175697459455764736876227d2c22636f6e74656e74223a2254776565742033227d7d7d7d".into()
+    }
+
+    /// tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv posts "Tweet 6" with the nonce 6
+    fn input_post_nonce_6() -> BinInput {
+        "7b22706b6579223a7b2245643235353139223a226564706b75444d556d375935337770346778654c425875694168585a724c6e385842315238336b737676657348384c7038626d43664b227d2c227369676e6174757265223a7b2245643235353139223a226564736967746b68504d4b6377623635353175674c6f446e7255324346784a4a556445694a684a31734d63656f4e413166513953724d5a6d376b54616a62363772554136733931476968467a3867505a78574b6572393864616d61566f557042504633227d2c22696e6e6572223a7b226e6f6e6365223a362c22636f6e74656e74223a7b22506f73745477656574223a7b22617574686f72223a7b22547a31223a22747a315146443957714c575a6d6d4175716e6e545050556a666175697459455764736876227d2c22636f6e74656e74223a2254776565742036227d7d7d7d".into()
+    }
+
+    /// tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv edits the tweet 0 to "Hello world!" with the nonce 2
+    fn input_edit() -> BinInput {
+        "7b22706b6579223a7b2245643235353139223a226564706b75444d556d375935337770346778654c425875694168585a724c6e385842315238336b737676657348384c7038626d43664b227d2c227369676e6174757265223a7b2245643235353139223a226564736967746e486851457463656459724d3268727a44575834615336596b794d3977486b556464344d6531356d63476145394b447a376e6f364d424a544e615062634e63766d75454176774a7673366878416a4d615078624851554c485971773644227d2c22696e6e6572223a7b226e6f6e6365223a322c22636f6e74656e74223a7b22456469745477656574223a7b2274776565745f6964223a302c22636f6e74656e74223a2248656c6c6f20776f726c6421227d7d7d7d".into()
+    }
+
+    /// tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv edits the tweet 0 to "Hello world!!" with the nonce 3
+    fn input_edit_2() -> BinInput {
+        "7b22706b6579223a7b2245643235353139223a226564706b75444d556d375935337770346778654c425875694168585a724c6e385842315238336b737676657348384c7038626d43664b227d2c227369676e6174757265223a7b2245643235353139223a226564736967746e75505237484753445072546939695248426b6e7267544e6d51556e466d74326238717050425265336534706d4341774c326b53323872637570696771664e4c544661346b79554172363564486471504d445453427279386b42514566227d2c22696e6e6572223a7b226e6f6e6365223a332c22636f6e74656e74223a7b22456469745477656574223a7b2274776565745f6964223a302c22636f6e74656e74223a2248656c6c6f20776f726c642121227d7d7d7d".into()
+    }
+
+    /// tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv posts the poll "Best color?" with the options "Red" and "Blue"
+    /// closing at the level 10, with the nonce 1
+    fn input_post_poll() -> BinInput {
+        "7b22706b6579223a7b2245643235353139223a226564706b75444d556d375935337770346778654c425875694168585a724c6e385842315238336b737676657348384c7038626d43664b227d2c227369676e6174757265223a7b2245643235353139223a226564736967753242467a5651373943424253667571516e426e42457575544648713372354e69386a393466563477544e77743844365343783439556d567674566d77376d76416a656f7350615a48666b6841477a4d6e78394676716f78414733334d43227d2c22696e6e6572223a7b226e6f6e6365223a312c22636f6e74656e74223a7b22506f7374506f6c6c223a7b22636f6e74656e74223a224265737420636f6c6f723f222c226f7074696f6e73223a5b22526564222c22426c7565225d2c22636c6f7365735f61745f6c6576656c223a31307d7d7d7d".into()
+    }
+
+    /// tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv votes for the option 1 of the poll 0 with the nonce 2
+    fn input_vote() -> BinInput {
+        "7b22706b6579223a7b2245643235353139223a226564706b75444d556d375935337770346778654c425875694168585a724c6e385842315238336b737676657348384c7038626d43664b227d2c227369676e6174757265223a7b2245643235353139223a22656473696774684e75437a743442547943554645644b41317457347a726638776b5475645a726f657a3267325263485766795a4a526f766f5a6a637437434c773153615861553868784375575633424641584d56796f3369384a51646e794761787867227d2c22696e6e6572223a7b226e6f6e6365223a322c22636f6e74656e74223a7b22566f7465223a7b2274776565745f6964223a302c226f7074696f6e223a317d7d7d7d".into()
+    }
+
+    /// tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv votes for the option 0 of the poll 0 with the nonce 3
+    fn input_vote_again() -> BinInput {
+        "7b22706b6579223a7b2245643235353139223a226564706b75444d556d375935337770346778654c425875694168585a724c6e385842315238336b737676657348384c7038626d43664b227d2c227369676e6174757265223a7b2245643235353139223a2265647369677477714139387754314e51777175525479795641616238486a4757516a5646703754427631666e6b3763364a6e6b5277534752516977775247703332425044384836456833536b5637695943704a6d553247423768727347577239775544227d2c22696e6e6572223a7b226e6f6e6365223a332c22636f6e74656e74223a7b22566f7465223a7b2274776565745f6964223a302c226f7074696f6e223a307d7d7d7d".into()
+    }
+
+    /// The admin votes for the option 1 of the poll 0 with the nonce 1
+    fn input_admin_vote() -> BinInput {
+        "7b22706b6579223a7b2245643235353139223a226564706b7653565a7652365a37534879474e557a3839484e614c655779775234516d444c5376545457634e4d3742597735696e655250227d2c227369676e6174757265223a7b2245643235353139223a226564736967746f555348584d716167443974645271674873566b5659537548574c65414367416e6148426357385775566b57516b476a58465072665a4435574b5a4758784e74377a54733139476e34427451726f316f4e56425252744b50637a744d4c227d2c22696e6e6572223a7b226e6f6e6365223a312c22636f6e74656e74223a7b22566f7465223a7b2274776565745f6964223a302c226f7074696f6e223a317d7d7d7d".into()
+    }
+
+    /// tz1Rf22vznYLW8bVH1A3M9VUS4DLyf58gjDA votes for the option 2 of the poll 0 with the nonce 1
+    fn input_invalid_vote() -> BinInput {
+        "7b22706b6579223a7b2245643235353139223a226564706b7477474c4d68617456483872646a733244786e46794c466d6f563731636238354e45747a477077573475454d324a48354852227d2c227369676e6174757265223a7b2245643235353139223a226564736967746b4376644762435538437778477147636b3756756a4c434672447076646342337033675172696a48675165767467743736517a76476d4c7248356263325252616172414c776b435166637850565977777757775652664d4e3872435133227d2c22696e6e6572223a7b226e6f6e6365223a312c22636f6e74656e74223a7b22566f7465223a7b2274776565745f6964223a302c226f7074696f6e223a327d7d7d7d".into()
+    }
+
+    /// tz1Rf22vznYLW8bVH1A3M9VUS4DLyf58gjDA retweets the tweet 0 with the nonce 1
+    fn input_retweet() -> BinInput {
+        "7b22706b6579223a7b2245643235353139223a226564706b7477474c4d68617456483872646a733244786e46794c466d6f563731636238354e45747a477077573475454d324a48354852227d2c227369676e6174757265223a7b2245643235353139223a22656473696775316f54636f315568594138585457716d65417551733358624277336a393645424a5a415a53486e5a595865677052577379503533626779614d774d4439366641356b464a4e7736664b48626e393137317a6657526a72737158696a3848227d2c22696e6e6572223a7b226e6f6e6365223a312c22636f6e74656e74223a7b2252657477656574223a307d7d7d".into()
+    }
+
+    /// tz1Rf22vznYLW8bVH1A3M9VUS4DLyf58gjDA retweets the tweet 0 with the nonce 2
+    fn input_retweet_again() -> BinInput {
+        "7b22706b6579223a7b2245643235353139223a226564706b7477474c4d68617456483872646a733244786e46794c466d6f563731636238354e45747a477077573475454d324a48354852227d2c227369676e6174757265223a7b2245643235353139223a226564736967746f325a696953666668644d457341767336424742457a706265766a544e6f6d4c44467431484a4e477153645a544563347a346d373733424b524472313334534372625476355455457746384c776378776670614b4b324a623555426b66227d2c22696e6e6572223a7b226e6f6e6365223a322c22636f6e74656e74223a7b2252657477656574223a307d7d7d".into()
+    }
+
+    /// tz1Rf22vznYLW8bVH1A3M9VUS4DLyf58gjDA quotes the tweet 0 with "So true" and the nonce 3
+    fn input_quote() -> BinInput {
+        "7b22706b6579223a7b2245643235353139223a226564706b7477474c4d68617456483872646a733244786e46794c466d6f563731636238354e45747a477077573475454d324a48354852227d2c227369676e6174757265223a7b2245643235353139223a22656473696774706271746b787137486e656239694e4b367331435547326439705053316356413639626e70394d6941777679513537394177516875464a75336444786e396a616f526564336a694b7442596836516d733351637a66326161556a6d5069227d2c22696e6e6572223a7b226e6f6e6365223a332c22636f6e74656e74223a7b2251756f7465223a7b2274776565745f6964223a302c22636f6e74656e74223a22536f2074727565227d7d7d7d".into()
+    }
+
+    /// The admin posts "Hello world" with the nonce 1
+    fn input_admin_post() -> BinInput {
+        "7b22706b6579223a7b2245643235353139223a226564706b7653565a7652365a37534879474e557a3839484e614c655779775234516d444c5376545457634e4d3742597735696e655250227d2c227369676e6174757265223a7b2245643235353139223a22656473696775334b725963756a367932505a43434c7a6e55324b65396868626d413261416a554e71737846665171746932627233356e644d6b68696d466e774a61314a546b67764a507069394e3853454e7142464a736e6962524d41463233714e6b44227d2c22696e6e6572223a7b226e6f6e6365223a312c22636f6e74656e74223a7b22506f73745477656574223a7b22617574686f72223a7b22547a31223a22747a3168455734427a32744c57424a6265667778367743716463583443636a794c586471227d2c22636f6e74656e74223a2248656c6c6f20776f726c64227d7d7d7d".into()
+    }
+
+    /// tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv retweets the tweet 1 with the nonce 2
+    fn input_retweet_admin_tweet() -> BinInput {
+        "7b22706b6579223a7b2245643235353139223a226564706b75444d556d375935337770346778654c425875694168585a724c6e385842315238336b737676657348384c7038626d43664b227d2c227369676e6174757265223a7b2245643235353139223a22656473696774636d544e6f655a35773664777447746252333777736535316b3770323131634a34624b6238537336755a367057357932753255445a725942374279676a3858563462584654425a483569713272444e6a547a326e50524a646935437943227d2c22696e6e6572223a7b226e6f6e6365223a322c22636f6e74656e74223a7b2252657477656574223a317d7d7d".into()
+    }
+
+    /// The admin pauses the kernel, rejecting the messages, with the nonce 1
+    fn input_admin_pause_reject() -> BinInput {
+        "7b22706b6579223a7b2245643235353139223a226564706b7653565a7652365a37534879474e557a3839484e614c655779775234516d444c5376545457634e4d3742597735696e655250227d2c227369676e6174757265223a7b2245643235353139223a22656473696774784a53794d57537866746845594e68514e79427133515064454b4450724a5062764d35434752565a4b6d616b4e59617745436253345354686e6571467244634b65725473517a376e4c61704e6b7045456853466577794863514442354d227d2c22696e6e6572223a7b226e6f6e6365223a312c22636f6e74656e74223a7b225061757365223a2252656a656374227d7d7d".into()
+    }
+
+    /// The admin pauses the kernel, deferring the messages, with the nonce 1
+    fn input_admin_pause_defer() -> BinInput {
+        "7b22706b6579223a7b2245643235353139223a226564706b7653565a7652365a37534879474e557a3839484e614c655779775234516d444c5376545457634e4d3742597735696e655250227d2c227369676e6174757265223a7b2245643235353139223a226564736967746b6f475a715161506f6b6674334e48794756555155663771456a3753745843366a69506b786a6d7733696f435a514d377a634c456378524a7677356d6f6f53645856586659783351554378787a794d41513244446b4a7965764d4a6650227d2c22696e6e6572223a7b226e6f6e6365223a312c22636f6e74656e74223a7b225061757365223a224465666572227d7d7d".into()
+    }
+
+    /// The admin unpauses the kernel with the nonce 2
+    fn input_admin_unpause() -> BinInput {
+        "7b22706b6579223a7b2245643235353139223a226564706b7653565a7652365a37534879474e557a3839484e614c655779775234516d444c5376545457634e4d3742597735696e655250227d2c227369676e6174757265223a7b2245643235353139223a22656473696774623646447371376d3973466168443171753178775843626d613351464233755173364a4d4863675464745272445463726e7478744e70626b384b5647656b7375486a4c36426e484156374e4c3566456271657644314346584d70747762227d2c22696e6e6572223a7b226e6f6e6365223a322c22636f6e74656e74223a22556e7061757365227d7d".into()
+    }
+
+    /// tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv tries to pause the kernel with the nonce 1
+    fn input_pause() -> BinInput {
+        "7b22706b6579223a7b2245643235353139223a226564706b75444d556d375935337770346778654c425875694168585a724c6e385842315238336b737676657348384c7038626d43664b227d2c227369676e6174757265223a7b2245643235353139223a22656473696774696a78716f5234685969725232676a6a626a536e57447879346478524d5a7038375676354164366e4a664376464179337a7166756358784c695262544a4a47514b456f7a454a5578466b6167446f44356551374550516e73526b357974227d2c22696e6e6572223a7b226e6f6e6365223a312c22636f6e74656e74223a7b225061757365223a2252656a656374227d7d7d".into()
+    }
+
+    /// Add the preimages of the body to the host and return the hex encoded root hash
+    fn set_preimages(host: &mut MockHost, body: &[u8]) -> String {
+        let root_hash = prepare_preimages(body, |_, page| {
+            host.set_preimage(page);
+        })
+        .unwrap();
+        hex::encode(root_hash.as_ref())
+    }
+
+    const ADMIN: &str = "tz1hEW4Bz2tLWBJbefwx6wCqdcX4CcjyLXdq";
+
     fn next_input<R: Runtime>(host: &mut R) -> Message {
-        read_input(host).unwrap().0
+        match read_input(host).unwrap().0 {
+            Input::Message(message) => message,
+            _ => panic!("The next input should be a tzwitter message"),
+        }
     }
 
     #[test]
-    fn test_step() {
-        let input = input_1();
-        // let inputs = [input.as_slice()].into_iter();
+    fn collect_tweet() {
         let mut host = MockHost::default();
-        host.add_external(input);
 
-        // host.as_mut().add_next_inputs(0, inputs);
+        host.add_external(input_1());
+        host.add_external(input_collect());
 
         let message = next_input(&mut host);
-        let res = step(&mut host, message, 0);
+        let res_1 = verify_and_step(&mut host, message, 0);
+        let message = next_input(&mut host);
+        let res_2 = verify_and_step(&mut host, message, 0);
 
-        assert!(res.is_ok());
+        assert!(res_1.is_ok());
+        assert!(res_2.is_ok());
 
-        assert_exist(&mut host, "/tweets/0");
-        assert_u64(&mut host, "/tweets/0/likes", Some(0));
+        assert_eq!(host.outbox_at(host.level()).len(), 1);
+        assert_exist(&mut host, "/tweets/0/collected_level");
+        assert_u64(&mut host, "/tweets/0/collect/outbox_index", Some(0));
         assert_exist(
             &mut host,
-            "/accounts/tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv/tweets/owned/0",
+            "/accounts/tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv/collected/0",
         );
-        assert_exist(
+        assert_not_exists(
             &mut host,
-            "/accounts/tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv/tweets/written/0",
+            "/accounts/tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv/collecting/0",
         );
     }
 
     #[test]
-    fn test_replay_attack() {
-        let input = input_1();
+    fn collect_tweet_with_full_outbox() {
         let mut host = MockHost::default();
-        host.add_external(input.clone());
-        host.add_external(input);
+
+        // Fill the outbox of the current level
+        while host.write_output(&[0x00]).is_ok() {}
+        let full_outbox_size = host.outbox_at(host.level()).len();
+
+        host.add_external(input_1());
+        host.add_external(input_collect());
 
         let message = next_input(&mut host);
-        let res1 = step(&mut host, message, 0);
+        let res_1 = verify_and_step(&mut host, message, 0);
         let message = next_input(&mut host);
-        let res2 = step(&mut host, message, 0);
+        let res_2 = verify_and_step(&mut host, message, 0);
+
+        assert!(res_1.is_ok());
+        assert!(res_2.is_ok());
+
+        // The message is queued, the tweet is not collected yet
+        assert_eq!(host.outbox_at(host.level()).len(), full_outbox_size);
+        assert_exist(&mut host, "/outbox/queue/0/payload");
+        assert_exist(
+            &mut host,
+            "/accounts/tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv/collecting/0",
+        );
+        assert_not_exists(
+            &mut host,
+            "/accounts/tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv/collected/0",
+        );
 
-        assert!(res1.is_ok());
-        assert!(res2.is_err());
+        // The queue is drained at the next levels
+        host.run_level(entry);
+        host.run_level(entry);
+
+        assert_not_exists(&mut host, "/outbox/queue/0");
+        assert_u64(&mut host, "/outbox/head", Some(1));
+        assert_exist(
+            &mut host,
+            "/accounts/tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv/collected/0",
+        );
+        assert_not_exists(
+            &mut host,
+            "/accounts/tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv/collecting/0",
+        );
     }
 
     #[test]
-    fn test_identical_tweets() {
-        let input_1 = input_1();
-        let input_2 = input_2();
+    fn collect_finalized_by_l1_contract() {
         let mut host = MockHost::default();
-        host.add_external(input_1);
-        host.add_external(input_2);
+
+        host.add_external(input_1());
+        host.add_external(input_collect());
 
         let message = next_input(&mut host);
-        let res_1 = step(&mut host, message, 0);
+        let _ = verify_and_step(&mut host, message, 0);
         let message = next_input(&mut host);
-        let res_2 = step(&mut host, message, 0);
+        let _ = verify_and_step(&mut host, message, 0);
 
-        assert!(res_1.is_ok());
-        assert!(res_2.is_ok());
+        assert_not_exists(&mut host, "/tweets/0/collect/finalized");
 
-        assert_u64(&mut host, "/constants/tweet-counter", Some(2));
-        assert_exist(&mut host, "/tweets/0");
-        assert_exist(&mut host, "/tweets/1");
+        // The L1 contract sends the id of the minted tweet
+        add_l1_message(&mut host, L1Message::Minted(MichelsonInt::from(0)));
+        host.run_level(entry);
+
+        assert_exist(&mut host, "/tweets/0/collect/finalized");
     }
 
     #[test]
-    fn test_like() {
-        let input_1 = input_1();
-        let input_2 = input_like();
+    fn collect_again_after_timeout() {
         let mut host = MockHost::default();
 
-        host.add_external(input_1);
-        host.add_external(input_2);
+        host.add_external(input_1());
+        host.add_external(input_collect());
+        host.add_external(input_collect_2());
 
         let message = next_input(&mut host);
-        let res_1 = step(&mut host, message, 0);
+        let res_1 = verify_and_step(&mut host, message, 0);
         let message = next_input(&mut host);
-        let res_2 = step(&mut host, message, 0);
+        let res_2 = verify_and_step(&mut host, message, 0);
+        let message = next_input(&mut host);
+        let res_3 = verify_and_step(&mut host, message, COLLECT_TIMEOUT + 1);
 
         assert!(res_1.is_ok());
         assert!(res_2.is_ok());
+        assert!(res_3.is_ok());
 
-        assert_u64(&mut host, "/tweets/0/likes", Some(1));
+        assert_eq!(host.outbox_at(host.level()).len(), 2);
+        // The second message is the first of the outbox of its level
+        let level_path = RefPath::assert_from(b"/tweets/0/collect/outbox_level");
+        let outbox_level = read_u32(&mut host, &level_path).unwrap();
+        assert_eq!(Some(COLLECT_TIMEOUT + 1), outbox_level);
+        assert_u64(&mut host, "/tweets/0/collect/outbox_index", Some(0));
     }
 
     #[test]
-    fn test_like_two_times_same_tweet() {
-        let input_1 = input_1();
-        let input_2 = input_like();
-        let input_3 = input_like_2();
-
+    fn collect_again_before_timeout() {
         let mut host = MockHost::default();
 
-        host.add_external(input_1);
-        host.add_external(input_2);
-        host.add_external(input_3);
+        host.add_external(input_1());
+        host.add_external(input_collect());
+        host.add_external(input_collect_2());
 
         let message = next_input(&mut host);
-        let res_1 = step(&mut host, message, 0);
+        let _ = verify_and_step(&mut host, message, 0);
         let message = next_input(&mut host);
-        let res_2 = step(&mut host, message, 0);
+        let _ = verify_and_step(&mut host, message, 0);
         let message = next_input(&mut host);
-        let res_3 = step(&mut host, message, 0);
-
-        assert!(res_1.is_ok());
-        assert!(res_2.is_ok());
-        assert!(res_3.is_err());
+        let res = verify_and_step(&mut host, message, COLLECT_TIMEOUT);
 
-        assert_u64(&mut host, "/tweets/0/likes", Some(1));
+        assert!(res.is_err());
+        assert_eq!(host.outbox_at(host.level()).len(), 1);
     }
 
     #[test]
-    fn transfer_tweet() {
-        let input_1 = input_1();
-        let input_2 = input_transfer();
-
+    fn reclaim_tweet() {
         let mut host = MockHost::default();
 
-        host.add_external(input_1);
-        host.add_external(input_2);
+        host.add_external(input_1());
+        host.add_external(input_collect());
+        host.add_external(input_reclaim());
+        host.add_external(input_reclaim_2());
 
         let message = next_input(&mut host);
-        let res_1 = step(&mut host, message, 0);
+        let _ = verify_and_step(&mut host, message, 0);
+        let message = next_input(&mut host);
+        let _ = verify_and_step(&mut host, message, 0);
+        let message = next_input(&mut host);
+        let res_1 = verify_and_step(&mut host, message, 0);
         let message = next_input(&mut host);
-        let res_2 = step(&mut host, message, 0);
+        let res_2 = verify_and_step(&mut host, message, COLLECT_TIMEOUT + 1);
 
-        assert!(res_1.is_ok());
+        assert!(res_1.is_err());
         assert!(res_2.is_ok());
 
+        assert_not_exists(&mut host, "/tweets/0/collected_level");
+        assert_not_exists(&mut host, "/tweets/0/collect");
         assert_not_exists(
             &mut host,
-            "/accounts/tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv/tweets/owned/0",
+            "/accounts/tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv/collected/0",
         );
         assert_exist(
             &mut host,
-            "/accounts/tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv/tweets/written/0",
+            "/accounts/tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv/tweets/owned/0",
         );
+    }
+
+    #[test]
+    fn post_quota_exceeded() {
+        let mut host = MockHost::default();
+        set_u32(&mut host, "/config/rate_limit/window", 10);
+        set_u64(&mut host, "/config/rate_limit/posts", 1);
+
+        host.add_external(input_1());
+        host.add_external(input_2());
+        host.run_level(entry);
+
+        assert_exist(&mut host, "/tweets/0");
+        assert_not_exists(&mut host, "/tweets/1");
         assert_exist(
             &mut host,
-            "/accounts/tz1TGu6TN5GSez2ndXXeDX6LgUDvLzPLqgYV/tweets/owned/0",
+            "/receipts/20bb985cd44bf6a15bfd3da4452f22bee1d9d81be4916f87ac757f75dcb3ec37/error",
         );
+    }
+
+    #[test]
+    fn post_quota_reset_at_next_window() {
+        let mut host = MockHost::default();
+        set_u32(&mut host, "/config/rate_limit/window", 10);
+        set_u64(&mut host, "/config/rate_limit/posts", 1);
+
+        host.add_external(input_1());
+        host.add_external(input_2());
+
+        let message = next_input(&mut host);
+        let res_1 = verify_and_step(&mut host, message, 0);
+        let message = next_input(&mut host);
+        let res_2 = verify_and_step(&mut host, message, 10);
+
+        assert!(res_1.is_ok());
+        assert!(res_2.is_ok());
+        assert_u64(&mut host, "/constants/tweet-counter", Some(2));
+    }
+
+    #[test]
+    fn rate_limits_disabled_by_default() {
+        let mut host = MockHost::default();
+        set_u64(&mut host, "/config/rate_limit/posts", 0);
+
+        host.add_external(input_1());
+        let message = next_input(&mut host);
+        let res = verify_and_step(&mut host, message, 0);
+
+        assert!(res.is_ok());
         assert_not_exists(
             &mut host,
-            "/accounts/tz1TGu6TN5GSez2ndXXeDX6LgUDvLzPLqgYV/tweets/writte/0",
+            "/accounts/tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv/quota/posts",
         );
     }
+
+    #[test]
+    fn like_quota_exceeded() {
+        let mut host = MockHost::default();
+        set_u32(&mut host, "/config/rate_limit/window", 10);
+        set_u64(&mut host, "/config/rate_limit/likes", 0);
+
+        host.add_external(input_1());
+        host.add_external(input_like());
+
+        let message = next_input(&mut host);
+        let res_1 = verify_and_step(&mut host, message, 0);
+        let message = next_input(&mut host);
+        let res_2 = verify_and_step(&mut host, message, 0);
+
+        assert!(res_1.is_ok());
+        assert!(matches!(res_2, Err(Error::LikeQuotaExceeded)));
+        assert_u64(&mut host, "/tweets/0/likes", Some(0));
+    }
+
+    #[test]
+    fn failed_like_does_not_spend_quota() {
+        let mut host = MockHost::default();
+        set_u32(&mut host, "/config/rate_limit/window", 10);
+        set_u64(&mut host, "/config/rate_limit/likes", 1);
+        set_u64(
+            &mut host,
+            "/accounts/tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv/nonce",
+            1,
+        );
+
+        // The tweet 0 does not exist yet
+        host.add_external(input_like());
+        let message = next_input(&mut host);
+        let res = verify_and_step(&mut host, message, 0);
+
+        assert!(matches!(res, Err(Error::TweetNotFound)));
+        assert_not_exists(
+            &mut host,
+            "/accounts/tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv/quota/likes",
+        );
+    }
+
+    #[test]
+    fn posting_fee_without_balance() {
+        let mut host = MockHost::default();
+        set_u64(&mut host, "/config/posting_fee", 10);
+
+        host.add_external(input_1());
+
+        let message = next_input(&mut host);
+        let res = verify_and_step(&mut host, message, 0);
+
+        assert!(matches!(res, Err(Error::InsufficientBalance)));
+        assert_not_exists(&mut host, "/tweets/0");
+    }
+
+    #[test]
+    fn posting_fee_paid_by_deposit() {
+        let mut host = MockHost::default();
+        set_u64(&mut host, "/config/posting_fee", 10);
+
+        let owner = Contract::from_b58check("tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv").unwrap();
+        let deposit = MichelsonPair(MichelsonContract(owner), MichelsonInt::from(15));
+        add_l1_message(&mut host, L1Message::Deposit(deposit));
+        host.run_level(entry);
+
+        assert_u64(
+            &mut host,
+            "/accounts/tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv/balance",
+            Some(15),
+        );
+
+        host.add_external(input_1());
+        host.add_external(input_2());
+        host.run_level(entry);
+
+        assert_exist(&mut host, "/tweets/0");
+        assert_not_exists(&mut host, "/tweets/1");
+        assert_u64(
+            &mut host,
+            "/accounts/tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv/balance",
+            Some(5),
+        );
+        assert_exist(
+            &mut host,
+            "/receipts/20bb985cd44bf6a15bfd3da4452f22bee1d9d81be4916f87ac757f75dcb3ec37/error",
+        );
+    }
+
+    #[test]
+    fn hide_tweet() {
+        let mut host = MockHost::default();
+        set_string(&mut host, "/config/admin", ADMIN);
+
+        host.add_external(input_1());
+        host.add_external(input_like());
+        host.add_external(input_admin_hide());
+
+        let message = next_input(&mut host);
+        let _ = verify_and_step(&mut host, message, 0);
+        let message = next_input(&mut host);
+        let _ = verify_and_step(&mut host, message, 0);
+        let message = next_input(&mut host);
+        let res = verify_and_step(&mut host, message, 0);
+
+        assert!(res.is_ok());
+        assert_exist(&mut host, "/tweets/0/hidden");
+        assert_string(&mut host, "/tweets/0/content", "[redacted]");
+        assert_u64(&mut host, "/tweets/0/likes", Some(1));
+        assert_exist(
+            &mut host,
+            "/accounts/tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv/tweets/owned/0",
+        );
+    }
+
+    #[test]
+    fn hide_tweet_not_admin() {
+        let mut host = MockHost::default();
+        set_string(&mut host, "/config/admin", ADMIN);
+
+        host.add_external(input_1());
+        host.add_external(input_like());
+        host.add_external(input_hide());
+
+        let message = next_input(&mut host);
+        let _ = verify_and_step(&mut host, message, 0);
+        let message = next_input(&mut host);
+        let _ = verify_and_step(&mut host, message, 0);
+        let message = next_input(&mut host);
+        let res = verify_and_step(&mut host, message, 0);
+
+        assert!(matches!(res, Err(Error::NotAdmin)));
+        assert_not_exists(&mut host, "/tweets/0/hidden");
+        assert_string(&mut host, "/tweets/0/content", "Hello world");
+    }
+
+    #[test]
+    fn post_tweet_ref() {
+        let mut host = MockHost::default();
+        let body = "Hello world ".repeat(1000);
+        let root_hash = set_preimages(&mut host, body.as_bytes());
+        assert_eq!(
+            root_hash,
+            "006d8e331605f0ee041907b1e6abe358fe00652c85d43e0a816e7500b4d75496f8"
+        );
+
+        host.add_external(input_post_ref());
+        let message = next_input(&mut host);
+        let res = verify_and_step(&mut host, message, 0);
+
+        assert!(res.is_ok());
+        assert_string(&mut host, "/tweets/0/content", &root_hash);
+        assert_string(&mut host, "/tweets/0/body", &body);
+        assert_exist(
+            &mut host,
+            "/accounts/tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv/tweets/owned/0",
+        );
+    }
+
+    #[test]
+    fn post_tweet_ref_too_large() {
+        let mut host = MockHost::default();
+        let body = "Hello world ".repeat(4000);
+        let root_hash = set_preimages(&mut host, body.as_bytes());
+        assert_eq!(
+            root_hash,
+            "007901e1b4ea4d7076a69a3d5ac3b4ff0b70b15a0691f35ece6db4110385a3f4ff"
+        );
+
+        host.add_external(input_post_ref_too_large());
+        let message = next_input(&mut host);
+        let res = verify_and_step(&mut host, message, 0);
+
+        assert!(matches!(res, Err(Error::TweetBodyTooLarge)));
+        assert_not_exists(&mut host, "/tweets/0");
+    }
+
+    #[test]
+    fn post_tweet_ref_invalid_page() {
+        let mut host = MockHost::default();
+        host.set_preimage(b"\xffnot a dac page".to_vec());
+
+        host.add_external(input_post_ref_invalid_page());
+        let message = next_input(&mut host);
+        let res = verify_and_step(&mut host, message, 0);
+
+        assert!(matches!(res, Err(Error::PreimageNotRevealed)));
+        assert_not_exists(&mut host, "/tweets/0");
+    }
+
+    #[test]
+    fn events_of_the_operations() {
+        let mut host = MockHost::default();
+
+        host.add_external(input_1());
+        host.add_external(input_like());
+        host.add_external(input_like());
+        let message = next_input(&mut host);
+        let _ = verify_and_step(&mut host, message, 4);
+        let message = next_input(&mut host);
+        let _ = verify_and_step(&mut host, message, 4);
+        let message = next_input(&mut host);
+        let res = verify_and_step(&mut host, message, 4);
+
+        // The failed operation does not have any event
+        assert!(res.is_err());
+        let events = get_events(&mut HostReader(&mut host), &4).unwrap();
+        assert_eq!(2, events.len());
+        assert!(matches!(events[0], Event::TweetPosted { tweet_id: 0, .. }));
+        match &events[1] {
+            Event::TweetLiked { tweet_id, account } => {
+                assert_eq!(0, *tweet_id);
+                assert_eq!("tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv", account.to_string());
+            }
+            _ => panic!("Wrong event"),
+        }
+    }
+
+    #[test]
+    fn event_log_full() {
+        let mut host = MockHost::default();
+        set_u64(&mut host, "/events/0/count", MAX_EVENTS_PER_LEVEL);
+
+        host.add_external(input_1());
+        let message = next_input(&mut host);
+        let res = verify_and_step(&mut host, message, 0);
+
+        assert!(matches!(res, Err(Error::EventLogFull)));
+        assert_not_exists(&mut host, "/tweets/0");
+        assert_u64(&mut host, "/events/0/count", Some(MAX_EVENTS_PER_LEVEL));
+    }
+
+    const ALICE: &str = "tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv";
+    const CAROL: &str = "tz1Rf22vznYLW8bVH1A3M9VUS4DLyf58gjDA";
+
+    #[test]
+    fn session_key_posts_for_the_account() {
+        let mut host = MockHost::default();
+
+        host.add_external(input_authorize_session_key());
+        host.add_external(input_session_post());
+        let message = next_input(&mut host);
+        let res_1 = verify_and_step(&mut host, message, 0);
+        let message = next_input(&mut host);
+        let res_2 = verify_and_step(&mut host, message, 0);
+
+        assert!(res_1.is_ok());
+        assert!(res_2.is_ok());
+        let session_key = format!("/accounts/{}/session_keys/{}", ALICE, CAROL);
+        assert_exist(&mut host, &format!("{}/allowed/PostTweet", session_key));
+        assert_not_exists(&mut host, &format!("{}/allowed/Transfer", session_key));
+
+        assert_string(&mut host, "/tweets/0/author", ALICE);
+        assert_exist(&mut host, &format!("/accounts/{}/tweets/owned/0", ALICE));
+        assert_u64(&mut host, &format!("/accounts/{}/nonce", ALICE), Some(2));
+        assert_not_exists(&mut host, &format!("/accounts/{}", CAROL));
+
+        let events = get_events(&mut HostReader(&mut host), &0).unwrap();
+        assert!(matches!(events[0], Event::SessionKeyAuthorized { .. }));
+        assert!(matches!(events[1], Event::TweetPosted { tweet_id: 0, .. }));
+    }
+
+    #[test]
+    fn session_key_cannot_transfer() {
+        let mut host = MockHost::default();
+
+        host.add_external(input_authorize_session_key());
+        host.add_external(input_session_post());
+        host.add_external(input_session_transfer());
+        let message = next_input(&mut host);
+        let _ = verify_and_step(&mut host, message, 0);
+        let message = next_input(&mut host);
+        let _ = verify_and_step(&mut host, message, 0);
+        let message = next_input(&mut host);
+        let res = verify_and_step(&mut host, message, 0);
+
+        assert!(matches!(res, Err(Error::OperationNotAllowed)));
+        assert_exist(&mut host, &format!("/accounts/{}/tweets/owned/0", ALICE));
+        assert_u64(&mut host, &format!("/accounts/{}/nonce", ALICE), Some(2));
+    }
+
+    #[test]
+    fn session_key_expired() {
+        let mut host = MockHost::default();
+
+        host.add_external(input_authorize_session_key());
+        host.add_external(input_session_post());
+        host.add_external(input_session_like());
+        let message = next_input(&mut host);
+        let _ = verify_and_step(&mut host, message, 0);
+        let message = next_input(&mut host);
+        let _ = verify_and_step(&mut host, message, 10);
+        let message = next_input(&mut host);
+        let res = verify_and_step(&mut host, message, 11);
+
+        assert!(matches!(res, Err(Error::SessionKeyExpired)));
+        assert_u64(&mut host, "/tweets/0/likes", Some(0));
+    }
+
+    #[test]
+    fn session_key_revoked() {
+        let mut host = MockHost::default();
+
+        host.add_external(input_authorize_session_key());
+        host.add_external(input_revoke_session_key());
+        host.add_external(input_session_like());
+        let message = next_input(&mut host);
+        let _ = verify_and_step(&mut host, message, 0);
+        let message = next_input(&mut host);
+        let res_1 = verify_and_step(&mut host, message, 0);
+        let message = next_input(&mut host);
+        let res_2 = verify_and_step(&mut host, message, 0);
+
+        assert!(res_1.is_ok());
+        assert!(matches!(res_2, Err(Error::SessionKeyNotAuthorized)));
+        assert_not_exists(
+            &mut host,
+            &format!("/accounts/{}/session_keys/{}", ALICE, CAROL),
+        );
+    }
+
+    #[test]
+    fn migrate_account() {
+        let mut host = MockHost::default();
+
+        host.add_external(input_1());
+        host.add_external(input_like());
+        host.add_external(input_migrate_account());
+        let message = next_input(&mut host);
+        let _ = verify_and_step(&mut host, message, 0);
+        let message = next_input(&mut host);
+        let _ = verify_and_step(&mut host, message, 0);
+        let message = next_input(&mut host);
+        let res = verify_and_step(&mut host, message, 0);
+
+        assert!(res.is_ok());
+        let new_owner = format!("/accounts/{}", CAROL);
+        assert_u64(&mut host, &format!("{}/nonce", new_owner), Some(3));
+        assert_exist(&mut host, &format!("{}/tweets/owned/0", new_owner));
+        assert_exist(&mut host, &format!("{}/tweets/written/0", new_owner));
+        assert_exist(&mut host, &format!("{}/likes/0", new_owner));
+        assert_string(&mut host, "/tweets/0/author", CAROL);
+
+        assert_not_exists(&mut host, &format!("/accounts/{}/nonce", ALICE));
+        assert_not_exists(&mut host, &format!("/accounts/{}/tweets", ALICE));
+        assert_string(
+            &mut host,
+            &format!("/accounts/{}/migrated_to", ALICE),
+            CAROL,
+        );
+
+        // The old key cannot be used anymore, the new one continues with the nonce
+        host.add_external(input_2());
+        host.add_external(input_post_after_migration());
+        let message = next_input(&mut host);
+        let res_1 = verify_and_step(&mut host, message, 0);
+        let message = next_input(&mut host);
+        let res_2 = verify_and_step(&mut host, message, 0);
+
+        assert!(matches!(res_1, Err(Error::AccountMigrated)));
+        assert!(res_2.is_ok());
+        assert_exist(&mut host, &format!("{}/tweets/owned/1", new_owner));
+    }
+
+    #[test]
+    fn migrate_to_existing_account() {
+        let mut host = MockHost::default();
+        set_u64(&mut host, &format!("/accounts/{}/nonce", ADMIN), 1);
+
+        host.add_external(input_migrate_to_admin());
+        let message = next_input(&mut host);
+        let res = verify_and_step(&mut host, message, 0);
+
+        assert!(matches!(res, Err(Error::AccountAlreadyExists)));
+        assert_u64(&mut host, &format!("/accounts/{}/nonce", ALICE), Some(1));
+        assert_not_exists(&mut host, &format!("/accounts/{}/migrated_to", ALICE));
+    }
+
+    #[test]
+    fn multisig_threshold() {
+        let mut host = MockHost::default();
+
+        host.add_external(input_set_signers());
+        host.add_external(input_multisig_one_signature());
+        host.add_external(input_multisig_same_signer());
+        host.add_external(input_multisig_two_signatures());
+        let message = next_input(&mut host);
+        let res_1 = verify_and_step(&mut host, message, 0);
+        let message = next_input(&mut host);
+        let res_2 = verify_and_step(&mut host, message, 0);
+        let message = next_input(&mut host);
+        let res_3 = verify_and_step(&mut host, message, 0);
+
+        assert!(res_1.is_ok());
+        assert!(matches!(res_2, Err(Error::NotEnoughSignatures)));
+        assert!(matches!(res_3, Err(Error::NotEnoughSignatures)));
+        assert_not_exists(&mut host, "/tweets/0");
+        assert_u64(&mut host, &format!("/accounts/{}/nonce", ALICE), Some(1));
+
+        let message = next_input(&mut host);
+        let res_4 = verify_and_step(&mut host, message, 0);
+
+        assert!(res_4.is_ok());
+        assert_string(&mut host, "/tweets/0/author", ALICE);
+        assert_exist(&mut host, &format!("/accounts/{}/tweets/owned/0", ALICE));
+        assert_u64(&mut host, &format!("/accounts/{}/nonce", ALICE), Some(2));
+        assert_not_exists(&mut host, &format!("/accounts/{}/nonce", CAROL));
+    }
+
+    #[test]
+    fn nonce_window_reordering() {
+        let mut host = MockHost::default();
+        set_u64(&mut host, "/config/nonce_window", 4);
+
+        host.add_external(input_post_nonce_3());
+        host.add_external(input_1());
+        host.add_external(input_post_nonce_2());
+        let message = next_input(&mut host);
+        let res_1 = verify_and_step(&mut host, message, 0);
+
+        assert!(res_1.is_ok());
+        assert_u64(&mut host, &format!("/accounts/{}/nonce", ALICE), Some(0));
+        assert_u64(
+            &mut host,
+            &format!("/accounts/{}/used_nonces", ALICE),
+            Some(0b100),
+        );
+
+        let message = next_input(&mut host);
+        let res_2 = verify_and_step(&mut host, message, 0);
+        let message = next_input(&mut host);
+        let res_3 = verify_and_step(&mut host, message, 0);
+
+        assert!(res_2.is_ok());
+        assert!(res_3.is_ok());
+        assert_u64(&mut host, &format!("/accounts/{}/nonce", ALICE), Some(3));
+        assert_not_exists(&mut host, &format!("/accounts/{}/used_nonces", ALICE));
+        assert_string(&mut host, "/tweets/0/content", "Tweet 3");
+        assert_string(&mut host, "/tweets/2/content", "Tweet 2");
+    }
+
+    #[test]
+    fn nonce_window_replay() {
+        let mut host = MockHost::default();
+        set_u64(&mut host, "/config/nonce_window", 4);
+
+        host.add_external(input_post_nonce_2());
+        host.add_external(input_post_nonce_2());
+        host.add_external(input_post_nonce_6());
+        host.add_external(input_1());
+        let message = next_input(&mut host);
+        let res_1 = verify_and_step(&mut host, message, 0);
+        let message = next_input(&mut host);
+        let res_2 = verify_and_step(&mut host, message, 0);
+        let message = next_input(&mut host);
+        let res_3 = verify_and_step(&mut host, message, 0);
+        let message = next_input(&mut host);
+        let res_4 = verify_and_step(&mut host, message, 0);
+
+        assert!(res_1.is_ok());
+        // Already used in the window
+        assert!(matches!(res_2, Err(Error::InvalidNonce)));
+        // Outside of the window
+        assert!(matches!(res_3, Err(Error::InvalidNonce)));
+        assert!(res_4.is_ok());
+        assert_u64(&mut host, &format!("/accounts/{}/nonce", ALICE), Some(2));
+        assert_not_exists(&mut host, "/tweets/2");
+
+        // The nonces below the nonce of the account cannot be replayed
+        host.add_external(input_post_nonce_2());
+        let message = next_input(&mut host);
+        let res_5 = verify_and_step(&mut host, message, 0);
+        assert!(matches!(res_5, Err(Error::InvalidNonce)));
+    }
+
+    #[test]
+    fn edit_tweet_history() {
+        let mut host = MockHost::default();
+
+        host.add_external(input_1());
+        host.add_external(input_edit());
+        host.add_external(input_edit_2());
+        let message = next_input(&mut host);
+        let _ = verify_and_step(&mut host, message, 3);
+        let message = next_input(&mut host);
+        let res_1 = verify_and_step(&mut host, message, 5);
+        let message = next_input(&mut host);
+        let res_2 = verify_and_step(&mut host, message, 8);
+
+        assert!(res_1.is_ok());
+        assert!(res_2.is_ok());
+        assert_string(&mut host, "/tweets/0/content", "Hello world!!");
+        assert_u64(&mut host, "/tweets/0/history/count", Some(2));
+
+        let history = get_tweet_history(&mut HostReader(&mut host), &0).unwrap();
+        assert_eq!(2, history.len());
+        assert_eq!("Hello world", history[0].content);
+        assert_eq!(5, history[0].level);
+        assert_eq!("Hello world!", history[1].content);
+        assert_eq!(8, history[1].level);
+    }
+
+    #[test]
+    fn collect_edited_tweet() {
+        let mut host = MockHost::default();
+
+        host.add_external(input_1());
+        host.add_external(input_edit());
+        host.add_external(input_collect_2());
+        let message = next_input(&mut host);
+        let _ = verify_and_step(&mut host, message, 0);
+        let message = next_input(&mut host);
+        let _ = verify_and_step(&mut host, message, 0);
+        let message = next_input(&mut host);
+        let res = verify_and_step(&mut host, message, 0);
+
+        assert!(res.is_ok());
+        let outbox = host.outbox_at(host.level());
+        let payload = outbox.last().unwrap();
+        let edited = b"Hello world!";
+        assert!(payload.windows(edited.len()).any(|bytes| bytes == edited));
+    }
+
+    #[test]
+    fn edit_collected_tweet() {
+        let mut host = MockHost::default();
+
+        host.add_external(input_1());
+        host.add_external(input_collect());
+        host.add_external(input_edit_2());
+        let message = next_input(&mut host);
+        let _ = verify_and_step(&mut host, message, 0);
+        let message = next_input(&mut host);
+        let _ = verify_and_step(&mut host, message, 0);
+        let message = next_input(&mut host);
+        let res = verify_and_step(&mut host, message, 0);
+
+        assert!(matches!(res, Err(Error::TweetAlreadyCollected)));
+        assert_string(&mut host, "/tweets/0/content", "Hello world");
+        assert_not_exists(&mut host, "/tweets/0/history");
+    }
+
+    #[test]
+    fn edit_transferred_tweet() {
+        let mut host = MockHost::default();
+
+        host.add_external(input_1());
+        host.add_external(input_transfer());
+        host.add_external(input_edit_2());
+        let message = next_input(&mut host);
+        let _ = verify_and_step(&mut host, message, 0);
+        let message = next_input(&mut host);
+        let _ = verify_and_step(&mut host, message, 0);
+        let message = next_input(&mut host);
+        let res = verify_and_step(&mut host, message, 0);
+
+        assert!(matches!(res, Err(Error::NotOwner)));
+        assert_string(&mut host, "/tweets/0/content", "Hello world");
+    }
+
+    #[test]
+    fn poll_votes() {
+        let mut host = MockHost::default();
+
+        host.add_external(input_post_poll());
+        host.add_external(input_vote());
+        host.add_external(input_admin_vote());
+        host.add_external(input_vote_again());
+        let message = next_input(&mut host);
+        let res_1 = verify_and_step(&mut host, message, 5);
+        let message = next_input(&mut host);
+        let res_2 = verify_and_step(&mut host, message, 6);
+        let message = next_input(&mut host);
+        let res_3 = verify_and_step(&mut host, message, 10);
+        let message = next_input(&mut host);
+        let res_4 = verify_and_step(&mut host, message, 10);
+
+        assert!(res_1.is_ok());
+        assert!(res_2.is_ok());
+        assert!(res_3.is_ok());
+        assert!(matches!(res_4, Err(Error::AlreadyVoted)));
+        assert_string(&mut host, "/tweets/0/content", "Best color?");
+        assert_string(&mut host, "/tweets/0/poll/options/1", "Blue");
+        assert_u64(&mut host, "/tweets/0/poll/tallies/0", Some(0));
+        assert_u64(&mut host, "/tweets/0/poll/tallies/1", Some(2));
+        assert_exist(&mut host, &format!("/accounts/{}/votes/0", ALICE));
+
+        let events = get_events(&mut HostReader(&mut host), &10).unwrap();
+        match &events[0] {
+            Event::PollVoted {
+                tweet_id,
+                account,
+                option,
+            } => {
+                assert_eq!(0, *tweet_id);
+                assert_eq!(ADMIN, account.to_string());
+                assert_eq!(1, *option);
+            }
+            _ => panic!("Wrong event"),
+        }
+    }
+
+    #[test]
+    fn poll_closed() {
+        let mut host = MockHost::default();
+
+        host.add_external(input_post_poll());
+        host.add_external(input_vote());
+        host.add_external(input_invalid_vote());
+        host.add_external(input_admin_vote());
+        let message = next_input(&mut host);
+        let _ = verify_and_step(&mut host, message, 5);
+        let message = next_input(&mut host);
+        let _ = verify_and_step(&mut host, message, 6);
+        let message = next_input(&mut host);
+        let res_1 = verify_and_step(&mut host, message, 7);
+
+        // The results are frozen at the start of the level following the closing level
+        close_polls(&mut host, 10).unwrap();
+        assert!(get_poll_results(&mut HostReader(&mut host), &0)
+            .unwrap()
+            .is_none());
+        close_polls(&mut host, 11).unwrap();
+
+        let message = next_input(&mut host);
+        let res_2 = verify_and_step(&mut host, message, 11);
+
+        assert!(matches!(res_1, Err(Error::InvalidPollOption)));
+        assert!(matches!(res_2, Err(Error::PollClosed)));
+        let results = get_poll_results(&mut HostReader(&mut host), &0)
+            .unwrap()
+            .unwrap();
+        assert_eq!(vec![0, 1], results.tallies);
+        assert_eq!(1, results.votes);
+        let events = get_events(&mut HostReader(&mut host), &11).unwrap();
+        assert!(matches!(events[0], Event::PollClosed { tweet_id: 0 }));
+    }
+
+    #[test]
+    fn poll_closing_in_the_past() {
+        let mut host = MockHost::default();
+
+        host.add_external(input_post_poll());
+        let message = next_input(&mut host);
+        let res = verify_and_step(&mut host, message, 11);
+
+        assert!(matches!(res, Err(Error::InvalidPoll)));
+        assert_not_exists(&mut host, "/tweets/0");
+    }
+
+    #[test]
+    fn retweet_and_quote() {
+        let mut host = MockHost::default();
+
+        host.add_external(input_1());
+        host.add_external(input_retweet());
+        host.add_external(input_retweet_again());
+        host.add_external(input_quote());
+        let message = next_input(&mut host);
+        let _ = verify_and_step(&mut host, message, 0);
+        let message = next_input(&mut host);
+        let res_1 = verify_and_step(&mut host, message, 0);
+        let message = next_input(&mut host);
+        let res_2 = verify_and_step(&mut host, message, 0);
+        let message = next_input(&mut host);
+        let res_3 = verify_and_step(&mut host, message, 0);
+
+        assert!(res_1.is_ok());
+        assert!(matches!(res_2, Err(Error::TweetAlreadyRetweeted)));
+        assert!(res_3.is_ok());
+        assert_u64(&mut host, "/tweets/0/retweets", Some(2));
+        assert_exist(&mut host, &format!("/accounts/{}/tweets/written/0", CAROL));
+        assert_not_exists(&mut host, &format!("/accounts/{}/tweets/owned/0", CAROL));
+
+        // The quote is a new tweet referencing the quoted one
+        assert_string(&mut host, "/tweets/1/author", CAROL);
+        assert_string(&mut host, "/tweets/1/content", "So true");
+        assert_u64(&mut host, "/tweets/1/quoted", Some(0));
+        assert_u64(&mut host, "/tweets/1/retweets", Some(0));
+        assert_exist(&mut host, &format!("/accounts/{}/tweets/owned/1", CAROL));
+
+        // The leaf of the retweeted tweet commits to its retweets
+        let root = get_merkle_root(&mut HostReader(&mut host)).unwrap();
+        let proof = get_tweet_proof(&mut HostReader(&mut host), &0)
+            .unwrap()
+            .unwrap();
+        assert_eq!(2, proof.tweet.retweets);
+        assert!(proof.verify(&root));
+    }
+
+    #[test]
+    fn migrate_account_with_retweet() {
+        let mut host = MockHost::default();
+
+        host.add_external(input_1());
+        host.add_external(input_admin_post());
+        host.add_external(input_retweet_admin_tweet());
+        host.add_external(input_migrate_account());
+        for _ in 0..3 {
+            let message = next_input(&mut host);
+            let _ = verify_and_step(&mut host, message, 0);
+        }
+        let message = next_input(&mut host);
+        let res = verify_and_step(&mut host, message, 0);
+
+        // The retweeted tweet keeps its author
+        assert!(res.is_ok());
+        assert_string(&mut host, "/tweets/0/author", CAROL);
+        assert_string(&mut host, "/tweets/1/author", ADMIN);
+        assert_exist(&mut host, &format!("/accounts/{}/tweets/written/1", CAROL));
+    }
+
+    #[test]
+    fn trending_likes_roll_off() {
+        let mut host = MockHost::default();
+
+        host.add_external(input_1());
+        host.add_external(input_like());
+        let message = next_input(&mut host);
+        let _ = verify_and_step(&mut host, message, 5);
+        let message = next_input(&mut host);
+        let res = verify_and_step(&mut host, message, 5);
+
+        assert!(res.is_ok());
+        assert_u64(&mut host, "/trending/buckets/5/count", Some(1));
+        assert_u64(&mut host, "/trending/buckets/5/likes/0", Some(1));
+        assert_u64(&mut host, "/trending/likes/0", Some(1));
+        let top = get_top_tweets(&mut HostReader(&mut host)).unwrap();
+        assert_eq!(&[(0, 1)], top.entries());
+
+        // The like still counts during the last level of the window
+        expire_trending(&mut host, 5 + TRENDING_WINDOW - 1).unwrap();
+        assert_u64(&mut host, "/trending/likes/0", Some(1));
+
+        expire_trending(&mut host, 5 + TRENDING_WINDOW).unwrap();
+        assert_not_exists(&mut host, "/trending/buckets/5");
+        assert_not_exists(&mut host, "/trending/likes/0");
+        assert_u64(&mut host, "/tweets/0/likes", Some(1));
+        let top = get_top_tweets(&mut HostReader(&mut host)).unwrap();
+        assert!(top.entries().is_empty());
+    }
+
+    #[test]
+    fn trending_leader_replaced_after_roll_off() {
+        let mut host = MockHost::default();
+        let mut top = TopTweets::default();
+
+        // The leader is liked twice at the level 3, the older tweets once at the level 5
+        let leader = TRENDING_TOP_SIZE as u64 + 1;
+        for _ in 0..2 {
+            let likes = add_trending_like(&mut host, &3, &leader).unwrap();
+            top.update(leader, likes, TRENDING_CANDIDATES);
+        }
+        for tweet_id in 0..leader {
+            let likes = add_trending_like(&mut host, &5, &tweet_id).unwrap();
+            top.update(tweet_id, likes, TRENDING_CANDIDATES);
+        }
+        store_top_tweets(&mut host, &top).unwrap();
+
+        let top = get_top_tweets(&mut HostReader(&mut host)).unwrap();
+        assert_eq!(TRENDING_TOP_SIZE, top.entries().len());
+        assert_eq!((leader, 2), top.entries()[0]);
+
+        // The tweet ranked after the top replaces the leader
+        expire_trending(&mut host, 3 + TRENDING_WINDOW).unwrap();
+        let top = get_top_tweets(&mut HostReader(&mut host)).unwrap();
+        let expected: Vec<(u64, u64)> = (0..TRENDING_TOP_SIZE as u64).map(|id| (id, 1)).collect();
+        assert_eq!(expected.as_slice(), top.entries());
+    }
+
+    #[test]
+    fn paused_kernel_rejects_user_messages() {
+        let mut host = MockHost::default();
+        set_string(&mut host, "/config/admin", ADMIN);
+
+        host.add_external(input_admin_pause_reject());
+        host.add_external(input_1());
+        host.run_level(entry);
+
+        assert_exist(&mut host, "/config/pause");
+        assert_not_exists(&mut host, "/tweets/0");
+        assert_string(
+            &mut host,
+            "/receipts/933dd79f9935573925f774ad0ac8789560e2489f083eb7dce7289485e3648a2d/error",
+            "The kernel is paused, retry later",
+        );
+
+        // The rejected message can be sent again after the pause
+        host.add_external(input_admin_unpause());
+        host.add_external(input_1());
+        host.run_level(entry);
+
+        assert_not_exists(&mut host, "/config/pause");
+        assert_exist(&mut host, "/tweets/0");
+        assert_not_exists(
+            &mut host,
+            "/receipts/933dd79f9935573925f774ad0ac8789560e2489f083eb7dce7289485e3648a2d/error",
+        );
+    }
+
+    #[test]
+    fn only_admin_can_pause() {
+        let mut host = MockHost::default();
+        set_string(&mut host, "/config/admin", ADMIN);
+
+        host.add_external(input_pause());
+        let message = next_input(&mut host);
+        let res = verify_and_step(&mut host, message, 0);
+
+        assert!(matches!(res, Err(Error::NotAdmin)));
+        assert_not_exists(&mut host, "/config/pause");
+    }
+
+    #[test]
+    fn paused_kernel_defers_user_messages() {
+        let mut host = MockHost::default();
+        set_string(&mut host, "/config/admin", ADMIN);
+
+        host.add_external(input_admin_pause_defer());
+        host.add_external(input_1());
+        host.run_level(entry);
+
+        assert_not_exists(&mut host, "/tweets/0");
+        assert_u64(&mut host, "/deferred/tail", Some(1));
+        assert_string(
+            &mut host,
+            "/receipts/933dd79f9935573925f774ad0ac8789560e2489f083eb7dce7289485e3648a2d/error",
+            "The kernel is paused, the message will be processed after the pause",
+        );
+
+        // The messages following the pause are queued behind the deferred ones
+        host.add_external(input_admin_unpause());
+        host.add_external(input_2());
+        host.run_level(entry);
+
+        assert_not_exists(&mut host, "/tweets/0");
+        assert_u64(&mut host, "/deferred/tail", Some(2));
+
+        // The deferred messages are replayed at the start of the next level
+        host.run_level(entry);
+
+        assert_exist(&mut host, "/tweets/0");
+        assert_exist(&mut host, "/tweets/1");
+        assert_u64(&mut host, "/deferred/head", Some(2));
+        assert_not_exists(&mut host, "/deferred/queue/0");
+        assert_not_exists(
+            &mut host,
+            "/receipts/933dd79f9935573925f774ad0ac8789560e2489f083eb7dce7289485e3648a2d/error",
+        );
+        assert_not_exists(
+            &mut host,
+            "/receipts/20bb985cd44bf6a15bfd3da4452f22bee1d9d81be4916f87ac757f75dcb3ec37/error",
+        );
+    }
+
+    #[test]
+    fn paused_kernel_defers_only_signed_messages() {
+        let mut host = MockHost::default();
+        set_string(&mut host, "/config/admin", ADMIN);
+
+        // The content "Hello world" is replaced by "Jello world" after the signature
+        let BinInput(input) = input_1();
+        let forged = input.replace("48656c6c6f20776f726c64", "4a656c6c6f20776f726c64");
+        host.add_external(input_admin_pause_defer());
+        host.add_external(BinInput(forged));
+        host.run_level(entry);
+
+        assert_u64(&mut host, "/deferred/tail", None);
+
+        // The deferred queue is bounded
+        set_u64(&mut host, "/deferred/tail", MAX_DEFERRED_MESSAGES);
+        host.add_external(input_1());
+        host.run_level(entry);
+
+        assert_u64(&mut host, "/deferred/tail", Some(MAX_DEFERRED_MESSAGES));
+        assert_string(
+            &mut host,
+            "/receipts/933dd79f9935573925f774ad0ac8789560e2489f083eb7dce7289485e3648a2d/error",
+            "Too many messages are waiting for the end of the pause, retry later",
+        );
+    }
+
+    #[test]
+    fn commit_state_to_registry() {
+        let mut host = MockHost::default();
+        set_u32(&mut host, "/config/commitment_period", 2);
+
+        // Nothing is committed until the registry is configured
+        for _ in 0..2 {
+            let level = host.level();
+            host.run_level(entry);
+            assert!(host.outbox_at(level).is_empty());
+        }
+        set_string(
+            &mut host,
+            "/config/registry",
+            "KT1EfTusMLoeCAAGd9MZJn5yKzFr6kJU5U91",
+        );
+
+        host.add_external(input_1());
+        host.run_level(entry);
+        let root = get_merkle_root(&mut HostReader(&mut host)).unwrap();
+
+        // The state is committed at the start of the even levels
+        for _ in 0..2 {
+            let level = host.level();
+            host.run_level(entry);
+            let outbox = host.outbox_at(level);
+            match level % 2 {
+                0 => {
+                    assert_eq!(1, outbox.len());
+                    let payload = &outbox[0];
+                    assert!(payload.windows(root.len()).any(|bytes| bytes == root));
+                    assert!(payload.windows(6).any(|bytes| bytes == b"commit"));
+                }
+                _ => assert!(outbox.is_empty()),
+            }
+        }
+    }
+
+    #[test]
+    fn commit_state_parameter_layout() {
+        let mut host = MockHost::default();
+        set_u32(&mut host, "/config/commitment_period", 2);
+        set_string(
+            &mut host,
+            "/config/registry",
+            "KT1EfTusMLoeCAAGd9MZJn5yKzFr6kJU5U91",
+        );
+
+        host.add_external(input_1());
+        host.run_level(entry);
+        let root = get_merkle_root(&mut HostReader(&mut host)).unwrap();
+
+        while host.level() % 2 != 0 {
+            host.run_level(entry);
+        }
+        let level = host.level();
+        host.run_level(entry);
+
+        // Pair (Pair counter level) root, the layout of the commitment record of registry.jsligo
+        let mut parameter = vec![0x07, 0x07, 0x07, 0x07, 0x00, 1];
+        MichelsonInt::from(level as i32)
+            .bin_write(&mut parameter)
+            .unwrap();
+        parameter.push(0x0a);
+        parameter.extend_from_slice(&(root.len() as u32).to_be_bytes());
+        parameter.extend_from_slice(&root);
+        let outbox = host.outbox_at(level);
+        assert_eq!(1, outbox.len());
+        assert!(outbox[0]
+            .windows(parameter.len())
+            .any(|bytes| bytes == parameter));
+    }
 }
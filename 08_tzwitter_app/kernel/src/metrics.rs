@@ -0,0 +1,130 @@
+use std::cell::RefCell;
+
+use tezos_smart_rollup::prelude::*;
+
+use crate::core::error::*;
+use crate::core::metrics::LevelMetrics;
+use crate::storage;
+
+/// Checks if the kernel is built with the metrics feature
+///
+/// Without the feature, nothing is recorded and the durable storage is not inspected
+pub const ENABLED: bool = cfg!(feature = "metrics");
+
+/// The metrics of the current run of the kernel, kept in memory
+#[derive(Default)]
+struct Recorder {
+    level: Option<u32>,
+    metrics: LevelMetrics,
+}
+
+thread_local! {
+    static RECORDER: RefCell<Recorder> = RefCell::new(Recorder::default());
+}
+
+/// Set the level of the inputs being processed
+pub fn set_level(level: u32) {
+    if ENABLED {
+        RECORDER.with(|recorder| recorder.borrow_mut().level = Some(level));
+    }
+}
+
+/// Record a write to the durable storage
+pub fn record_write(bytes: usize, growth: usize) {
+    if ENABLED {
+        RECORDER.with(|recorder| {
+            let metrics = &mut recorder.borrow_mut().metrics;
+            metrics.bytes_written += bytes as u64;
+            metrics.storage_growth += growth as i64;
+        });
+    }
+}
+
+/// Record the deletion of a value of the durable storage
+pub fn record_delete(size: usize) {
+    if ENABLED {
+        RECORDER.with(|recorder| recorder.borrow_mut().metrics.storage_growth -= size as i64);
+    }
+}
+
+/// Process an operation and record its metrics under its tag
+pub fn measure<T>(tag: u8, operation: impl FnOnce() -> Result<T>) -> Result<T> {
+    if !ENABLED {
+        return operation();
+    }
+    let totals = |recorder: &RefCell<Recorder>| {
+        let metrics = &recorder.borrow().metrics;
+        (metrics.bytes_written, metrics.storage_growth)
+    };
+    let (bytes_written, storage_growth) = RECORDER.with(totals);
+    let result = operation();
+    RECORDER.with(|recorder| {
+        let (bytes_written_after, storage_growth_after) = totals(recorder);
+        let mut recorder = recorder.borrow_mut();
+        let metrics = recorder.metrics.operation(tag);
+        metrics.count += 1;
+        metrics.failed += result.is_err() as u64;
+        metrics.bytes_written += bytes_written_after - bytes_written;
+        metrics.storage_growth += storage_growth_after - storage_growth;
+    });
+    result
+}
+
+/// Add the metrics of the run to /metrics/{level}
+///
+/// A level processed by several runs of the kernel accumulates their metrics
+/// The writes of the record itself are not counted
+pub fn flush<R: Runtime>(host: &mut R) -> Result<()> {
+    if !ENABLED {
+        return Ok(());
+    }
+    let Recorder { level, metrics } = RECORDER.with(|recorder| recorder.take());
+    let level = match level {
+        Some(level) if !metrics.is_empty() => level,
+        _ => return Ok(()),
+    };
+    let mut record = storage::read_level_metrics(host, &level)?.unwrap_or_default();
+    record.merge(&metrics);
+    let result = storage::store_level_metrics(host, &level, &record);
+    RECORDER.with(|recorder| recorder.take());
+    result
+}
+
+#[cfg(all(test, feature = "metrics"))]
+mod tests {
+    use tezos_smart_rollup::testing::prelude::MockHost;
+
+    use super::{flush, measure, set_level};
+    use crate::core::error::Error;
+    use crate::core::trending::TopTweets;
+    use crate::storage::{read_level_metrics, store_top_tweets};
+
+    #[test]
+    fn test_record_level_metrics() {
+        let mut host = MockHost::default();
+        let mut top = TopTweets::default();
+        top.update(0, 1, 10);
+
+        set_level(3);
+        measure(2, || store_top_tweets(&mut host, &top)).unwrap();
+        let _ = measure(0, || Err::<(), _>(Error::NotAdmin));
+        flush(&mut host).unwrap();
+
+        let metrics = read_level_metrics(&mut host, &3).unwrap().unwrap();
+        assert_eq!(16, metrics.bytes_written);
+        assert_eq!(16, metrics.storage_growth);
+        assert_eq!(1, metrics.operations[0].failed);
+        assert_eq!(Some("LikeTweet"), metrics.operations[1].name());
+        assert_eq!(16, metrics.operations[1].storage_growth);
+
+        // The next run at the same level adds its metrics, replacing a value does not grow the storage
+        set_level(3);
+        measure(2, || store_top_tweets(&mut host, &top)).unwrap();
+        flush(&mut host).unwrap();
+
+        let metrics = read_level_metrics(&mut host, &3).unwrap().unwrap();
+        assert_eq!(2, metrics.operations[1].count);
+        assert_eq!(32, metrics.operations[1].bytes_written);
+        assert_eq!(16, metrics.storage_growth);
+    }
+}
@@ -0,0 +1,265 @@
+use tezos_smart_rollup::prelude::*;
+
+use crate::constants::MIGRATION_BATCH_SIZE;
+use crate::core::error::*;
+use crate::storage;
+
+/// The migrations of the durable storage, in the order of the schema versions
+///
+/// The migration at the index i upgrades the storage from the version i to the version i + 1
+/// A new layout of the storage is shipped with a new migration at the end of this list
+const MIGRATIONS: &[Migration] = &[
+    Migration::BackfillRetweets,
+    Migration::BuildTweetTree,
+    Migration::IndexAccountTweets,
+];
+
+/// The version of the storage written by this kernel
+pub const SCHEMA_VERSION: u64 = MIGRATIONS.len() as u64;
+
+/// A change of the layout of the durable storage
+enum Migration {
+    /// Version 1: every tweet has a /tweets/{id}/retweets counter
+    BackfillRetweets,
+    /// Version 2: every tweet has a /tweets/{id}/owner and a leaf in the merkle tree of the tweets
+    BuildTweetTree,
+    /// Version 3: the accounts index their written and owned tweets in /accounts/{tz1...}/tweets/index
+    IndexAccountTweets,
+}
+
+/// The progress of a migration after a batch
+enum Progress {
+    /// The migration continues at this cursor
+    Pending(u64),
+    /// The migration is complete, after migrating this number of items in the batch
+    Complete(u64),
+}
+
+impl Migration {
+    /// Migrates at most batch_size items, starting at the cursor
+    fn run<R: Runtime>(&self, host: &mut R, cursor: u64, batch_size: u64) -> Result<Progress> {
+        match self {
+            Migration::BackfillRetweets => {
+                let counter = storage::read_tweet_counter(host)?;
+                let end = counter.min(cursor + batch_size);
+                for tweet_id in cursor..end {
+                    storage::init_retweets_counter(host, &tweet_id)?;
+                }
+                Ok(progress(cursor, end, counter))
+            }
+            Migration::BuildTweetTree => {
+                let counter = storage::read_tweet_counter(host)?;
+                let end = counter.min(cursor + batch_size);
+                for tweet_id in cursor..end {
+                    storage::init_tweet_owner(host, &tweet_id)?;
+                    storage::commit_tweet(host, &tweet_id)?;
+                }
+                Ok(progress(cursor, end, counter))
+            }
+            Migration::IndexAccountTweets => {
+                let counter = storage::read_tweet_counter(host)?;
+                let end = counter.min(cursor + batch_size);
+                for tweet_id in cursor..end {
+                    storage::index_account_tweets(host, &tweet_id)?;
+                }
+                Ok(progress(cursor, end, counter))
+            }
+        }
+    }
+}
+
+/// The progress of a migration over the items cursor..end of the items 0..len
+fn progress(cursor: u64, end: u64, len: u64) -> Progress {
+    match end < len {
+        true => Progress::Pending(end),
+        false => Progress::Complete(end.saturating_sub(cursor)),
+    }
+}
+
+/// Upgrade the durable storage to SCHEMA_VERSION
+///
+/// Returns true when the storage is up to date
+pub fn migrate<R: Runtime>(host: &mut R) -> Result<bool> {
+    migrate_batch(host, MIGRATION_BATCH_SIZE)
+}
+
+/// Run the pending migrations on at most batch_size items
+///
+/// A completed migration is followed by the next one while the batch is not exhausted,
+/// so an empty storage is upgraded in a single call.
+/// The cursor of the migration is stored in /schema/cursor,
+/// when the batch does not complete the migrations the kernel reboots to run the next one
+fn migrate_batch<R: Runtime>(host: &mut R, batch_size: u64) -> Result<bool> {
+    let mut remaining = batch_size;
+    loop {
+        let version = storage::read_schema_version(host)?;
+        let migration = match version {
+            version if version == SCHEMA_VERSION => return Ok(true),
+            version if version > SCHEMA_VERSION => return Err(Error::UnknownSchemaVersion),
+            version => &MIGRATIONS[version as usize],
+        };
+        if remaining == 0 {
+            host.mark_for_reboot().map_err(Error::from)?;
+            return Ok(false);
+        }
+        let cursor = storage::read_migration_cursor(host)?;
+        match migration.run(host, cursor, remaining)? {
+            Progress::Pending(cursor) => {
+                storage::store_migration_cursor(host, &cursor)?;
+                host.mark_for_reboot().map_err(Error::from)?;
+                return Ok(false);
+            }
+            Progress::Complete(migrated) => {
+                storage::store_schema_version(host, &(version + 1))?;
+                storage::delete_migration_cursor(host)?;
+                remaining -= migrated;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tezos_smart_rollup::{prelude::*, storage::path::RefPath, testing::prelude::MockHost};
+
+    use super::{migrate, migrate_batch, SCHEMA_VERSION};
+    use crate::core::error::Error;
+    use crate::core::public_key_hash::PublicKeyHash;
+    use crate::query::{get_merkle_root, get_tweet, get_tweet_proof, HostReader};
+    use crate::storage::{read_owned_tweets, read_u64, read_written_tweets, store_schema_version};
+
+    fn set_u64(host: &mut MockHost, path: &str, value: u64) {
+        let path = RefPath::assert_from(path.as_bytes());
+        host.store_write(&path, &value.to_be_bytes(), 0).unwrap();
+    }
+
+    fn set_string(host: &mut MockHost, path: &str, value: &str) {
+        let path = RefPath::assert_from(path.as_bytes());
+        host.store_write(&path, value.as_bytes(), 0).unwrap();
+    }
+
+    /// Writes tweets with the layout of the storage before the schema versions
+    fn populate_version_0(host: &mut MockHost, tweets: u64) {
+        for tweet_id in 0..tweets {
+            let author = "tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv";
+            set_string(host, &format!("/tweets/{}/author", tweet_id), author);
+            set_string(
+                host,
+                &format!("/tweets/{}/content", tweet_id),
+                "Hello world",
+            );
+            set_u64(host, &format!("/tweets/{}/likes", tweet_id), tweet_id);
+        }
+        set_u64(host, "/constants/tweet-counter", tweets);
+    }
+
+    fn read(host: &mut MockHost, path: &str) -> Option<u64> {
+        read_u64(host, &RefPath::assert_from(path.as_bytes())).unwrap()
+    }
+
+    #[test]
+    fn test_migrate_empty_storage() {
+        let mut host = MockHost::default();
+
+        assert!(migrate(&mut host).unwrap());
+        assert_eq!(Some(SCHEMA_VERSION), read(&mut host, "/schema/version"));
+    }
+
+    #[test]
+    fn test_migration_resumes_after_reboot() {
+        let mut host = MockHost::default();
+        populate_version_0(&mut host, 5);
+        let author = "tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv";
+        for tweet_id in 0..5 {
+            let owned = format!("/accounts/{}/tweets/owned/{}", author, tweet_id);
+            set_string(&mut host, &owned, "\0");
+        }
+
+        assert!(!migrate_batch(&mut host, 2).unwrap());
+        assert_eq!(Some(2), read(&mut host, "/schema/cursor"));
+        assert_eq!(Some(0), read(&mut host, "/tweets/1/retweets"));
+        assert_eq!(None, read(&mut host, "/tweets/2/retweets"));
+        assert_eq!(None, read(&mut host, "/schema/version"));
+
+        assert!(!migrate_batch(&mut host, 2).unwrap());
+        assert_eq!(Some(4), read(&mut host, "/schema/cursor"));
+
+        // The next migration continues with the rest of the batch
+        assert!(!migrate_batch(&mut host, 2).unwrap());
+        assert_eq!(Some(1), read(&mut host, "/schema/version"));
+        assert_eq!(Some(1), read(&mut host, "/schema/cursor"));
+
+        while !migrate_batch(&mut host, 2).unwrap() {}
+        assert_eq!(Some(SCHEMA_VERSION), read(&mut host, "/schema/version"));
+        assert_eq!(None, read(&mut host, "/schema/cursor"));
+
+        // The tweets of the version 0 are owned by their author, their leaves are in the merkle tree
+        let root = get_merkle_root(&mut HostReader(&mut host)).unwrap();
+        for tweet_id in 0..5 {
+            let proof = get_tweet_proof(&mut HostReader(&mut host), &tweet_id)
+                .unwrap()
+                .unwrap();
+            assert_eq!(author, proof.owner.as_ref().unwrap().to_string());
+            assert!(proof.verify(&root));
+        }
+
+        for tweet_id in 0..5 {
+            let tweet = get_tweet(&mut HostReader(&mut host), &tweet_id)
+                .unwrap()
+                .unwrap();
+            assert_eq!("Hello world", tweet.content);
+            assert_eq!(tweet_id, tweet.likes);
+            assert_eq!(0, tweet.retweets);
+        }
+
+        // An up to date storage is not migrated again
+        assert!(migrate_batch(&mut host, 2).unwrap());
+    }
+
+    #[test]
+    fn test_index_account_tweets() {
+        let mut host = MockHost::default();
+        populate_version_0(&mut host, 3);
+        let author = "tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv";
+        for tweet_id in 0..3 {
+            let written = format!("/accounts/{}/tweets/written/{}", author, tweet_id);
+            set_string(&mut host, &written, "\0");
+        }
+        for tweet_id in 0..2 {
+            let owned = format!("/accounts/{}/tweets/owned/{}", author, tweet_id);
+            set_string(&mut host, &owned, "\0");
+        }
+        // The tweet 2 has been transferred to another account
+        let transferee = "tz1TGu6TN5GSez2ndXXeDX6LgUDvLzPLqgYV";
+        let owned = format!("/accounts/{}/tweets/owned/2", transferee);
+        set_string(&mut host, &owned, "\0");
+
+        // Its owner cannot be found by the kernel, the migration stops
+        assert!(matches!(migrate(&mut host), Err(Error::UnknownTweetOwner)));
+        assert_eq!(Some(1), read(&mut host, "/schema/version"));
+
+        // The migration resumes once the owner is written
+        set_string(&mut host, "/tweets/2/owner", transferee);
+        assert!(migrate(&mut host).unwrap());
+
+        let author = PublicKeyHash::from_b58(author).unwrap();
+        let transferee = PublicKeyHash::from_b58(transferee).unwrap();
+        assert_eq!(
+            vec![0, 1, 2],
+            read_written_tweets(&mut host, &author).unwrap()
+        );
+        assert_eq!(vec![0, 1], read_owned_tweets(&mut host, &author).unwrap());
+        assert_eq!(vec![2], read_owned_tweets(&mut host, &transferee).unwrap());
+    }
+
+    #[test]
+    fn test_newer_schema_version() {
+        let mut host = MockHost::default();
+        store_schema_version(&mut host, &(SCHEMA_VERSION + 1)).unwrap();
+
+        assert!(matches!(
+            migrate(&mut host),
+            Err(Error::UnknownSchemaVersion)
+        ));
+    }
+}
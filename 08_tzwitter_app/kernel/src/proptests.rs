@@ -0,0 +1,275 @@
+//! Property based tests of the message parsing and of the state transitions
+//!
+//! Random sequences of signed operations, with valid and invalid signatures, nonces and ids,
+//! are driven through `verify_and_step` against a MockHost, the invariants of the state are checked after every step
+
+use ed25519_compact::{KeyPair, Seed};
+use proptest::prelude::*;
+use tezos_crypto_rs::hash::{Ed25519Signature, PublicKeyEd25519};
+use tezos_data_encoding::enc::{BinResult, BinWriter};
+use tezos_smart_rollup::{storage::path::RefPath, testing::prelude::MockHost};
+
+use crate::constants::MAGIC_BYTE;
+use crate::core::hash::Blake2b;
+use crate::core::message::{Content, Inner, Input, Message, PostTweet, Transfer};
+use crate::core::nonce::Nonce;
+use crate::core::public_key::PublicKey;
+use crate::core::public_key_hash::PublicKeyHash;
+use crate::core::signature::Signature;
+use crate::query::{get_merkle_root, get_tweet_proof, HostReader};
+use crate::stages::read_input;
+use crate::storage::{exists, read_u64};
+use crate::verify_and_step;
+
+/// Number of accounts sending the operations
+const ACCOUNTS: u8 = 3;
+
+/// The ids of the operations are drawn below this bound, so some of them do not exist
+const MAX_TWEET_ID: u64 = 8;
+
+/// An Ed25519 key of the tests
+struct Key {
+    key_pair: KeyPair,
+    public_key_hash: PublicKeyHash,
+}
+
+impl Key {
+    fn new(index: u8) -> Key {
+        let key_pair = KeyPair::from_seed(Seed::new([index + 1; 32]));
+        let public_key_hash = PublicKeyHash::from(&public_key(&key_pair));
+        Key {
+            key_pair,
+            public_key_hash,
+        }
+    }
+
+    /// Signs the blake2b of the hash of the inner, as the kernel verifies it
+    fn sign(&self, inner: &Inner) -> Signature {
+        let data = Blake2b::from(inner.hash().as_ref());
+        let signature = self.key_pair.sk.sign(data.as_ref(), None);
+        Signature::Ed25519(Ed25519Signature::try_from(signature.to_vec()).unwrap())
+    }
+}
+
+fn public_key(key_pair: &KeyPair) -> PublicKey {
+    PublicKey::Ed25519(PublicKeyEd25519::try_from(key_pair.pk.to_vec()).unwrap())
+}
+
+#[derive(Debug, Clone)]
+enum Action {
+    Post(String),
+    Like(u64),
+    Transfer(u64, u8),
+    Retweet(u64),
+}
+
+#[derive(Debug, Clone)]
+enum NonceChoice {
+    /// The nonce following the one of the account
+    Next,
+    /// The current nonce of the account, already used
+    Replay,
+    /// A nonce ahead of the next one, outside of the default nonce window
+    Ahead(u64),
+}
+
+#[derive(Debug, Clone)]
+struct Operation {
+    signer: u8,
+    action: Action,
+    nonce: NonceChoice,
+    /// The message is signed by another key than its public key
+    forged: bool,
+}
+
+impl Operation {
+    /// Checks if the kernel has to reject the operation before reading its content
+    fn is_invalid(&self) -> bool {
+        self.forged || !matches!(self.nonce, NonceChoice::Next)
+    }
+
+    fn message(&self, keys: &[Key], current: &Nonce) -> Message {
+        let signer = &keys[self.signer as usize];
+        let content = match &self.action {
+            Action::Post(content) => Content::PostTweet(PostTweet {
+                author: signer.public_key_hash.clone(),
+                content: content.clone(),
+            }),
+            Action::Like(tweet_id) => Content::LikeTweet(*tweet_id),
+            Action::Transfer(tweet_id, destination) => Content::Transfer(Transfer {
+                destination: keys[*destination as usize].public_key_hash.clone(),
+                tweet_id: *tweet_id,
+            }),
+            Action::Retweet(tweet_id) => Content::Retweet(*tweet_id),
+        };
+        let nonce = match self.nonce {
+            NonceChoice::Next => current.next(),
+            NonceChoice::Replay => Nonce(current.0),
+            NonceChoice::Ahead(ahead) => Nonce(current.0 + ahead),
+        };
+        let inner = Inner::new(nonce, content);
+        let signature = match self.forged {
+            false => signer.sign(&inner),
+            true => keys[(self.signer as usize + 1) % keys.len()].sign(&inner),
+        };
+        Message::new(public_key(&signer.key_pair), signature, inner)
+    }
+}
+
+fn action() -> impl Strategy<Value = Action> {
+    prop_oneof![
+        "[a-zA-Z ]{0,32}".prop_map(Action::Post),
+        (0..MAX_TWEET_ID).prop_map(Action::Like),
+        (0..MAX_TWEET_ID, 0..ACCOUNTS).prop_map(|(id, to)| Action::Transfer(id, to)),
+        (0..MAX_TWEET_ID).prop_map(Action::Retweet),
+    ]
+}
+
+fn nonce_choice() -> impl Strategy<Value = NonceChoice> {
+    prop_oneof![
+        8 => Just(NonceChoice::Next),
+        1 => Just(NonceChoice::Replay),
+        1 => (2..5u64).prop_map(NonceChoice::Ahead),
+    ]
+}
+
+fn operation() -> impl Strategy<Value = Operation> {
+    (
+        0..ACCOUNTS,
+        action(),
+        nonce_choice(),
+        prop::bool::weighted(0.1),
+    )
+        .prop_map(|(signer, action, nonce, forged)| Operation {
+            signer,
+            action,
+            nonce,
+            forged,
+        })
+}
+
+fn has(host: &mut MockHost, path: &str) -> bool {
+    exists(host, &RefPath::assert_from(path.as_bytes())).unwrap()
+}
+
+fn read(host: &mut MockHost, path: &str) -> u64 {
+    read_u64(host, &RefPath::assert_from(path.as_bytes()))
+        .unwrap()
+        .unwrap_or_default()
+}
+
+fn nonces(host: &mut MockHost, keys: &[Key]) -> Vec<u64> {
+    keys.iter()
+        .map(|key| {
+            read(
+                host,
+                &format!("/accounts/{}/nonce", key.public_key_hash.to_string()),
+            )
+        })
+        .collect()
+}
+
+/// Checks the invariants of the tweets
+///
+/// - the tweet counter equals the number of tweets
+/// - each tweet has exactly one owner
+/// - the likes of a tweet equal the like flags of the accounts
+/// - the merkle root commits to each tweet and its owner
+fn check_tweets(host: &mut MockHost, keys: &[Key]) -> std::result::Result<(), TestCaseError> {
+    let counter = read(host, "/constants/tweet-counter");
+    let root = get_merkle_root(&mut HostReader(host)).unwrap();
+    for tweet_id in 0..counter {
+        let author_path = format!("/tweets/{}/author", tweet_id);
+        prop_assert!(has(host, &author_path));
+
+        let owners = keys
+            .iter()
+            .filter(|key| {
+                let account = key.public_key_hash.to_string();
+                has(
+                    host,
+                    &format!("/accounts/{}/tweets/owned/{}", account, tweet_id),
+                )
+            })
+            .count();
+        prop_assert_eq!(1, owners);
+
+        let flags = keys
+            .iter()
+            .filter(|key| {
+                let account = key.public_key_hash.to_string();
+                has(host, &format!("/accounts/{}/likes/{}", account, tweet_id))
+            })
+            .count() as u64;
+        prop_assert_eq!(flags, read(host, &format!("/tweets/{}/likes", tweet_id)));
+
+        let proof = get_tweet_proof(&mut HostReader(host), &tweet_id)
+            .unwrap()
+            .unwrap();
+        let owned_path = proof
+            .owner
+            .as_ref()
+            .map(|owner| format!("/accounts/{}/tweets/owned/{}", owner.to_string(), tweet_id));
+        prop_assert!(owned_path.map_or(false, |owned_path| has(host, &owned_path)));
+        prop_assert!(proof.verify(&root));
+    }
+    let next_tweet_path = format!("/tweets/{}", counter);
+    prop_assert!(!has(host, &next_tweet_path));
+    Ok(())
+}
+
+/// Writes the JSON of a message as an external message of tzwitter
+struct ExternalMessage(Vec<u8>);
+
+impl BinWriter for ExternalMessage {
+    fn bin_write(&self, output: &mut Vec<u8>) -> BinResult {
+        output.push(MAGIC_BYTE);
+        output.extend_from_slice(&self.0);
+        Ok(())
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn signed_messages_are_read_from_the_inbox(operation in operation(), nonce in 0..1_000u64) {
+        let keys: Vec<Key> = (0..ACCOUNTS).map(Key::new).collect();
+        let message = operation.message(&keys, &Nonce(nonce));
+        let json = serde_json_wasm::to_string(&message).unwrap();
+
+        let mut host = MockHost::default();
+        host.add_external(ExternalMessage(json.into_bytes()));
+        let read = match read_input(&mut host) {
+            Ok((Input::Message(read), _)) => read,
+            _ => panic!("The signed message should be read as a tzwitter message"),
+        };
+        prop_assert_eq!(message.hash().to_string(), read.hash().to_string());
+    }
+
+    #[test]
+    fn operations_keep_the_invariants(operations in prop::collection::vec(operation(), 1..48)) {
+        let mut host = MockHost::default();
+        let keys: Vec<Key> = (0..ACCOUNTS).map(Key::new).collect();
+
+        for (index, operation) in operations.iter().enumerate() {
+            let before = nonces(&mut host, &keys);
+            let signer = operation.signer as usize;
+            let message = operation.message(&keys, &Nonce(before[signer]));
+            let result = verify_and_step(&mut host, message, index as u32);
+            let after = nonces(&mut host, &keys);
+
+            // The nonce of the signer moves by one for each accepted message, the others are untouched
+            for (account, (before, after)) in before.iter().zip(&after).enumerate() {
+                match account == signer && !operation.is_invalid() {
+                    true => prop_assert_eq!(before + 1, *after),
+                    false => prop_assert_eq!(before, after),
+                }
+            }
+            if operation.is_invalid() {
+                prop_assert!(result.is_err());
+            }
+            check_tweets(&mut host, &keys)?;
+        }
+    }
+}
@@ -0,0 +1,611 @@
+//! Read side of the durable state of tzwitter
+//!
+//! The queries decode the values with the byte formats written by the kernel.
+//! They run over any Runtime with HostReader,
+//! or over a key/value getter (like the sequencer's Node::get_value/get_subkeys) with KeyValueReader
+
+use tezos_smart_rollup::{prelude::*, storage::path::*};
+
+use crate::constants::{MERKLE_TREE_DEPTH, TRENDING_TOP_SIZE};
+
+use crate::core::account::Account;
+use crate::core::error::*;
+use crate::core::event::Event;
+use crate::core::hash::Blake2b;
+use crate::core::merkle::{self, Node, TweetProof};
+use crate::core::metrics::LevelMetrics;
+use crate::core::nonce::Nonce;
+use crate::core::poll::{Poll, PollResults};
+use crate::core::public_key_hash::PublicKeyHash;
+use crate::core::receipt::Receipt;
+use crate::core::trending::TopTweets;
+use crate::core::tweet::{Tweet, TweetVersion};
+use crate::storage::{
+    self, account_owned_tweet_path, account_owned_tweets_path, account_written_tweet_path,
+    account_written_tweets_path, event_path, events_count_path, merkle_node_path, metrics_path,
+    migrated_to_path, nonce_path, poll_results_field_path, receipt_error_path,
+    receipt_success_path, trending_likes_path, tweet_author_path, tweet_content_path,
+    tweet_history_count_path, tweet_likes_path, tweet_owner_path, tweet_poll_closes_at_level_path,
+    tweet_poll_option_path, tweet_poll_options_count_path, tweet_poll_tally_path,
+    tweet_quoted_path, tweet_retweets_path, tweet_version_content_path, tweet_version_level_path,
+    MERKLE_ROOT, TRENDING_TOP, TWEET_COUNTER,
+};
+
+/// Gives access to the values of the durable state
+pub trait StateReader {
+    /// Reads the value at the given path
+    fn read(&mut self, path: &impl Path) -> Result<Option<Vec<u8>>>;
+
+    /// Lists the subkeys of the given path
+    ///
+    /// Returns None when the state cannot be listed, as in the kernel
+    fn subkeys(&mut self, _path: &impl Path) -> Result<Option<Vec<String>>> {
+        Ok(None)
+    }
+
+    /// Checks if a value is present at the given path
+    fn has(&mut self, path: &impl Path) -> Result<bool> {
+        self.read(path).map(|value| value.is_some())
+    }
+}
+
+/// Reads the durable state from a Runtime
+pub struct HostReader<'a, R>(pub &'a mut R);
+
+impl<'a, R: Runtime> StateReader for HostReader<'a, R> {
+    fn read(&mut self, path: &impl Path) -> Result<Option<Vec<u8>>> {
+        storage::read_bytes(self.0, path)
+    }
+
+    fn has(&mut self, path: &impl Path) -> Result<bool> {
+        storage::exists(self.0, path)
+    }
+}
+
+/// Reads the durable state from a key/value getter
+pub struct KeyValueReader<V, S> {
+    get_value: V,
+    get_subkeys: S,
+}
+
+impl<V, S> KeyValueReader<V, S>
+where
+    V: FnMut(&str) -> Option<Vec<u8>>,
+    S: FnMut(&str) -> Option<Vec<String>>,
+{
+    pub fn new(get_value: V, get_subkeys: S) -> Self {
+        KeyValueReader {
+            get_value,
+            get_subkeys,
+        }
+    }
+}
+
+impl<V, S> StateReader for KeyValueReader<V, S>
+where
+    V: FnMut(&str) -> Option<Vec<u8>>,
+    S: FnMut(&str) -> Option<Vec<String>>,
+{
+    fn read(&mut self, path: &impl Path) -> Result<Option<Vec<u8>>> {
+        let path = path_to_str(path)?;
+        Ok((self.get_value)(path))
+    }
+
+    fn subkeys(&mut self, path: &impl Path) -> Result<Option<Vec<String>>> {
+        let path = path_to_str(path)?;
+        Ok(Some((self.get_subkeys)(path).unwrap_or_default()))
+    }
+}
+
+fn path_to_str(path: &impl Path) -> Result<&str> {
+    std::str::from_utf8(path.as_bytes()).map_err(|_| Error::StateDeserializarion)
+}
+
+/// Decodes an u64 stored in big endian
+fn decode_u64(bytes: Vec<u8>) -> Result<u64> {
+    let bytes: [u8; 8] = bytes.try_into().map_err(|_| Error::StateDeserializarion)?;
+    Ok(u64::from_be_bytes(bytes))
+}
+
+/// Decodes an u32 stored in big endian
+fn decode_u32(bytes: Vec<u8>) -> Result<u32> {
+    let bytes: [u8; 4] = bytes.try_into().map_err(|_| Error::StateDeserializarion)?;
+    Ok(u32::from_be_bytes(bytes))
+}
+
+/// Decodes an utf8 string
+fn decode_string(bytes: Vec<u8>) -> Result<String> {
+    String::from_utf8(bytes).map_err(Error::from)
+}
+
+/// Get a tweet by its id
+pub fn get_tweet(reader: &mut impl StateReader, tweet_id: &u64) -> Result<Option<Tweet>> {
+    let author = reader.read(&tweet_author_path(tweet_id)?)?;
+    let content = reader.read(&tweet_content_path(tweet_id)?)?;
+    let likes = reader.read(&tweet_likes_path(tweet_id)?)?;
+
+    match (author, content, likes) {
+        (Some(author), Some(content), Some(likes)) => {
+            let author = PublicKeyHash::from_b58(&decode_string(author)?)?;
+            // The tweets posted before the retweets do not have a counter
+            let retweets = match reader.read(&tweet_retweets_path(tweet_id)?)? {
+                None => 0,
+                Some(retweets) => decode_u64(retweets)?,
+            };
+            let quoted = match reader.read(&tweet_quoted_path(tweet_id)?)? {
+                None => None,
+                Some(quoted) => Some(decode_u64(quoted)?),
+            };
+            Ok(Some(Tweet {
+                author,
+                content: decode_string(content)?,
+                likes: decode_u64(likes)?,
+                retweets,
+                quoted,
+            }))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Get the owner of a tweet
+///
+/// The tweets transferred before the owners were stored have no known owner
+pub fn get_tweet_owner(
+    reader: &mut impl StateReader,
+    tweet_id: &u64,
+) -> Result<Option<PublicKeyHash>> {
+    match reader.read(&tweet_owner_path(tweet_id)?)? {
+        None => Ok(None),
+        Some(owner) => Ok(Some(PublicKeyHash::from_b58(&decode_string(owner)?)?)),
+    }
+}
+
+/// Get the merkle root of the tweets
+///
+/// The root of the empty tree is returned before the first tweet
+pub fn get_merkle_root(reader: &mut impl StateReader) -> Result<Node> {
+    match reader.read(&MERKLE_ROOT)? {
+        None => Ok(merkle::empty_nodes()[MERKLE_TREE_DEPTH]),
+        Some(root) => merkle::decode_node(&root),
+    }
+}
+
+/// Get the proof that a tweet is committed by the merkle root of the tweets
+///
+/// A client holding a trusted root checks the proof with TweetProof::verify
+pub fn get_tweet_proof(
+    reader: &mut impl StateReader,
+    tweet_id: &u64,
+) -> Result<Option<TweetProof>> {
+    let tweet = match get_tweet(reader, tweet_id)? {
+        None => return Ok(None),
+        Some(tweet) => tweet,
+    };
+    let owner = get_tweet_owner(reader, tweet_id)?;
+    let empty = merkle::empty_nodes();
+    let mut siblings = Vec::with_capacity(MERKLE_TREE_DEPTH);
+    for (height, empty) in empty.iter().take(MERKLE_TREE_DEPTH).enumerate() {
+        let index = (tweet_id >> height) ^ 1;
+        let sibling = match reader.read(&merkle_node_path(&height, &index)?)? {
+            None => *empty,
+            Some(sibling) => merkle::decode_node(&sibling)?,
+        };
+        siblings.push(sibling);
+    }
+    Ok(Some(TweetProof {
+        tweet_id: *tweet_id,
+        tweet,
+        owner,
+        siblings,
+    }))
+}
+
+/// Get the previous versions of an edited tweet, from the oldest to the latest
+pub fn get_tweet_history(
+    reader: &mut impl StateReader,
+    tweet_id: &u64,
+) -> Result<Vec<TweetVersion>> {
+    let count = match reader.read(&tweet_history_count_path(tweet_id)?)? {
+        None => 0,
+        Some(count) => decode_u64(count)?,
+    };
+    let mut history = Vec::new();
+    for version in 0..count {
+        let content = reader.read(&tweet_version_content_path(tweet_id, &version)?)?;
+        let level = reader.read(&tweet_version_level_path(tweet_id, &version)?)?;
+        match (content, level) {
+            (Some(content), Some(level)) => history.push(TweetVersion {
+                content: decode_string(content)?,
+                level: decode_u32(level)?,
+            }),
+            _ => return Err(Error::StateDeserializarion),
+        }
+    }
+    Ok(history)
+}
+
+/// Get the poll attached to a tweet
+///
+/// Returns None if the tweet is not a poll
+pub fn get_poll(reader: &mut impl StateReader, tweet_id: &u64) -> Result<Option<Poll>> {
+    let closes_at_level = match reader.read(&tweet_poll_closes_at_level_path(tweet_id)?)? {
+        None => return Ok(None),
+        Some(level) => decode_u32(level)?,
+    };
+    let count = reader
+        .read(&tweet_poll_options_count_path(tweet_id)?)?
+        .ok_or(Error::StateDeserializarion)?;
+    let mut options = Vec::new();
+    for option in 0..decode_u32(count)? {
+        let label = reader
+            .read(&tweet_poll_option_path(tweet_id, &option)?)?
+            .ok_or(Error::StateDeserializarion)?;
+        options.push(decode_string(label)?);
+    }
+    Ok(Some(Poll {
+        options,
+        closes_at_level,
+    }))
+}
+
+/// Get the current number of votes of each option of a poll
+pub fn get_poll_tallies(reader: &mut impl StateReader, tweet_id: &u64) -> Result<Vec<u64>> {
+    let count = match reader.read(&tweet_poll_options_count_path(tweet_id)?)? {
+        None => return Err(Error::NotAPoll),
+        Some(count) => decode_u32(count)?,
+    };
+    let mut tallies = Vec::new();
+    for option in 0..count {
+        match reader.read(&tweet_poll_tally_path(tweet_id, &option)?)? {
+            None => tallies.push(0),
+            Some(tally) => tallies.push(decode_u64(tally)?),
+        }
+    }
+    Ok(tallies)
+}
+
+/// Get the frozen results of a closed poll
+///
+/// Returns None while the poll is open
+pub fn get_poll_results(
+    reader: &mut impl StateReader,
+    tweet_id: &u64,
+) -> Result<Option<PollResults>> {
+    let votes = match reader.read(&poll_results_field_path(tweet_id, "/votes")?)? {
+        None => return Ok(None),
+        Some(votes) => decode_u64(votes)?,
+    };
+    let count = reader
+        .read(&tweet_poll_options_count_path(tweet_id)?)?
+        .ok_or(Error::StateDeserializarion)?;
+    let mut tallies = Vec::new();
+    for option in 0..decode_u32(count)? {
+        let tally = reader
+            .read(&poll_results_field_path(tweet_id, &format!("/{}", option))?)?
+            .ok_or(Error::StateDeserializarion)?;
+        tallies.push(decode_u64(tally)?);
+    }
+    Ok(Some(PollResults { tallies, votes }))
+}
+
+/// Get the most liked tweets of the last TRENDING_WINDOW levels, with their likes
+pub fn get_top_tweets(reader: &mut impl StateReader) -> Result<TopTweets> {
+    let mut top = get_trending_candidates(reader)?;
+    top.truncate(TRENDING_TOP_SIZE);
+    Ok(top)
+}
+
+/// Get the TRENDING_CANDIDATES most liked tweets of the last TRENDING_WINDOW levels
+pub fn get_trending_candidates(reader: &mut impl StateReader) -> Result<TopTweets> {
+    match reader.read(&TRENDING_TOP)? {
+        None => Ok(TopTweets::default()),
+        Some(top) => TopTweets::decode(&top),
+    }
+}
+
+/// Get the metrics recorded at a level by a kernel built with the metrics feature
+pub fn get_level_metrics(
+    reader: &mut impl StateReader,
+    level: &u32,
+) -> Result<Option<LevelMetrics>> {
+    match reader.read(&metrics_path(level)?)? {
+        None => Ok(None),
+        Some(metrics) => LevelMetrics::decode(&metrics).map(Some),
+    }
+}
+
+/// Get the likes received by a tweet during the last TRENDING_WINDOW levels
+pub fn get_trending_likes(reader: &mut impl StateReader, tweet_id: &u64) -> Result<u64> {
+    match reader.read(&trending_likes_path(tweet_id)?)? {
+        None => Ok(0),
+        Some(likes) => decode_u64(likes),
+    }
+}
+
+/// List the ids of the tweets under the given prefix
+///
+/// When the state cannot be listed, every tweet id is checked
+fn list_tweets(
+    reader: &mut impl StateReader,
+    prefix: &OwnedPath,
+    tweet_path: impl Fn(&u64) -> Result<OwnedPath>,
+) -> Result<Vec<u64>> {
+    match reader.subkeys(prefix)? {
+        Some(subkeys) => {
+            let mut ids: Vec<u64> = subkeys
+                .iter()
+                .filter_map(|subkey| subkey.parse().ok())
+                .collect();
+            ids.sort_unstable();
+            Ok(ids)
+        }
+        None => {
+            let counter = match reader.read(&TWEET_COUNTER)? {
+                None => 0,
+                Some(counter) => decode_u64(counter)?,
+            };
+            let mut ids = Vec::new();
+            for tweet_id in 0..counter {
+                if reader.has(&tweet_path(&tweet_id)?)? {
+                    ids.push(tweet_id);
+                }
+            }
+            Ok(ids)
+        }
+    }
+}
+
+/// List the ids of the tweets owned by an account
+pub fn list_owned(
+    reader: &mut impl StateReader,
+    public_key_hash: &PublicKeyHash,
+) -> Result<Vec<u64>> {
+    let prefix = account_owned_tweets_path(public_key_hash)?;
+    list_tweets(reader, &prefix, |tweet_id| {
+        account_owned_tweet_path(public_key_hash, tweet_id)
+    })
+}
+
+/// List the ids of the tweets written by an account
+pub fn list_written(
+    reader: &mut impl StateReader,
+    public_key_hash: &PublicKeyHash,
+) -> Result<Vec<u64>> {
+    let prefix = account_written_tweets_path(public_key_hash)?;
+    list_tweets(reader, &prefix, |tweet_id| {
+        account_written_tweet_path(public_key_hash, tweet_id)
+    })
+}
+
+/// Get the account of a public key hash
+///
+/// An account that has never sent a message has the default nonce
+pub fn get_account(
+    reader: &mut impl StateReader,
+    public_key_hash: PublicKeyHash,
+) -> Result<Account> {
+    let nonce = match reader.read(&nonce_path(&public_key_hash)?)? {
+        None => 0,
+        Some(nonce) => decode_u64(nonce)?,
+    };
+    Ok(Account {
+        public_key_hash,
+        nonce: Nonce(nonce),
+    })
+}
+
+/// Get the account an address has been migrated to
+///
+/// Returns None if the address has never been migrated
+pub fn get_migrated_to(
+    reader: &mut impl StateReader,
+    public_key_hash: &PublicKeyHash,
+) -> Result<Option<PublicKeyHash>> {
+    match reader.read(&migrated_to_path(public_key_hash)?)? {
+        None => Ok(None),
+        Some(migrated_to) => PublicKeyHash::from_b58(&decode_string(migrated_to)?).map(Some),
+    }
+}
+
+/// Get the receipt of a message by its hash
+pub fn get_receipt(reader: &mut impl StateReader, hash: &Blake2b) -> Result<Option<Receipt>> {
+    let success = match reader.read(&receipt_success_path(hash)?)? {
+        None => return Ok(None),
+        Some(success) => success == [0x01],
+    };
+    let error = match reader.read(&receipt_error_path(hash)?)? {
+        None => None,
+        Some(error) => Some(decode_string(error)?),
+    };
+    Ok(Some(Receipt::from_state(hash.clone(), success, error)))
+}
+
+/// Get the events appended during a level, in the order of the operations
+pub fn get_events(reader: &mut impl StateReader, level: &u32) -> Result<Vec<Event>> {
+    let count = match reader.read(&events_count_path(level)?)? {
+        None => 0,
+        Some(count) => decode_u64(count)?,
+    };
+    let mut events = Vec::new();
+    for index in 0..count {
+        let event = reader
+            .read(&event_path(level, &index)?)?
+            .ok_or(Error::StateDeserializarion)?;
+        events.push(Event::decode(&event)?);
+    }
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use tezos_smart_rollup::testing::prelude::MockHost;
+
+    use super::{
+        get_account, get_merkle_root, get_receipt, get_tweet, get_tweet_proof, list_owned,
+        list_written, HostReader, KeyValueReader,
+    };
+    use crate::core::account::Account;
+    use crate::core::error::Error;
+    use crate::core::hash::Blake2b;
+    use crate::core::nonce::Nonce;
+    use crate::core::public_key_hash::PublicKeyHash;
+    use crate::core::receipt::Receipt;
+    use crate::core::tweet::Tweet;
+    use crate::storage::{
+        add_owned_tweet_to_account, add_written_tweet_to_account, commit_tweet,
+        increment_tweet_counter, read_tweet, store_account, store_receipt, store_tweet,
+    };
+
+    const ALICE: &str = "tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv";
+    const BOB: &str = "tz1hEW4Bz2tLWBJbefwx6wCqdcX4CcjyLXdq";
+
+    fn public_key_hash(tz1: &str) -> PublicKeyHash {
+        PublicKeyHash::from_b58(tz1).unwrap()
+    }
+
+    /// Alice writes the tweets 0 and 1, and transfers the tweet 1 to Bob
+    fn prepare_host() -> MockHost {
+        let mut host = MockHost::default();
+        for content in ["Hello world", "Hello Bob"] {
+            let id = increment_tweet_counter(&mut host).unwrap();
+            let tweet = Tweet {
+                author: public_key_hash(ALICE),
+                content: content.to_string(),
+                likes: id,
+                retweets: 0,
+                quoted: None,
+            };
+            store_tweet(&mut host, &id, &tweet).unwrap();
+            add_written_tweet_to_account(&mut host, &public_key_hash(ALICE), &id).unwrap();
+        }
+        add_owned_tweet_to_account(&mut host, &public_key_hash(ALICE), &0).unwrap();
+        add_owned_tweet_to_account(&mut host, &public_key_hash(BOB), &1).unwrap();
+        let account = Account {
+            public_key_hash: public_key_hash(ALICE),
+            nonce: Nonce(3),
+        };
+        store_account(&mut host, &account).unwrap();
+        host
+    }
+
+    #[test]
+    fn test_get_tweet_from_host() {
+        let mut host = prepare_host();
+        let mut reader = HostReader(&mut host);
+
+        let tweet = get_tweet(&mut reader, &1).unwrap().unwrap();
+        assert_eq!(ALICE, tweet.author.to_string());
+        assert_eq!("Hello Bob", tweet.content);
+        assert_eq!(1, tweet.likes);
+
+        let tweet = get_tweet(&mut reader, &2).unwrap();
+        assert!(tweet.is_none());
+    }
+
+    #[test]
+    fn test_list_tweets_from_host() {
+        let mut host = prepare_host();
+        let mut reader = HostReader(&mut host);
+
+        let owned = list_owned(&mut reader, &public_key_hash(BOB)).unwrap();
+        assert_eq!(vec![1], owned);
+
+        let written = list_written(&mut reader, &public_key_hash(ALICE)).unwrap();
+        assert_eq!(vec![0, 1], written);
+    }
+
+    #[test]
+    fn test_tweet_proof_from_host() {
+        let mut host = prepare_host();
+        commit_tweet(&mut host, &0).unwrap();
+        commit_tweet(&mut host, &1).unwrap();
+
+        let root = get_merkle_root(&mut HostReader(&mut host)).unwrap();
+        let proof = get_tweet_proof(&mut HostReader(&mut host), &1)
+            .unwrap()
+            .unwrap();
+        assert_eq!(BOB, proof.owner.as_ref().unwrap().to_string());
+        assert_eq!("Hello Bob", proof.tweet.content);
+        assert!(proof.verify(&root));
+
+        // A like changes the root, the previous proof does not match it anymore
+        let tweet = read_tweet(&mut host, &0).unwrap().unwrap().like();
+        store_tweet(&mut host, &0, &tweet).unwrap();
+        commit_tweet(&mut host, &0).unwrap();
+        let new_root = get_merkle_root(&mut HostReader(&mut host)).unwrap();
+        assert!(!proof.verify(&new_root));
+
+        let proof = get_tweet_proof(&mut HostReader(&mut host), &1)
+            .unwrap()
+            .unwrap();
+        assert!(proof.verify(&new_root));
+        assert!(get_tweet_proof(&mut HostReader(&mut host), &2)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_get_account_from_host() {
+        let mut host = prepare_host();
+        let mut reader = HostReader(&mut host);
+
+        let account = get_account(&mut reader, public_key_hash(ALICE)).unwrap();
+        assert!(account.nonce == Nonce(3));
+
+        let account = get_account(&mut reader, public_key_hash(BOB)).unwrap();
+        assert!(account.nonce == Nonce::default());
+    }
+
+    #[test]
+    fn test_get_receipt_from_host() {
+        let mut host = MockHost::default();
+        let hash = Blake2b::from(b"message".as_slice());
+        let receipt = Receipt::new(hash.clone(), &Err(Error::InvalidNonce));
+        store_receipt(&mut host, &receipt).unwrap();
+
+        let mut reader = HostReader(&mut host);
+        let receipt = get_receipt(&mut reader, &hash).unwrap().unwrap();
+        assert!(!receipt.success());
+        assert_eq!(Some(&"Invalid nonce".to_string()), receipt.error());
+
+        let hash = Blake2b::from(b"unknown".as_slice());
+        assert!(get_receipt(&mut reader, &hash).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_key_value_reader() {
+        let owned_path = format!("/accounts/{}/tweets/owned", BOB);
+        let nonce_path = format!("/accounts/{}/nonce", BOB);
+        let mut values: HashMap<String, Vec<u8>> = HashMap::new();
+        values.insert("/tweets/4/author".to_string(), ALICE.as_bytes().to_vec());
+        values.insert("/tweets/4/content".to_string(), b"Hello world".to_vec());
+        values.insert("/tweets/4/likes".to_string(), 2_u64.to_be_bytes().to_vec());
+        values.insert(nonce_path, 7_u64.to_be_bytes().to_vec());
+        let mut subkeys: HashMap<String, Vec<String>> = HashMap::new();
+        subkeys.insert(
+            owned_path,
+            vec!["12".to_string(), "4".to_string(), "likes".to_string()],
+        );
+
+        let mut reader = KeyValueReader::new(
+            |path: &str| values.get(path).cloned(),
+            |path: &str| subkeys.get(path).cloned(),
+        );
+
+        let tweet = get_tweet(&mut reader, &4).unwrap().unwrap();
+        assert_eq!("Hello world", tweet.content);
+        assert_eq!(2, tweet.likes);
+
+        let owned = list_owned(&mut reader, &public_key_hash(BOB)).unwrap();
+        assert_eq!(vec![4, 12], owned);
+
+        let written = list_written(&mut reader, &public_key_hash(BOB)).unwrap();
+        assert!(written.is_empty());
+
+        let account = get_account(&mut reader, public_key_hash(BOB)).unwrap();
+        assert!(account.nonce == Nonce(7));
+    }
+}
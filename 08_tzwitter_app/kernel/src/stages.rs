@@ -1,81 +1,558 @@
 use crate::{
-    constants::{L1_TOKEN_CONTRACT_ADDRESS, L1_TOKEN_CONTRACT_ENTRYPOINT, MAGIC_BYTE},
+    constants::{
+        L1_REGISTRY_CONTRACT_ENTRYPOINT, L1_TOKEN_CONTRACT_ADDRESS, L1_TOKEN_CONTRACT_ENTRYPOINT,
+        MAGIC_BYTE, MAX_DAC_LEVELS, MAX_DEFERRED_MESSAGES, MAX_EVENTS_PER_LEVEL, MAX_POLL_OPTIONS,
+        MAX_SIGNERS, MAX_TWEET_BODY_SIZE, TRENDING_CANDIDATES, TRENDING_WINDOW,
+    },
     core::{
         account::Account,
-        message::{Content, Inner, PostTweet, Transfer},
-        nonce::Nonce,
+        collect::CollectState,
+        event::Event,
+        message::{
+            AuthorizeSessionKey, Content, EditTweet, Inner, Input, MigrateAccount, PostPoll,
+            PostTweet, PostTweetRef, Quote, SetSigners, Transfer, Vote,
+        },
+        multisig::Signers,
+        outbox::PendingOutput,
+        pause::PauseMode,
+        poll::Poll,
+        public_key_hash::PublicKeyHash,
+        quota::Quota,
+        session_key::SessionKey,
         tweet::Tweet,
     },
     storage::{
         self, add_collecting_tweet_to_account, add_owned_tweet_to_account,
-        add_written_tweet_to_account, increment_tweet_counter, is_liked, is_not_collected,
-        is_owner, read_tweet, set_collected_block, set_like_flag, store_tweet,
+        add_written_tweet_to_account, increment_outbox_counter, increment_tweet_counter, is_liked,
+        is_outbox_queue_empty, is_owner, peek_outbox_queue, pop_outbox_queue, push_outbox_queue,
+        read_balance, read_collect_state, read_config, read_quota, read_tweet, reset_collect,
+        set_collect_finalized, set_collect_outbox, set_collected_block, set_collected_flag,
+        set_like_flag, store_balance, store_tweet, store_tweet_body,
     },
 };
 
+use kernel_router::{Dispatch, Router};
 use num_bigint::ToBigInt;
-use tezos_data_encoding::{enc::BinWriter, types::Zarith};
+use tezos_data_encoding::{enc::BinWriter, nom::NomReader, types::Zarith};
 use tezos_smart_rollup::{
-    michelson::{MichelsonContract, MichelsonInt, MichelsonPair, MichelsonString},
+    core_unsafe::PREIMAGE_HASH_SIZE,
+    dac::{reveal_loop, V0SliceContentPage, MAX_PAGE_SIZE},
+    host::RuntimeError,
+    michelson::{
+        Michelson, MichelsonBytes, MichelsonContract, MichelsonInt, MichelsonPair, MichelsonString,
+    },
     outbox::{OutboxMessage, OutboxMessageTransaction, OutboxMessageTransactionBatch},
     prelude::*,
     types::{Contract, Entrypoint},
 };
+use tezos_smart_rollup_host::Error as HostError;
 
 use crate::core::error::*;
 use crate::core::message::Message;
 
 /// Read a message from the inbox
 ///
-/// It will only read messages External Messages with the MAGIC_BYTE
+/// It will only read External Messages with the MAGIC_BYTE,
+/// the start of level messages and the transfers from the L1 token contract
 /// Benchmark: 2_000_000 ticks (processing an inbox with only one message)
-pub fn read_input<R: Runtime>(host: &mut R) -> std::result::Result<(Message, u32), ReadInputError> {
-    let input = host.read_input().map_err(ReadInputError::Runtime)?;
-    match input {
+pub fn read_input<R: Runtime>(host: &mut R) -> std::result::Result<(Input, u32), ReadInputError> {
+    match router().dispatch(host).map_err(ReadInputError::Runtime)? {
         None => Err(ReadInputError::EndOfInbox),
-        Some(message) => {
-            let data = message.as_ref();
-            match data {
-                [0x01, MAGIC_BYTE, ..] => {
-                    let bytes = data.iter().skip(2).copied().collect();
-                    let str = String::from_utf8(bytes).map_err(ReadInputError::FromUtf8Error)?;
-                    let msg = serde_json_wasm::from_str(&str).map_err(ReadInputError::SerdeJson)?;
-                    Ok((msg, message.level))
-                }
-                _ => Err(ReadInputError::NotATzwitterMessage),
+        Some((Dispatch::Handled(input), info)) => input.map(|input| (input, info.level)),
+        Some((Dispatch::Unhandled, _)) | Some((Dispatch::Malformed, _)) => {
+            Err(ReadInputError::NotATzwitterMessage)
+        }
+    }
+}
+
+type InputRouter<R> = Router<R, std::result::Result<Input, ReadInputError>, MichelsonBytes>;
+
+/// The handlers of the messages read by the kernel
+fn router<R: Runtime>() -> InputRouter<R> {
+    Router::new()
+        .external(MAGIC_BYTE, |_, _, data| {
+            let str = String::from_utf8(data.to_vec()).map_err(ReadInputError::FromUtf8Error)?;
+            let msg = serde_json_wasm::from_str(&str).map_err(ReadInputError::SerdeJson)?;
+            Ok(Input::Message(msg))
+        })
+        .transfer_from(L1_TOKEN_CONTRACT_ADDRESS, |_, _, transfer| {
+            let MichelsonBytes(bytes) = transfer.payload;
+            read_l1_message(&bytes).ok_or(ReadInputError::InvalidTransferPayload)
+        })
+        .start_of_level(|_, _| Ok(Input::StartOfLevel))
+}
+
+/// Messages sent by the L1 token contract to the rollup
+///
+/// The payload of the transfer is the packed value of:
+/// (or (nat %minted) (pair %deposit address nat))
+#[derive(Debug, PartialEq, Eq)]
+pub enum L1Message {
+    Minted(MichelsonInt),
+    Deposit(MichelsonPair<MichelsonContract, MichelsonInt>),
+}
+
+// The Micheline encodings of Left and Right, primitives with one argument and no annotation
+const MICHELINE_LEFT: [u8; 2] = [0x05, 0x05];
+const MICHELINE_RIGHT: [u8; 2] = [0x05, 0x08];
+
+impl BinWriter for L1Message {
+    fn bin_write(&self, output: &mut Vec<u8>) -> tezos_data_encoding::enc::BinResult {
+        match self {
+            L1Message::Minted(tweet_id) => {
+                output.extend_from_slice(&MICHELINE_LEFT);
+                tweet_id.bin_write(output)
+            }
+            L1Message::Deposit(deposit) => {
+                output.extend_from_slice(&MICHELINE_RIGHT);
+                deposit.bin_write(output)
             }
         }
     }
 }
 
+/// Decode the packed message sent by the L1 token contract
+fn read_l1_message(bytes: &[u8]) -> Option<Input> {
+    let packed = match bytes {
+        [0x05, packed @ ..] => packed,
+        _ => return None,
+    };
+    let message = if let Some(minted) = packed.strip_prefix(&MICHELINE_LEFT) {
+        let (_, tweet_id) = MichelsonInt::nom_read(minted).ok()?;
+        L1Message::Minted(tweet_id)
+    } else if let Some(deposit) = packed.strip_prefix(&MICHELINE_RIGHT) {
+        let (_, deposit) = MichelsonPair::nom_read(deposit).ok()?;
+        L1Message::Deposit(deposit)
+    } else {
+        return None;
+    };
+    match message {
+        L1Message::Minted(tweet_id) => {
+            let tweet_id = u64::try_from(&tweet_id.0 .0).ok()?;
+            Some(Input::Minted(tweet_id))
+        }
+        L1Message::Deposit(MichelsonPair(MichelsonContract(contract), amount)) => {
+            let public_key_hash = PublicKeyHash::from_b58(&contract.to_b58check()).ok()?;
+            let amount = u64::try_from(&amount.0 .0).ok()?;
+            Some(Input::Deposit {
+                public_key_hash,
+                amount,
+            })
+        }
+    }
+}
+
 /// Verify the signature of a message
 ///
-/// Returns the inner message
-pub fn verify_signature(message: Message) -> Result<Inner> {
+/// The message is either signed by the key of the account,
+/// by enough signers of a multisig account,
+/// or by a session key acting for the account of the inner message
+///
+/// Returns the account of the message and the inner message
+pub fn verify_signature<R: Runtime>(
+    host: &mut R,
+    level: u32,
+    message: Message,
+) -> Result<(PublicKeyHash, Inner)> {
+    let account = verify_signer(host, level, &message)?;
+    let Message { inner, .. } = message;
+    Ok((account, inner))
+}
+
+/// Verify the signature of a message without consuming it
+///
+/// Returns the account of the message
+pub fn verify_signer<R: Runtime>(
+    host: &mut R,
+    level: u32,
+    message: &Message,
+) -> Result<PublicKeyHash> {
     let signature = message.signature();
     let pkey = message.public_key();
     let inner = message.inner();
     let hash = inner.hash();
 
     signature.verify(pkey, hash.as_ref())?;
-    let Message { inner, .. } = message;
-    Ok(inner)
+    let signer = PublicKeyHash::from(pkey);
+
+    let account = inner.account.clone().unwrap_or_else(|| signer.clone());
+    match storage::read_signers(host, &account)? {
+        Some(signers) if inner.account.is_none() || signers.contains(&signer) => {
+            verify_threshold(message, &signers)?;
+            return Ok(account);
+        }
+        _ => {}
+    }
+
+    match &inner.account {
+        None => Ok(signer),
+        Some(account) => {
+            let session_key = storage::read_session_key(host, account, &signer)?
+                .ok_or(Error::SessionKeyNotAuthorized)?;
+            if session_key.is_expired(level) {
+                return Err(Error::SessionKeyExpired);
+            }
+            if !session_key.allows(&inner.content) {
+                return Err(Error::OperationNotAllowed);
+            }
+            Ok(account.clone())
+        }
+    }
+}
+
+/// Count the distinct signers of a multisig account that signed the message
+///
+/// Every signature has to be valid, even the ones of keys that are not signers
+fn verify_threshold(message: &Message, signers: &Signers) -> Result<()> {
+    let hash = message.hash();
+    let signatures = std::iter::once((message.public_key(), message.signature())).chain(
+        message
+            .cosignatures()
+            .iter()
+            .map(|cosignature| (&cosignature.pkey, &cosignature.signature)),
+    );
+
+    let mut signed_by: Vec<String> = Vec::new();
+    for (pkey, signature) in signatures {
+        signature.verify(pkey, hash.as_ref())?;
+        let public_key_hash = PublicKeyHash::from(pkey);
+        if signers.contains(&public_key_hash) {
+            signed_by.push(public_key_hash.to_string());
+        }
+    }
+    signed_by.sort_unstable();
+    signed_by.dedup();
+    match signed_by.len() >= signers.threshold as usize {
+        true => Ok(()),
+        false => Err(Error::NotEnoughSignatures),
+    }
 }
 
 /// Verify the nonce of the inner message
 ///
-/// If the nonce is correct the content of the inner is returned
-pub fn verify_nonce(inner: Inner, nonce: &Nonce) -> Result<Content> {
-    let next_nonce = nonce.next();
-    let inner_nonce = inner.nonce();
-    if &next_nonce == inner_nonce {
-        let Inner { content, .. } = inner;
-        Ok(content)
-    } else {
-        Err(Error::InvalidNonce)
+/// Without window, the nonce has to be the next nonce of the account
+/// With a window, any unused nonce in [nonce + 1, nonce + window] is accepted
+/// and the nonces used ahead are tracked in a bitmap
+///
+/// If the nonce is correct the content of the inner and the updated account are returned
+pub fn verify_nonce<R: Runtime>(
+    host: &mut R,
+    inner: Inner,
+    account: Account,
+) -> Result<(Content, Account)> {
+    let config = read_config(host)?;
+    let used_nonces = storage::read_used_nonces(host, &account.public_key_hash)?;
+    let (nonce, used_nonces) = used_nonces
+        .accept(account.nonce(), inner.nonce(), config.nonce_window)
+        .ok_or(Error::InvalidNonce)?;
+    storage::store_used_nonces(host, &account.public_key_hash, &used_nonces)?;
+
+    let Inner { content, .. } = inner;
+    Ok((content, account.with_nonce(nonce)))
+}
+
+/// Check that the account has not been migrated to a new owner
+///
+/// The nonce of a migrated account has moved, its old messages could be replayed
+pub fn verify_not_migrated<R: Runtime>(
+    host: &mut R,
+    public_key_hash: &PublicKeyHash,
+) -> Result<()> {
+    match storage::read_migrated_to(host, public_key_hash)? {
+        Some(_) => Err(Error::AccountMigrated),
+        None => Ok(()),
+    }
+}
+
+/// Check that a banned account is not posting, editing, liking, voting or retweeting
+pub fn verify_not_banned<R: Runtime>(
+    host: &mut R,
+    account: &Account,
+    content: &Content,
+) -> Result<()> {
+    match content {
+        Content::PostTweet(_)
+        | Content::PostTweetRef(_)
+        | Content::EditTweet(_)
+        | Content::PostPoll(_)
+        | Content::LikeTweet(_)
+        | Content::Vote(_)
+        | Content::Retweet(_)
+        | Content::Quote(_) => match storage::is_banned(host, &account.public_key_hash)? {
+            true => Err(Error::AccountBanned),
+            false => Ok(()),
+        },
+        _ => Ok(()),
+    }
+}
+
+/// Checks if the account is the admin of the kernel
+fn is_admin<R: Runtime>(host: &mut R, account: &Account) -> Result<()> {
+    let admin = storage::read_admin(host)?;
+    match admin {
+        Some(admin) if admin.to_string() == account.public_key_hash.to_string() => Ok(()),
+        _ => Err(Error::NotAdmin),
+    }
+}
+
+/// Hide the content of a tweet
+///
+/// Only the admin can hide a tweet
+pub fn hide_tweet<R: Runtime>(host: &mut R, account: &Account, tweet_id: &u64) -> Result<()> {
+    is_admin(host, account)?;
+    let _ = read_tweet(host, tweet_id)?.ok_or(Error::TweetNotFound)?;
+    storage::hide_tweet_content(host, tweet_id)?;
+    storage::commit_tweet(host, tweet_id)
+}
+
+/// Ban an account from posting and liking
+///
+/// Only the admin can ban an account
+pub fn ban_account<R: Runtime>(
+    host: &mut R,
+    account: &Account,
+    public_key_hash: &PublicKeyHash,
+) -> Result<()> {
+    is_admin(host, account)?;
+    storage::ban_account(host, public_key_hash)
+}
+
+/// Remove the ban of an account
+///
+/// Only the admin can unban an account
+pub fn unban_account<R: Runtime>(
+    host: &mut R,
+    account: &Account,
+    public_key_hash: &PublicKeyHash,
+) -> Result<()> {
+    is_admin(host, account)?;
+    storage::unban_account(host, public_key_hash)
+}
+
+/// Pause the processing of the user messages
+///
+/// Only the admin can pause the kernel
+pub fn pause_kernel<R: Runtime>(host: &mut R, account: &Account, mode: &PauseMode) -> Result<()> {
+    is_admin(host, account)?;
+    storage::store_pause_mode(host, mode)
+}
+
+/// Resume the processing of the user messages
+///
+/// The deferred messages are replayed from the start of the next level
+/// Only the admin can unpause the kernel
+pub fn unpause_kernel<R: Runtime>(host: &mut R, account: &Account) -> Result<()> {
+    is_admin(host, account)?;
+    storage::delete_pause_mode(host)
+}
+
+/// What to do with a message of the inbox
+pub enum Admission {
+    /// Process the message of the account whose signature has been verified
+    Process(PublicKeyHash),
+    Reject,
+    Defer,
+}
+
+/// Decide if a message of the inbox is processed now
+///
+/// The signature is verified first, so only signed messages can be deferred,
+/// and it is not verified again when the message is processed
+/// The messages of the admin are always processed
+/// While deferred messages are waiting, the new messages are queued behind them to keep their order
+pub fn admit_message<R: Runtime>(host: &mut R, level: u32, message: &Message) -> Result<Admission> {
+    let account = verify_signer(host, level, message)?;
+    match storage::read_admin(host)? {
+        Some(admin) if admin.to_string() == account.to_string() => {
+            return Ok(Admission::Process(account))
+        }
+        _ => {}
+    }
+    match storage::read_pause_mode(host)? {
+        Some(PauseMode::Reject) => Ok(Admission::Reject),
+        Some(PauseMode::Defer) => Ok(Admission::Defer),
+        None if storage::is_deferred_queue_empty(host)? => Ok(Admission::Process(account)),
+        None => Ok(Admission::Defer),
+    }
+}
+
+/// Copy a message in the deferred queue, it will be replayed after the pause
+///
+/// The queue holds at most MAX_DEFERRED_MESSAGES messages
+pub fn defer_message<R: Runtime>(host: &mut R, message: &Message) -> Result<()> {
+    if storage::read_deferred_queue_len(host)? >= MAX_DEFERRED_MESSAGES {
+        return Err(Error::DeferredQueueFull);
+    }
+    storage::push_deferred_message(host, message)
+}
+
+/// Returns the next deferred message to replay
+///
+/// Nothing is replayed while the kernel is paused
+pub fn next_deferred_message<R: Runtime>(host: &mut R) -> Result<Option<Message>> {
+    match storage::read_pause_mode(host)? {
+        Some(_) => Ok(None),
+        None => storage::pop_deferred_message(host),
+    }
+}
+
+/// Authorize a session key to sign some operations for the account
+///
+/// Only the key of the account can authorize a session key
+pub fn authorize_session_key<R: Runtime>(
+    host: &mut R,
+    account: &Account,
+    authorize_session_key: AuthorizeSessionKey,
+) -> Result<()> {
+    let AuthorizeSessionKey {
+        key,
+        expiry_level,
+        allowed_ops,
+    } = authorize_session_key;
+    let session_key = SessionKey {
+        expiry_level,
+        allowed_ops,
+    };
+    storage::store_session_key(host, &account.public_key_hash, &key, &session_key)
+}
+
+/// Revoke a session key of the account
+pub fn revoke_session_key<R: Runtime>(
+    host: &mut R,
+    account: &Account,
+    key: &PublicKeyHash,
+) -> Result<()> {
+    match storage::read_session_key(host, &account.public_key_hash, key)? {
+        None => Err(Error::SessionKeyNotAuthorized),
+        Some(_) => storage::revoke_session_key(host, &account.public_key_hash, key),
+    }
+}
+
+/// Move the account to a new owner
+///
+/// The tweets, the likes, the nonce and the balance are moved to the new owner,
+/// which should not have any state yet
+/// The leaves of its written and owned tweets are updated in the merkle tree
+/// A banned account cannot escape its ban by migrating
+pub fn migrate_account<R: Runtime>(
+    host: &mut R,
+    account: &Account,
+    migrate_account: &MigrateAccount,
+) -> Result<()> {
+    let MigrateAccount { new_owner } = migrate_account;
+    if storage::account_exists(host, new_owner)? {
+        return Err(Error::AccountAlreadyExists);
+    }
+    if storage::is_banned(host, &account.public_key_hash)? || storage::is_banned(host, new_owner)? {
+        return Err(Error::AccountBanned);
+    }
+    let written_tweets = storage::read_written_tweets(host, &account.public_key_hash)?;
+    let owned_tweets = storage::read_owned_tweets(host, &account.public_key_hash)?;
+    storage::migrate_account(
+        host,
+        &account.public_key_hash,
+        new_owner,
+        &written_tweets,
+        &owned_tweets,
+    )?;
+    for tweet_id in written_tweets.iter().chain(&owned_tweets) {
+        storage::commit_tweet(host, tweet_id)?;
+    }
+    Ok(())
+}
+
+/// Set the M-of-N signers controlling the account
+///
+/// Once set, the messages of the account need the signatures of threshold signers
+pub fn set_signers<R: Runtime>(
+    host: &mut R,
+    account: &Account,
+    set_signers: SetSigners,
+) -> Result<()> {
+    let SetSigners { threshold, signers } = set_signers;
+    let signers = Signers { threshold, signers };
+    if !signers.is_valid(MAX_SIGNERS) {
+        return Err(Error::InvalidSigners);
+    }
+    storage::store_signers(host, &account.public_key_hash, &signers)
+}
+
+/// Check that the event log of the level can record the operation
+pub fn verify_event_log<R: Runtime>(host: &mut R, level: u32) -> Result<()> {
+    let count = storage::read_events_count(host, &level)?;
+    match count < MAX_EVENTS_PER_LEVEL {
+        true => Ok(()),
+        false => Err(Error::EventLogFull),
+    }
+}
+
+/// Count the posts and the likes of the account
+///
+/// Returns the quota to store once the operation has succeeded,
+/// None if the operation is not limited or the rate limits are disabled
+/// Returns an error if the account has reached its quota for the current window of levels
+pub fn check_rate_limit<R: Runtime>(
+    host: &mut R,
+    level: u32,
+    account: &Account,
+    content: &Content,
+) -> Result<Option<Quota>> {
+    let config = read_config(host)?;
+    if config.rate_limit.window == 0 {
+        return Ok(None);
+    }
+    let quota = read_quota(host, &account.public_key_hash)?.at_level(&config.rate_limit, level);
+    match content {
+        Content::PostTweet(_)
+        | Content::PostTweetRef(_)
+        | Content::PostPoll(_)
+        | Content::Retweet(_)
+        | Content::Quote(_) => quota.post(&config.rate_limit).map(Some),
+        Content::LikeTweet(_) | Content::Vote(_) => quota.like(&config.rate_limit).map(Some),
+        _ => Ok(None),
+    }
+}
+
+/// Check that the account can pay the posting fee
+///
+/// Returns the balance to store once the operation has succeeded, None if the operation is free
+pub fn check_posting_fee<R: Runtime>(
+    host: &mut R,
+    account: &Account,
+    content: &Content,
+) -> Result<Option<u64>> {
+    let config = read_config(host)?;
+    match content {
+        Content::PostTweet(_)
+        | Content::PostTweetRef(_)
+        | Content::PostPoll(_)
+        | Content::Quote(_)
+            if config.posting_fee > 0 =>
+        {
+            let balance = read_balance(host, &account.public_key_hash)?;
+            let balance = balance
+                .checked_sub(config.posting_fee)
+                .ok_or(Error::InsufficientBalance)?;
+            Ok(Some(balance))
+        }
+        _ => Ok(None),
     }
 }
 
+/// Credit the balance of an account with a deposit from the L1
+///
+/// A deposit to a migrated account is credited to its new owner
+pub fn deposit<R: Runtime>(
+    host: &mut R,
+    public_key_hash: &PublicKeyHash,
+    amount: u64,
+) -> Result<()> {
+    let public_key_hash = storage::forwarded_account(host, public_key_hash)?;
+    let balance = read_balance(host, &public_key_hash)?;
+    let balance = balance.saturating_add(amount);
+    store_balance(host, &public_key_hash, &balance)
+}
+
 /// Create a new tweet from the PostTweet request
 /// Save the tweet to the durable state
 /// And add a tweet entry to the user account
@@ -83,16 +560,208 @@ pub fn create_tweet<R: Runtime>(
     host: &mut R,
     account: &Account,
     post_tweet: PostTweet,
-) -> Result<()> {
+) -> Result<u64> {
     let id = increment_tweet_counter(host)?;
     let tweet = Tweet::from(post_tweet);
     let _ = store_tweet(host, &id, &tweet)?;
     add_owned_tweet_to_account(host, &account.public_key_hash, &id)?;
     add_written_tweet_to_account(host, &account.public_key_hash, &id)?;
+    storage::commit_tweet(host, &id)?;
+    Ok(id)
+}
+
+/// Create a tweet whose body is revealed from the preimages of the root hash
+///
+/// The content of the tweet is the root hash, the body is stored next to it
+pub fn create_tweet_ref<R: Runtime>(
+    host: &mut R,
+    account: &Account,
+    post_tweet_ref: PostTweetRef,
+) -> Result<u64> {
+    let PostTweetRef { author, root_hash } = post_tweet_ref;
+    let hash: [u8; PREIMAGE_HASH_SIZE] = hex::decode(&root_hash)
+        .ok()
+        .and_then(|hash| hash.try_into().ok())
+        .ok_or(Error::InvalidRootHash)?;
+    let body = reveal_tweet_body(host, &hash)?;
+
+    let id = increment_tweet_counter(host)?;
+    let tweet = Tweet::from(PostTweet {
+        author,
+        content: root_hash,
+    });
+    let _ = store_tweet(host, &id, &tweet)?;
+    store_tweet_body(host, &id, &body)?;
+    add_owned_tweet_to_account(host, &account.public_key_hash, &id)?;
+    add_written_tweet_to_account(host, &account.public_key_hash, &id)?;
+    storage::commit_tweet(host, &id)?;
+    Ok(id)
+}
+
+/// Fetch the content pages of the root hash, with the same machinery as the installer kernel
+///
+/// The body cannot be bigger than MAX_TWEET_BODY_SIZE
+fn reveal_tweet_body<R: Runtime>(
+    host: &mut R,
+    root_hash: &[u8; PREIMAGE_HASH_SIZE],
+) -> Result<Vec<u8>> {
+    let mut buffer = [0; MAX_PAGE_SIZE * MAX_DAC_LEVELS];
+    let mut body = Vec::new();
+    let mut too_large = false;
+
+    let revealed = reveal_loop(
+        host,
+        0,
+        root_hash,
+        buffer.as_mut_slice(),
+        MAX_DAC_LEVELS,
+        &mut |_, page: V0SliceContentPage| {
+            let page = page.as_ref();
+            if body.len() + page.len() > MAX_TWEET_BODY_SIZE {
+                too_large = true;
+                return Err("The body of the tweet is too large");
+            }
+            body.extend_from_slice(page);
+            Ok(())
+        },
+    );
+
+    match (revealed, too_large) {
+        (Ok(()), _) => Ok(body),
+        (Err(_), true) => Err(Error::TweetBodyTooLarge),
+        (Err(_), false) => Err(Error::PreimageNotRevealed),
+    }
+}
+
+/// Edit the content of a tweet
+///
+/// Only the author can edit the tweet, while they still own it and it is not collected
+/// The previous versions are kept in the history of the tweet
+pub fn edit_tweet<R: Runtime>(
+    host: &mut R,
+    level: u32,
+    account: &Account,
+    edit_tweet: &EditTweet,
+) -> Result<()> {
+    let EditTweet { tweet_id, content } = edit_tweet;
+    let tweet = read_tweet(host, tweet_id)?.ok_or(Error::TweetNotFound)?;
+    if tweet.author.to_string() != account.public_key_hash.to_string() {
+        return Err(Error::NotAuthor);
+    }
+    is_owner(host, &account.public_key_hash, tweet_id)?;
+    match read_collect_state(host, tweet_id)? {
+        CollectState::NotCollected => {}
+        _ => return Err(Error::TweetAlreadyCollected),
+    }
+    // The voters chose an option knowing the content of the poll
+    if storage::is_hidden(host, tweet_id)?
+        || storage::has_tweet_body(host, tweet_id)?
+        || storage::is_poll(host, tweet_id)?
+    {
+        return Err(Error::TweetNotEditable);
+    }
+    storage::edit_tweet_content(host, tweet_id, content, &level)?;
+    storage::commit_tweet(host, tweet_id)
+}
+
+/// Create a tweet with a poll
+///
+/// The author of the poll is the account
+pub fn create_poll<R: Runtime>(
+    host: &mut R,
+    level: u32,
+    account: &Account,
+    post_poll: PostPoll,
+) -> Result<u64> {
+    let PostPoll {
+        content,
+        options,
+        closes_at_level,
+    } = post_poll;
+    let poll = Poll {
+        options,
+        closes_at_level,
+    };
+    if !poll.is_valid(level, MAX_POLL_OPTIONS) {
+        return Err(Error::InvalidPoll);
+    }
+
+    let id = increment_tweet_counter(host)?;
+    let tweet = Tweet::from(PostTweet {
+        author: account.public_key_hash.clone(),
+        content,
+    });
+    let _ = store_tweet(host, &id, &tweet)?;
+    storage::store_poll(host, &id, &poll)?;
+    add_owned_tweet_to_account(host, &account.public_key_hash, &id)?;
+    add_written_tweet_to_account(host, &account.public_key_hash, &id)?;
+    storage::commit_tweet(host, &id)?;
+    Ok(id)
+}
+
+/// Vote for an option of a poll
+///
+/// Each account can vote once, until the closing level of the poll
+pub fn vote<R: Runtime>(host: &mut R, level: u32, account: &Account, vote: &Vote) -> Result<()> {
+    let Vote { tweet_id, option } = vote;
+    let poll = storage::read_poll(host, tweet_id)?.ok_or(Error::NotAPoll)?;
+    if !poll.is_open(level) {
+        return Err(Error::PollClosed);
+    }
+    if *option as usize >= poll.options.len() {
+        return Err(Error::InvalidPollOption);
+    }
+    if storage::has_voted(host, &account.public_key_hash, tweet_id)? {
+        return Err(Error::AlreadyVoted);
+    }
+    storage::increment_poll_tally(host, tweet_id, option)?;
+    storage::set_vote_flag(host, &account.public_key_hash, tweet_id)
+}
+
+/// Freeze the results of the polls closed at the previous level
+///
+/// A poll closing at a level accepts the votes of that whole level,
+/// its results are frozen at the start of the next one
+pub fn close_polls<R: Runtime>(host: &mut R, level: u32) -> Result<()> {
+    let closed_at_level = match level.checked_sub(1) {
+        None => return Ok(()),
+        Some(level) => level,
+    };
+    for tweet_id in storage::read_closing_polls(host, &closed_at_level)? {
+        storage::freeze_poll_results(host, &tweet_id)?;
+        storage::append_event(host, &level, &Event::PollClosed { tweet_id })?;
+    }
     Ok(())
 }
 
-pub fn like_tweet<R: Runtime>(host: &mut R, account: &Account, tweet_id: &u64) -> Result<()> {
+/// Remove the likes of the level leaving the trending window
+///
+/// The likes received at a level count during TRENDING_WINDOW levels
+pub fn expire_trending<R: Runtime>(host: &mut R, level: u32) -> Result<()> {
+    let expired_level = match level.checked_sub(TRENDING_WINDOW) {
+        None => return Ok(()),
+        Some(level) => level,
+    };
+    let expired = storage::expire_trending_bucket(host, &expired_level)?;
+    if expired.is_empty() {
+        return Ok(());
+    }
+    let mut top = storage::read_trending_candidates(host)?;
+    for (tweet_id, likes) in expired {
+        top.update(tweet_id, likes, TRENDING_CANDIDATES);
+    }
+    storage::store_top_tweets(host, &top)
+}
+
+/// Like a tweet
+///
+/// The like is also counted in the trending tweets of the level
+pub fn like_tweet<R: Runtime>(
+    host: &mut R,
+    level: u32,
+    account: &Account,
+    tweet_id: &u64,
+) -> Result<()> {
     let already_liked = is_liked(host, &account.public_key_hash, tweet_id)?;
     match already_liked {
         true => Err(Error::TweetAlreadyLiked),
@@ -104,31 +773,81 @@ pub fn like_tweet<R: Runtime>(host: &mut R, account: &Account, tweet_id: &u64) -
                     let tweet = tweet.like();
                     store_tweet(host, tweet_id, &tweet)?;
                     set_like_flag(host, &account.public_key_hash, tweet_id)?;
-                    Ok(())
+                    storage::commit_tweet(host, tweet_id)?;
+                    let likes = storage::add_trending_like(host, &level, tweet_id)?;
+                    let mut top = storage::read_trending_candidates(host)?;
+                    top.update(*tweet_id, likes, TRENDING_CANDIDATES);
+                    storage::store_top_tweets(host, &top)
                 }
             }
         }
     }
 }
 
+/// Retweet a tweet
+///
+/// The tweet is added to the written tweets of the account, its content is not copied
+pub fn retweet<R: Runtime>(host: &mut R, account: &Account, tweet_id: &u64) -> Result<()> {
+    if storage::is_retweeted(host, &account.public_key_hash, tweet_id)? {
+        return Err(Error::TweetAlreadyRetweeted);
+    }
+    let tweet = read_tweet(host, tweet_id)?.ok_or(Error::TweetNotFound)?;
+    let _ = store_tweet(host, tweet_id, &tweet.retweet())?;
+    storage::commit_tweet(host, tweet_id)?;
+    storage::set_retweet_flag(host, &account.public_key_hash, tweet_id)?;
+    add_written_tweet_to_account(host, &account.public_key_hash, tweet_id)
+}
+
+/// Create a tweet quoting another one
+///
+/// The quote counts as a retweet of the quoted tweet
+pub fn quote_tweet<R: Runtime>(host: &mut R, account: &Account, quote: Quote) -> Result<u64> {
+    let Quote { tweet_id, content } = quote;
+    let quoted = read_tweet(host, &tweet_id)?.ok_or(Error::TweetNotFound)?;
+    let _ = store_tweet(host, &tweet_id, &quoted.retweet())?;
+    storage::commit_tweet(host, &tweet_id)?;
+
+    let id = increment_tweet_counter(host)?;
+    let tweet = Tweet {
+        quoted: Some(tweet_id),
+        ..Tweet::from(PostTweet {
+            author: account.public_key_hash.clone(),
+            content,
+        })
+    };
+    let _ = store_tweet(host, &id, &tweet)?;
+    add_owned_tweet_to_account(host, &account.public_key_hash, &id)?;
+    add_written_tweet_to_account(host, &account.public_key_hash, &id)?;
+    storage::commit_tweet(host, &id)?;
+    Ok(id)
+}
+
 /// Transfer a tweet from an account to another one
 ///
 /// Checks if the account parameter is owner of the tweet
+/// A tweet sent to a migrated account is received by its new owner
+///
+/// Returns the account receiving the tweet
 pub fn transfer_tweet<R: Runtime>(
     host: &mut R,
     account: &Account,
     transfer: &Transfer,
-) -> Result<()> {
+) -> Result<PublicKeyHash> {
     let Transfer {
         tweet_id,
         destination,
     } = transfer;
     is_owner(host, &account.public_key_hash, tweet_id)?;
-    storage::transfer(host, &account.public_key_hash, tweet_id, destination)?;
-    Ok(())
+    let destination = storage::forwarded_account(host, destination)?;
+    storage::transfer(host, &account.public_key_hash, tweet_id, &destination)?;
+    storage::commit_tweet(host, tweet_id)?;
+    Ok(destination)
 }
 
 /// Withdraw the tweet to layer 1
+///
+/// If a previous collect has never been minted before its outbox message expired,
+/// the tweet can be collected again
 pub fn withdraw_tweet<R: Runtime>(
     host: &mut R,
     level: u32,
@@ -136,7 +855,13 @@ pub fn withdraw_tweet<R: Runtime>(
     tweet_id: &u64,
 ) -> Result<()> {
     is_owner(host, &account.public_key_hash, tweet_id)?;
-    is_not_collected(host, tweet_id)?;
+    match read_collect_state(host, tweet_id)? {
+        CollectState::NotCollected => {}
+        state if state.is_expired(level) => {
+            reset_collect(host, &account.public_key_hash, tweet_id)?;
+        }
+        _ => return Err(Error::TweetAlreadyCollected),
+    }
 
     let tweet = read_tweet(host, tweet_id)
         .map_err(Error::from)?
@@ -165,9 +890,6 @@ pub fn withdraw_tweet<R: Runtime>(
     };
     let content = MichelsonString(tweet.content);
 
-    let destination = Contract::from_b58check(L1_TOKEN_CONTRACT_ADDRESS)
-        .map_err(|_| Error::FromBase58CheckError)?;
-
     // (pair %mint
     //     (pair (nat %id) (address %owner))
     //     (pair %token (pair (address %author) (string %content)) (nat %likes)))
@@ -177,24 +899,145 @@ pub fn withdraw_tweet<R: Runtime>(
         MichelsonPair(MichelsonPair(author, content), likes),
     );
 
+    let output = outbox_transaction(
+        michelson,
+        L1_TOKEN_CONTRACT_ADDRESS,
+        L1_TOKEN_CONTRACT_ENTRYPOINT,
+    )?;
+
+    // Freeze the tweets
+    set_collected_block(host, tweet_id, &level)?;
+    // Indicates that the user is collecting the tweet
+    add_collecting_tweet_to_account(host, &account.public_key_hash, tweet_id)?;
+
+    let output = PendingOutput::collect(output, account.public_key_hash.clone(), *tweet_id);
+    write_output(host, level, output)
+}
+
+/// Serialize an outbox message calling the entrypoint of a L1 contract
+fn outbox_transaction<M: Michelson>(
+    parameters: M,
+    contract: &str,
+    entrypoint: &str,
+) -> Result<Vec<u8>> {
+    let destination = Contract::from_b58check(contract).map_err(|_| Error::FromBase58CheckError)?;
     let transaction = OutboxMessageTransaction {
-        parameters: michelson,
+        parameters,
         destination,
-        entrypoint: Entrypoint::try_from(L1_TOKEN_CONTRACT_ENTRYPOINT.to_string())
-            .map_err(Error::from)?,
+        entrypoint: Entrypoint::try_from(entrypoint.to_string()).map_err(Error::from)?,
     };
 
     let batch = OutboxMessageTransactionBatch::from(vec![transaction]);
     let message = OutboxMessage::AtomicTransactionBatch(batch);
 
     let mut output = Vec::default();
-    message.bin_write(&mut output).unwrap();
+    message.bin_write(&mut output).map_err(Error::from)?;
+    Ok(output)
+}
 
-    host.write_output(&output).unwrap();
+/// Commit the state of tzwitter to the L1 registry, every commitment period
+///
+/// The commitments are disabled until both the period and the registry are configured
+/// The commitment is taken at the start of the level, before its messages
+/// It carries the level, the tweet counter and the merkle root of the tweets
+pub fn commit_state<R: Runtime>(host: &mut R, level: u32) -> Result<()> {
+    let config = storage::read_config(host)?;
+    let registry = match (config.commitment_period, config.registry) {
+        (0, _) | (_, None) => return Ok(()),
+        (period, Some(_)) if level % period != 0 => return Ok(()),
+        (_, Some(registry)) => registry,
+    };
+    let counter = storage::read_tweet_counter(host)?;
+    let root = storage::read_merkle_root(host)?;
 
-    // Freeze the tweets
-    set_collected_block(host, tweet_id, &level)?;
-    // Indicates that the user is collecting the tweet
-    add_collecting_tweet_to_account(host, &account.public_key_hash, tweet_id)?;
+    let committed_level = {
+        let level = level.to_bigint().ok_or(Error::BigIntError)?;
+        MichelsonInt(Zarith(level))
+    };
+    let counter = {
+        let counter = counter.to_bigint().ok_or(Error::BigIntError)?;
+        MichelsonInt(Zarith(counter))
+    };
+    let root = MichelsonBytes(root.to_vec());
+
+    // The registry takes a record, laid out by LIGO with its fields sorted by name
+    // (pair %commit (pair (nat %counter) (nat %level)) (bytes %root))
+    let michelson = MichelsonPair(MichelsonPair(counter, committed_level), root);
+
+    let output = outbox_transaction(michelson, &registry, L1_REGISTRY_CONTRACT_ENTRYPOINT)?;
+    write_output(host, level, PendingOutput::new(output))
+}
+
+/// Give back a tweet whose collect has expired without being minted
+///
+/// The tweet is not frozen anymore
+pub fn reclaim_tweet<R: Runtime>(
+    host: &mut R,
+    level: u32,
+    account: &Account,
+    tweet_id: &u64,
+) -> Result<()> {
+    is_owner(host, &account.public_key_hash, tweet_id)?;
+    match read_collect_state(host, tweet_id)? {
+        CollectState::NotCollected => Err(Error::TweetNotCollected),
+        state if state.is_expired(level) => reset_collect(host, &account.public_key_hash, tweet_id),
+        _ => Err(Error::CollectNotExpired),
+    }
+}
+
+/// Finalize the collect of a tweet once the L1 contract has minted it
+pub fn finalize_collect<R: Runtime>(host: &mut R, tweet_id: &u64) -> Result<()> {
+    match read_collect_state(host, tweet_id)? {
+        CollectState::Written { .. } => set_collect_finalized(host, tweet_id),
+        CollectState::Finalized => Ok(()),
+        _ => Err(Error::TweetNotCollected),
+    }
+}
+
+/// Write a message to the outbox
+///
+/// If the outbox of the current level is full, or if older messages are still waiting,
+/// the message is queued in the durable storage, it will be written at a later level
+fn write_output<R: Runtime>(host: &mut R, level: u32, output: PendingOutput) -> Result<()> {
+    if is_outbox_queue_empty(host)? {
+        match host.write_output(&output.payload) {
+            Ok(()) => return output_written(host, level, output),
+            Err(RuntimeError::HostErr(HostError::FullOutbox)) => {}
+            Err(err) => return Err(Error::from(err)),
+        }
+    }
+    push_outbox_queue(host, &output)
+}
+
+/// Write the queued messages to the outbox
+///
+/// Stops when the queue is empty or when the outbox of the current level is full
+pub fn flush_outbox_queue<R: Runtime>(host: &mut R, level: u32) -> Result<()> {
+    while let Some(output) = peek_outbox_queue(host)? {
+        match host.write_output(&output.payload) {
+            Ok(()) => {
+                pop_outbox_queue(host)?;
+                output_written(host, level, output)?;
+            }
+            Err(RuntimeError::HostErr(HostError::FullOutbox)) => return Ok(()),
+            Err(err) => return Err(Error::from(err)),
+        }
+    }
     Ok(())
 }
+
+/// Update the state once a message has been written to the outbox
+///
+/// The outbox level and index of a collect are recorded
+fn output_written<R: Runtime>(host: &mut R, level: u32, output: PendingOutput) -> Result<()> {
+    let index = increment_outbox_counter(host, &level)?;
+    match output.collect {
+        Some((owner, tweet_id)) => {
+            // The owner may have migrated while the output was queued
+            let owner = storage::forwarded_account(host, &owner)?;
+            set_collected_flag(host, &owner, &tweet_id)?;
+            set_collect_outbox(host, &tweet_id, &level, &index)
+        }
+        None => Ok(()),
+    }
+}
@@ -1,14 +1,65 @@
-use tezos_smart_rollup::{prelude::*, storage::path::*};
+use tezos_smart_rollup::{core_unsafe::MAX_FILE_CHUNK_SIZE, prelude::*, storage::path::*};
 
+use crate::constants::{
+    DEFAULT_COMMITMENT_PERIOD, DEFAULT_MAX_LIKES, DEFAULT_MAX_POSTS, DEFAULT_NONCE_WINDOW,
+    DEFAULT_POSTING_FEE, DEFAULT_RATE_LIMIT_WINDOW, MAX_NONCE_WINDOW, MERKLE_TREE_DEPTH,
+    REDACTED_CONTENT,
+};
+use crate::core::collect::CollectState;
+use crate::core::config::{Config, RateLimit};
+use crate::core::event::Event;
+use crate::core::hash::Blake2b;
+use crate::core::merkle::{self, Node};
+use crate::core::message::Message;
+use crate::core::metrics::LevelMetrics;
+use crate::core::multisig::Signers;
+use crate::core::nonce::UsedNonces;
+use crate::core::outbox::PendingOutput;
+use crate::core::pause::PauseMode;
+use crate::core::poll::Poll;
 use crate::core::public_key_hash::PublicKeyHash;
+use crate::core::quota::Quota;
 use crate::core::receipt::Receipt;
+use crate::core::session_key::{SessionKey, SessionOperation};
+use crate::core::trending::TopTweets;
 use crate::core::tweet::Tweet;
-use crate::core::{account::Account, error::*, nonce::Nonce};
+use crate::core::{account::Account, error::*};
+use crate::metrics;
+use crate::query::{self, HostReader};
 
 const ACCOUNTS: RefPath = RefPath::assert_from(b"/accounts");
 pub const TWEETS: RefPath = RefPath::assert_from(b"/tweets");
-const TWEET_COUNTER: RefPath = RefPath::assert_from(b"/constants/tweet-counter"); // The name constants is not appropriate
+pub(crate) const TWEET_COUNTER: RefPath = RefPath::assert_from(b"/constants/tweet-counter"); // The name constants is not appropriate
 const RECEIPTS: RefPath = RefPath::assert_from(b"/receipts");
+const OUTBOX_QUEUE: RefPath = RefPath::assert_from(b"/outbox/queue");
+const OUTBOX_QUEUE_HEAD: RefPath = RefPath::assert_from(b"/outbox/head");
+const OUTBOX_QUEUE_TAIL: RefPath = RefPath::assert_from(b"/outbox/tail");
+const OUTBOX_LEVEL: RefPath = RefPath::assert_from(b"/outbox/level");
+const OUTBOX_COUNTER: RefPath = RefPath::assert_from(b"/outbox/counter");
+const CONFIG_RATE_LIMIT_WINDOW: RefPath = RefPath::assert_from(b"/config/rate_limit/window");
+const CONFIG_MAX_POSTS: RefPath = RefPath::assert_from(b"/config/rate_limit/posts");
+const CONFIG_MAX_LIKES: RefPath = RefPath::assert_from(b"/config/rate_limit/likes");
+const CONFIG_POSTING_FEE: RefPath = RefPath::assert_from(b"/config/posting_fee");
+const CONFIG_NONCE_WINDOW: RefPath = RefPath::assert_from(b"/config/nonce_window");
+const CONFIG_COMMITMENT_PERIOD: RefPath = RefPath::assert_from(b"/config/commitment_period");
+const CONFIG_REGISTRY: RefPath = RefPath::assert_from(b"/config/registry");
+const CONFIG_ADMIN: RefPath = RefPath::assert_from(b"/config/admin");
+const CONFIG_PAUSE: RefPath = RefPath::assert_from(b"/config/pause");
+const DEFERRED_QUEUE: RefPath = RefPath::assert_from(b"/deferred/queue");
+const DEFERRED_QUEUE_HEAD: RefPath = RefPath::assert_from(b"/deferred/head");
+const DEFERRED_QUEUE_TAIL: RefPath = RefPath::assert_from(b"/deferred/tail");
+const EVENTS: RefPath = RefPath::assert_from(b"/events");
+const METRICS: RefPath = RefPath::assert_from(b"/metrics");
+const SCHEMA_VERSION: RefPath = RefPath::assert_from(b"/schema/version");
+const SCHEMA_CURSOR: RefPath = RefPath::assert_from(b"/schema/cursor");
+const BANNED_ACCOUNTS: RefPath = RefPath::assert_from(b"/moderation/banned");
+const POLLS_CLOSING: RefPath = RefPath::assert_from(b"/polls/closing");
+const POLLS_RESULTS: RefPath = RefPath::assert_from(b"/polls/results");
+const TRENDING_BUCKETS: RefPath = RefPath::assert_from(b"/trending/buckets");
+const TRENDING_LIKES: RefPath = RefPath::assert_from(b"/trending/likes");
+pub(crate) const TRENDING_TOP: RefPath = RefPath::assert_from(b"/trending/top");
+const MERKLE_NODES: RefPath = RefPath::assert_from(b"/merkle/tweets");
+pub(crate) const MERKLE_ROOT: RefPath = RefPath::assert_from(b"/merkle/root");
 
 /// Compute the paths for the different fields of a tweet
 ///
@@ -23,24 +74,96 @@ fn tweet_field_path(tweet_id: &u64, field_path: &str) -> Result<OwnedPath> {
     concat(&tweet_path, &field_path).map_err(Error::from)
 }
 
+/// Compute the path of the tweet owner
+/// /tweets/{id}/owner
+pub(crate) fn tweet_owner_path(tweet_id: &u64) -> Result<OwnedPath> {
+    tweet_field_path(tweet_id, "/owner")
+}
+
 /// Compute the path of the tweet author
 /// /tweets/{id}/author
-fn tweet_author_path(tweet_id: &u64) -> Result<OwnedPath> {
+pub(crate) fn tweet_author_path(tweet_id: &u64) -> Result<OwnedPath> {
     tweet_field_path(tweet_id, "/author")
 }
 
 /// Compute the path of the tweet content
 /// /tweets/{hash}/content
-fn tweet_content_path(tweet_id: &u64) -> Result<OwnedPath> {
+pub(crate) fn tweet_content_path(tweet_id: &u64) -> Result<OwnedPath> {
     tweet_field_path(tweet_id, "/content")
 }
 
 /// Compute the path of the tweet content
 /// /tweets/{hash}/content
-fn tweet_likes_path(tweet_id: &u64) -> Result<OwnedPath> {
+pub(crate) fn tweet_likes_path(tweet_id: &u64) -> Result<OwnedPath> {
     tweet_field_path(tweet_id, "/likes")
 }
 
+/// Path of the number of retweets and quotes of a tweet
+/// /tweets/{id}/retweets
+pub(crate) fn tweet_retweets_path(tweet_id: &u64) -> Result<OwnedPath> {
+    tweet_field_path(tweet_id, "/retweets")
+}
+
+/// Path of the id of the tweet quoted by a tweet
+/// /tweets/{id}/quoted
+pub(crate) fn tweet_quoted_path(tweet_id: &u64) -> Result<OwnedPath> {
+    tweet_field_path(tweet_id, "/quoted")
+}
+
+/// Path of the body of a tweet revealed from the preimages
+/// /tweets/{id}/body
+fn tweet_body_path(tweet_id: &u64) -> Result<OwnedPath> {
+    tweet_field_path(tweet_id, "/body")
+}
+
+/// Path of the number of previous versions of an edited tweet
+/// /tweets/{id}/history/count
+pub(crate) fn tweet_history_count_path(tweet_id: &u64) -> Result<OwnedPath> {
+    tweet_field_path(tweet_id, "/history/count")
+}
+
+/// Path of the content of a previous version of a tweet
+/// /tweets/{id}/history/{n}/content
+pub(crate) fn tweet_version_content_path(tweet_id: &u64, version: &u64) -> Result<OwnedPath> {
+    tweet_field_path(tweet_id, &format!("/history/{}/content", version))
+}
+
+/// Path of the level of the edit that replaced a version of a tweet
+/// /tweets/{id}/history/{n}/level
+pub(crate) fn tweet_version_level_path(tweet_id: &u64, version: &u64) -> Result<OwnedPath> {
+    tweet_field_path(tweet_id, &format!("/history/{}/level", version))
+}
+
+/// Path of the flag indicating that the tweet has been hidden by the moderation
+/// /tweets/{id}/hidden
+fn tweet_hidden_path(tweet_id: &u64) -> Result<OwnedPath> {
+    tweet_field_path(tweet_id, "/hidden")
+}
+
+/// Path of the last level at which the accounts can vote on a poll
+/// /tweets/{id}/poll/closes_at_level
+pub(crate) fn tweet_poll_closes_at_level_path(tweet_id: &u64) -> Result<OwnedPath> {
+    tweet_field_path(tweet_id, "/poll/closes_at_level")
+}
+
+/// Path of the number of options of a poll
+/// /tweets/{id}/poll/options/count
+pub(crate) fn tweet_poll_options_count_path(tweet_id: &u64) -> Result<OwnedPath> {
+    tweet_field_path(tweet_id, "/poll/options/count")
+}
+
+/// Path of the label of an option of a poll
+/// /tweets/{id}/poll/options/{n}
+pub(crate) fn tweet_poll_option_path(tweet_id: &u64, option: &u32) -> Result<OwnedPath> {
+    tweet_field_path(tweet_id, &format!("/poll/options/{}", option))
+}
+
+/// Path of the number of votes of an option of a poll
+/// /tweets/{id}/poll/tallies/{n}
+pub(crate) fn tweet_poll_tally_path(tweet_id: &u64, option: &u32) -> Result<OwnedPath> {
+    tweet_field_path(tweet_id, &format!("/poll/tallies/{}", option))
+}
+
 /// Path to know if user has collected the tweet
 /// The stored value is the block level
 /// /tweets/{id}/collected_hash
@@ -48,55 +171,198 @@ fn tweet_collected_block_path(tweet_id: &u64) -> Result<OwnedPath> {
     tweet_field_path(tweet_id, "/collected_level")
 }
 
+/// Path of the collect information of a tweet
+/// /tweets/{id}/collect
+fn tweet_collect_path(tweet_id: &u64) -> Result<OwnedPath> {
+    tweet_field_path(tweet_id, "/collect")
+}
+
+/// Path of the outbox level where the collect message has been written
+/// /tweets/{id}/collect/outbox_level
+fn tweet_collect_outbox_level_path(tweet_id: &u64) -> Result<OwnedPath> {
+    tweet_field_path(tweet_id, "/collect/outbox_level")
+}
+
+/// Path of the index of the collect message in its outbox level
+/// /tweets/{id}/collect/outbox_index
+fn tweet_collect_outbox_index_path(tweet_id: &u64) -> Result<OwnedPath> {
+    tweet_field_path(tweet_id, "/collect/outbox_index")
+}
+
+/// Path of the flag indicating that the L1 contract has minted the tweet
+/// /tweets/{id}/collect/finalized
+fn tweet_collect_finalized_path(tweet_id: &u64) -> Result<OwnedPath> {
+    tweet_field_path(tweet_id, "/collect/finalized")
+}
+
 /// Compute the paths for the different fields of an account
 ///
 /// The field_path should start with slash
 fn account_field_path(public_key_hash: &PublicKeyHash, field_path: &str) -> Result<OwnedPath> {
-    let public_key_hash: Vec<u8> = format!("/{}", public_key_hash.to_string()).into();
-    let public_key_hash = OwnedPath::try_from(public_key_hash).map_err(Error::from)?;
-    let public_key_hash = concat(&ACCOUNTS, &public_key_hash).map_err(Error::from)?;
+    let public_key_hash = account_path(public_key_hash)?;
 
     let field_path: Vec<u8> = field_path.into();
     let field_path = OwnedPath::try_from(field_path).map_err(Error::from)?;
     concat(&public_key_hash, &field_path).map_err(Error::from)
 }
 
+/// Compute the path /accounts/{tz1...}
+fn account_path(public_key_hash: &PublicKeyHash) -> Result<OwnedPath> {
+    let public_key_hash: Vec<u8> = format!("/{}", public_key_hash.to_string()).into();
+    let public_key_hash = OwnedPath::try_from(public_key_hash).map_err(Error::from)?;
+    concat(&ACCOUNTS, &public_key_hash).map_err(Error::from)
+}
+
+/// Compute the path of the forwarding record of a migrated account
+/// /accounts/{tz1...}/migrated_to
+pub(crate) fn migrated_to_path(public_key_hash: &PublicKeyHash) -> Result<OwnedPath> {
+    account_field_path(public_key_hash, "/migrated_to")
+}
+
 /// Compute the path /accounts/{tz1...}/nonce
-fn nonce_path(public_key_hash: &PublicKeyHash) -> Result<OwnedPath> {
+pub(crate) fn nonce_path(public_key_hash: &PublicKeyHash) -> Result<OwnedPath> {
     account_field_path(public_key_hash, "/nonce")
 }
 
+/// Compute the path of a session key of an account
+/// /accounts/{tz1...}/session_keys/{tz1...}
+fn session_key_path(account: &PublicKeyHash, key: &PublicKeyHash) -> Result<OwnedPath> {
+    account_field_path(account, &format!("/session_keys/{}", key.to_string()))
+}
+
+/// Compute the path of the different fields of a session key
+fn session_key_field_path(
+    account: &PublicKeyHash,
+    key: &PublicKeyHash,
+    field_path: &str,
+) -> Result<OwnedPath> {
+    let field_path: Vec<u8> = field_path.into();
+    let field_path = OwnedPath::try_from(field_path).map_err(Error::from)?;
+    concat(&session_key_path(account, key)?, &field_path).map_err(Error::from)
+}
+
+/// Compute the path of the different fields of the signers of a multisig account
+/// /accounts/{tz1...}/signers/{field}
+fn signers_field_path(account: &PublicKeyHash, field: &str) -> Result<OwnedPath> {
+    account_field_path(account, &format!("/signers{}", field))
+}
+
+/// Compute the path of the bitmap of the nonces used ahead of the nonce of the account
+/// /accounts/{tz1...}/used_nonces
+fn used_nonces_path(public_key_hash: &PublicKeyHash) -> Result<OwnedPath> {
+    account_field_path(public_key_hash, "/used_nonces")
+}
+
+/// Compute the path /moderation/banned/{tz1...}
+fn banned_account_path(public_key_hash: &PublicKeyHash) -> Result<OwnedPath> {
+    let path: Vec<u8> = format!("/{}", public_key_hash.to_string()).into();
+    let path = OwnedPath::try_from(path).map_err(Error::from)?;
+    concat(&BANNED_ACCOUNTS, &path).map_err(Error::from)
+}
+
+/// Compute the path /accounts/{tz1...}/balance
+fn balance_path(public_key_hash: &PublicKeyHash) -> Result<OwnedPath> {
+    account_field_path(public_key_hash, "/balance")
+}
+
+/// Compute the path of the different fields of the quota of an account
+/// /accounts/{tz1...}/quota/{field}
+fn quota_field_path(public_key_hash: &PublicKeyHash, field: &str) -> Result<OwnedPath> {
+    account_field_path(public_key_hash, &format!("/quota/{}", field))
+}
+
 /// Compute the path to the liked tweet
 fn account_likes_path(public_key_hash: &PublicKeyHash, tweet_id: &u64) -> Result<OwnedPath> {
     account_field_path(public_key_hash, &format!("/likes/{}", tweet_id))
 }
 
+/// Compute the path to the retweeted tweet
+/// /accounts/{tz1...}/retweets/{tweet_id}
+fn account_retweets_path(public_key_hash: &PublicKeyHash, tweet_id: &u64) -> Result<OwnedPath> {
+    account_field_path(public_key_hash, &format!("/retweets/{}", tweet_id))
+}
+
+/// Compute the path of the flag indicating that the account has voted on a poll
+/// /accounts/{tz1...}/votes/{tweet_id}
+fn account_votes_path(public_key_hash: &PublicKeyHash, tweet_id: &u64) -> Result<OwnedPath> {
+    account_field_path(public_key_hash, &format!("/votes/{}", tweet_id))
+}
+
 /// Compute the path of the being collected tweets
 fn account_collecting_path(public_key_hash: &PublicKeyHash, tweet_id: &u64) -> Result<OwnedPath> {
     account_field_path(public_key_hash, &format!("/collecting/{}", tweet_id))
 }
 
+/// Compute the path of the collected tweets
+///
+/// A tweet is collected once its outbox message has been written
+fn account_collected_path(public_key_hash: &PublicKeyHash, tweet_id: &u64) -> Result<OwnedPath> {
+    account_field_path(public_key_hash, &format!("/collected/{}", tweet_id))
+}
+
 /// Path to keep track of owned tweets
 ///
 /// /account/{tz1...}/tweets/{tweet_id}
 /// If the id is present in the subkey /tweets then the account owns the tweets
 ///
 /// TODO: this structure is not the best one, it does not ensure that a tweet is owned by only one user.
-fn account_owned_tweet_path(public_key_hash: &PublicKeyHash, tweet_id: &u64) -> Result<OwnedPath> {
+pub(crate) fn account_owned_tweet_path(
+    public_key_hash: &PublicKeyHash,
+    tweet_id: &u64,
+) -> Result<OwnedPath> {
     account_field_path(public_key_hash, &format!("/tweets/owned/{}", tweet_id))
 }
 
+/// Path of all the tweets owned by an account
+/// /accounts/{tz1...}/tweets/owned
+pub(crate) fn account_owned_tweets_path(public_key_hash: &PublicKeyHash) -> Result<OwnedPath> {
+    account_field_path(public_key_hash, "/tweets/owned")
+}
+
+/// Path of all the tweets written by an account
+/// /accounts/{tz1...}/tweets/written
+pub(crate) fn account_written_tweets_path(public_key_hash: &PublicKeyHash) -> Result<OwnedPath> {
+    account_field_path(public_key_hash, "/tweets/written")
+}
+
 /// Path to keep track of the tweets written by a user
-fn account_written_tweet_path(
+pub(crate) fn account_written_tweet_path(
     public_key_hash: &PublicKeyHash,
     tweet_id: &u64,
 ) -> Result<OwnedPath> {
     account_field_path(public_key_hash, &format!("/tweets/written/{}", tweet_id))
 }
 
+/// The index of the tweets written by an account
+const WRITTEN_INDEX: &str = "written";
+/// The index of the tweets owned by an account
+const OWNED_INDEX: &str = "owned";
+
+/// Compute the path of an entry of an index of the tweets of an account
+/// /accounts/{tz1...}/tweets/index/{written|owned}/{index}
+fn account_tweet_index_path(
+    public_key_hash: &PublicKeyHash,
+    kind: &str,
+    index: &u64,
+) -> Result<OwnedPath> {
+    account_field_path(
+        public_key_hash,
+        &format!("/tweets/index/{}/{}", kind, index),
+    )
+}
+
+/// Compute the path of the number of entries of an index of the tweets of an account
+/// /accounts/{tz1...}/tweets/index/{written|owned}/count
+fn account_tweet_index_count_path(
+    public_key_hash: &PublicKeyHash,
+    kind: &str,
+) -> Result<OwnedPath> {
+    account_field_path(public_key_hash, &format!("/tweets/index/{}/count", kind))
+}
+
 /// Compute the path of the different field of a receipt
-fn receipt_field_path(receipt: &Receipt, field_path: &str) -> Result<OwnedPath> {
-    let receipt_path = format!("/{}", receipt.hash().to_string());
+fn receipt_field_path(hash: &Blake2b, field_path: &str) -> Result<OwnedPath> {
+    let receipt_path = format!("/{}", hash.to_string());
     let receipt_path = OwnedPath::try_from(receipt_path).map_err(Error::from)?;
     let receipt_path = concat(&RECEIPTS, &receipt_path)?;
 
@@ -106,8 +372,94 @@ fn receipt_field_path(receipt: &Receipt, field_path: &str) -> Result<OwnedPath>
 }
 
 /// Compute the path of the success field of a receipt
-fn receipt_success_path(receipt: &Receipt) -> Result<OwnedPath> {
-    receipt_field_path(receipt, "/success")
+pub(crate) fn receipt_success_path(hash: &Blake2b) -> Result<OwnedPath> {
+    receipt_field_path(hash, "/success")
+}
+
+/// Compute the path of the error field of a receipt
+pub(crate) fn receipt_error_path(hash: &Blake2b) -> Result<OwnedPath> {
+    receipt_field_path(hash, "/error")
+}
+
+/// Compute the path of an event
+/// /events/{level}/{index}
+pub(crate) fn event_path(level: &u32, index: &u64) -> Result<OwnedPath> {
+    let path: Vec<u8> = format!("/{}/{}", level, index).into();
+    let path = OwnedPath::try_from(path).map_err(Error::from)?;
+    concat(&EVENTS, &path).map_err(Error::from)
+}
+
+/// Compute the path of the number of events of a level
+/// /events/{level}/count
+pub(crate) fn events_count_path(level: &u32) -> Result<OwnedPath> {
+    let path: Vec<u8> = format!("/{}/count", level).into();
+    let path = OwnedPath::try_from(path).map_err(Error::from)?;
+    concat(&EVENTS, &path).map_err(Error::from)
+}
+
+/// Compute the path of a poll closing at the given level
+/// /polls/closing/{level}/{index}
+fn closing_poll_path(level: &u32, index: &u64) -> Result<OwnedPath> {
+    let path: Vec<u8> = format!("/{}/{}", level, index).into();
+    let path = OwnedPath::try_from(path).map_err(Error::from)?;
+    concat(&POLLS_CLOSING, &path).map_err(Error::from)
+}
+
+/// Compute the path of the number of polls closing at the given level
+/// /polls/closing/{level}/count
+fn closing_polls_count_path(level: &u32) -> Result<OwnedPath> {
+    let path: Vec<u8> = format!("/{}/count", level).into();
+    let path = OwnedPath::try_from(path).map_err(Error::from)?;
+    concat(&POLLS_CLOSING, &path).map_err(Error::from)
+}
+
+/// Compute the path of the different fields of the results of a closed poll
+/// /polls/results/{tweet_id}/{field}
+pub(crate) fn poll_results_field_path(tweet_id: &u64, field_path: &str) -> Result<OwnedPath> {
+    let path: Vec<u8> = format!("/{}{}", tweet_id, field_path).into();
+    let path = OwnedPath::try_from(path).map_err(Error::from)?;
+    concat(&POLLS_RESULTS, &path).map_err(Error::from)
+}
+
+/// Compute the path of the bucket of the likes received during a level
+/// /trending/buckets/{level}
+fn trending_bucket_path(level: &u32) -> Result<OwnedPath> {
+    let path: Vec<u8> = format!("/{}", level).into();
+    let path = OwnedPath::try_from(path).map_err(Error::from)?;
+    concat(&TRENDING_BUCKETS, &path).map_err(Error::from)
+}
+
+/// Compute the path of the different fields of a bucket of likes
+/// /trending/buckets/{level}/{field}
+fn trending_bucket_field_path(level: &u32, field_path: &str) -> Result<OwnedPath> {
+    let field_path: Vec<u8> = field_path.into();
+    let field_path = OwnedPath::try_from(field_path).map_err(Error::from)?;
+    concat(&trending_bucket_path(level)?, &field_path).map_err(Error::from)
+}
+
+/// Compute the path of the likes received by a tweet during the trending window
+/// /trending/likes/{tweet_id}
+pub(crate) fn trending_likes_path(tweet_id: &u64) -> Result<OwnedPath> {
+    let path: Vec<u8> = format!("/{}", tweet_id).into();
+    let path = OwnedPath::try_from(path).map_err(Error::from)?;
+    concat(&TRENDING_LIKES, &path).map_err(Error::from)
+}
+
+/// Compute the path of a node of the merkle tree of the tweets
+/// /merkle/tweets/{height}/{index}
+pub(crate) fn merkle_node_path(height: &usize, index: &u64) -> Result<OwnedPath> {
+    let path: Vec<u8> = format!("/{}/{}", height, index).into();
+    let path = OwnedPath::try_from(path).map_err(Error::from)?;
+    concat(&MERKLE_NODES, &path).map_err(Error::from)
+}
+
+/// Compute the paths for the different fields of a queued outbox message
+///
+/// The field_path should start with slash
+fn outbox_queue_field_path(index: &u64, field_path: &str) -> Result<OwnedPath> {
+    let entry_path: Vec<u8> = format!("/{}{}", index, field_path).into();
+    let entry_path = OwnedPath::try_from(entry_path).map_err(Error::from)?;
+    concat(&OUTBOX_QUEUE, &entry_path).map_err(Error::from)
 }
 
 ///  Check if a path exists
@@ -138,9 +490,21 @@ fn store_u64<'a, R: Runtime>(host: &mut R, path: &impl Path, u64: &'a u64) -> Re
     let data = u64.to_be_bytes();
     let data = data.as_slice();
 
-    host.store_write(path, data, 0)
-        .map_err(Error::from)
-        .map(|_| u64)
+    write(host, path, data, 0).map(|_| u64)
+}
+
+/// Read an u32 from a given path
+pub fn read_u32<R: Runtime>(host: &mut R, path: &impl Path) -> Result<Option<u32>> {
+    let is_exists = exists(host, path)?;
+    if !is_exists {
+        return Ok(None);
+    }
+
+    let mut buffer = [0_u8; 4];
+    match host.store_read_slice(path, 0, &mut buffer) {
+        Ok(4) => Ok(Some(u32::from_be_bytes(buffer))),
+        _ => Err(Error::StateDeserializarion),
+    }
 }
 
 /// Store an u32 at a given path
@@ -148,41 +512,101 @@ fn store_u32<'a, R: Runtime>(host: &mut R, path: &impl Path, u32: &'a u32) -> Re
     let data = u32.to_be_bytes();
     let data = data.as_slice();
 
-    host.store_write(path, data, 0)
-        .map_err(Error::from)
-        .map(|_| u32)
+    write(host, path, data, 0).map(|_| u32)
 }
 
 /// Stores a string at a given path
+///
+/// The previous value is replaced
 fn store_string<'a, R: Runtime, T>(host: &mut R, path: &OwnedPath, data: &'a T) -> Result<&'a T>
 where
     T: ToString,
 {
     let string = data.to_string();
     let bytes = string.as_bytes();
-    host.store_write(path, bytes, 0)
-        .map_err(Error::from)
-        .map(|_| data)
+    store_bytes(host, path, bytes).map(|_| data)
+}
+
+fn read_string<R: Runtime>(host: &mut R, path: &impl Path) -> Result<Option<String>> {
+    match read_bytes(host, path)? {
+        None => Ok(None),
+        Some(bytes) => String::from_utf8(bytes).map_err(Error::from).map(Some),
+    }
+}
+
+/// Write data at an offset of a value
+///
+/// With the metrics feature, the written bytes and the growth of the value are recorded
+fn write<R: Runtime>(host: &mut R, path: &impl Path, data: &[u8], offset: usize) -> Result<()> {
+    if metrics::ENABLED {
+        let size = host.store_value_size(path).unwrap_or(0);
+        let growth = (offset + data.len()).saturating_sub(size);
+        metrics::record_write(data.len(), growth);
+    }
+    host.store_write(path, data, offset).map_err(Error::from)
+}
+
+/// Delete a value or a subtree
+///
+/// With the metrics feature, the size of the value at the path is recorded,
+/// the values below the path are not counted
+fn delete<R: Runtime>(host: &mut R, path: &impl Path) -> Result<()> {
+    if metrics::ENABLED {
+        let size = host.store_value_size(path).unwrap_or(0);
+        metrics::record_delete(size);
+    }
+    host.store_delete(path).map_err(Error::from)
+}
+
+/// Stores bytes at a given path
+///
+/// The host can only write MAX_FILE_CHUNK_SIZE bytes at once,
+/// so the data is written chunk by chunk
+/// An empty value is written too, so that the path exists
+fn store_bytes<R: Runtime>(host: &mut R, path: &impl Path, data: &[u8]) -> Result<()> {
+    if exists(host, path)? {
+        delete(host, path)?;
+    }
+    if data.is_empty() {
+        return write(host, path, data, 0);
+    }
+
+    let mut size_written = 0;
+    while size_written < data.len() {
+        let num_to_write = usize::min(MAX_FILE_CHUNK_SIZE, data.len() - size_written);
+        let bytes_to_write = &data[size_written..(size_written + num_to_write)];
+        write(host, path, bytes_to_write, size_written)?;
+        size_written += num_to_write;
+    }
+    Ok(())
 }
 
-fn read_string<R: Runtime>(host: &mut R, path: &OwnedPath) -> Result<Option<String>> {
+/// Read bytes from a given path
+///
+/// The host can only read MAX_FILE_CHUNK_SIZE bytes at once,
+/// so the data is read chunk by chunk
+pub fn read_bytes<R: Runtime>(host: &mut R, path: &impl Path) -> Result<Option<Vec<u8>>> {
     let is_exists = exists(host, path)?;
     if !is_exists {
         return Ok(None);
     }
 
     let size = host.store_value_size(path).map_err(Error::from)?;
-    let buffer = host.store_read(path, 0, size).map_err(Error::from)?;
-
-    String::from_utf8(buffer).map_err(Error::from).map(Some)
+    let mut buffer = Vec::with_capacity(size);
+    while buffer.len() < size {
+        let num_to_read = usize::min(MAX_FILE_CHUNK_SIZE, size - buffer.len());
+        let mut chunk = host
+            .store_read(path, buffer.len(), num_to_read)
+            .map_err(Error::from)?;
+        buffer.append(&mut chunk);
+    }
+    Ok(Some(buffer))
 }
 
 /// Creates a flag at the given path
 fn store_flag<R: Runtime>(host: &mut R, path: &impl Path) -> Result<()> {
     let data = [0x00].as_slice();
-    host.store_write(path, data, 0)
-        .map_err(Error::from)
-        .map(|_| ())
+    write(host, path, data, 0).map(|_| ())
 }
 
 /// Stores a boolean at a given path
@@ -192,19 +616,12 @@ fn store_bool<R: Runtime>(host: &mut R, path: &impl Path, bool: bool) -> Result<
         false => [0x00],
     };
 
-    host.store_write(path, &data, 0)
-        .map_err(Error::from)
-        .map(|_| ())
+    write(host, path, &data, 0).map(|_| ())
 }
 
 /// Read the account of the user
 pub fn read_account<R: Runtime>(host: &mut R, public_key_hash: PublicKeyHash) -> Result<Account> {
-    let nonce_path = nonce_path(&public_key_hash)?;
-    let nonce = read_u64(host, &nonce_path)?.unwrap_or_default();
-    Ok(Account {
-        public_key_hash,
-        nonce: Nonce(nonce),
-    })
+    query::get_account(&mut HostReader(host), public_key_hash)
 }
 
 /// Store an account to the location /account/{tz...}
@@ -220,6 +637,31 @@ pub fn store_account<'a, R: Runtime>(host: &mut R, account: &'a Account) -> Resu
     Ok(account)
 }
 
+/// Read the nonces used ahead of the nonce of the account
+pub fn read_used_nonces<R: Runtime>(
+    host: &mut R,
+    public_key_hash: &PublicKeyHash,
+) -> Result<UsedNonces> {
+    let path = used_nonces_path(public_key_hash)?;
+    Ok(UsedNonces(read_u64(host, &path)?.unwrap_or_default()))
+}
+
+/// Store the nonces used ahead of the nonce of the account
+///
+/// The bitmap is deleted when no nonce is used ahead
+pub fn store_used_nonces<R: Runtime>(
+    host: &mut R,
+    public_key_hash: &PublicKeyHash,
+    used_nonces: &UsedNonces,
+) -> Result<()> {
+    let path = used_nonces_path(public_key_hash)?;
+    match used_nonces.0 {
+        0 if exists(host, &path)? => delete(host, &path),
+        0 => Ok(()),
+        bitmap => store_u64(host, &path, &bitmap).map(|_| ()),
+    }
+}
+
 /// Store a tweet to the location /tweets/{tz...}
 pub fn store_tweet<'a, R: Runtime>(
     host: &mut R,
@@ -230,18 +672,31 @@ pub fn store_tweet<'a, R: Runtime>(
         author,
         content,
         likes,
+        retweets,
+        quoted,
     } = tweet;
     let author_path = tweet_author_path(tweet_id)?;
     let content_path = tweet_content_path(tweet_id)?;
     let likes_path = tweet_likes_path(tweet_id)?;
+    let retweets_path = tweet_retweets_path(tweet_id)?;
 
     let _ = store_string(host, &author_path, author)?;
     let _ = store_string(host, &content_path, content)?;
     let _ = store_u64(host, &likes_path, likes)?;
+    let _ = store_u64(host, &retweets_path, retweets)?;
+    if let Some(quoted) = quoted {
+        let _ = store_u64(host, &tweet_quoted_path(tweet_id)?, quoted)?;
+    }
 
     Ok(tweet)
 }
 
+/// Store the body of a tweet revealed from the preimages
+pub fn store_tweet_body<R: Runtime>(host: &mut R, tweet_id: &u64, body: &[u8]) -> Result<()> {
+    let path = tweet_body_path(tweet_id)?;
+    store_bytes(host, &path, body)
+}
+
 /// Increment the tweet counter and return the previous one.
 pub fn increment_tweet_counter<R: Runtime>(host: &mut R) -> Result<u64> {
     let previous_counter = read_u64(host, &TWEET_COUNTER)?.unwrap_or_default();
@@ -250,137 +705,1107 @@ pub fn increment_tweet_counter<R: Runtime>(host: &mut R) -> Result<u64> {
     Ok(previous_counter)
 }
 
+/// Read the number of tweets
+pub fn read_tweet_counter<R: Runtime>(host: &mut R) -> Result<u64> {
+    Ok(read_u64(host, &TWEET_COUNTER)?.unwrap_or_default())
+}
+
 /// Read a tweet from the durable state
 ///
 /// If the tweet is not present an Option is return
 pub fn read_tweet<R: Runtime>(host: &mut R, tweet_id: &u64) -> Result<Option<Tweet>> {
-    let author_path = tweet_author_path(tweet_id)?;
-    let content_path = tweet_content_path(tweet_id)?;
-    let likes_path = tweet_likes_path(tweet_id)?;
-
-    let author = read_string(host, &author_path)?;
-    let author = match author {
-        None => None,
-        Some(str) => Some(PublicKeyHash::from_b58(&str)?),
-    };
-    let content = read_string(host, &content_path)?;
-    let likes = read_u64(host, &likes_path)?;
+    query::get_tweet(&mut HostReader(host), tweet_id)
+}
 
-    match (author, content, likes) {
-        (Some(author), Some(content), Some(likes)) => Ok(Some(Tweet {
-            author,
-            content,
-            likes,
-        })),
-        _ => Ok(None),
+/// Store the poll of a tweet, with a zero tally for each option
+///
+/// The poll is indexed by its closing level, to freeze its results once closed
+pub fn store_poll<R: Runtime>(host: &mut R, tweet_id: &u64, poll: &Poll) -> Result<()> {
+    let Poll {
+        options,
+        closes_at_level,
+    } = poll;
+    let _ = store_u32(
+        host,
+        &tweet_poll_closes_at_level_path(tweet_id)?,
+        closes_at_level,
+    )?;
+    let count = options.len() as u32;
+    let _ = store_u32(host, &tweet_poll_options_count_path(tweet_id)?, &count)?;
+    for (option, label) in (0..count).zip(options) {
+        let _ = store_string(host, &tweet_poll_option_path(tweet_id, &option)?, label)?;
+        let _ = store_u64(host, &tweet_poll_tally_path(tweet_id, &option)?, &0)?;
     }
+
+    let count_path = closing_polls_count_path(closes_at_level)?;
+    let index = read_u64(host, &count_path)?.unwrap_or_default();
+    let _ = store_u64(host, &closing_poll_path(closes_at_level, &index)?, tweet_id)?;
+    let _ = store_u64(host, &count_path, &(index + 1))?;
+    Ok(())
 }
 
-/// Create a flag in the user account that indicates that the user has liked the given tweet
-pub fn set_like_flag<R: Runtime>(
-    host: &mut R,
-    public_key_hash: &PublicKeyHash,
-    tweet_id: &u64,
-) -> Result<()> {
-    let path = account_likes_path(public_key_hash, tweet_id)?;
-    store_flag(host, &path)
+/// Read the poll of a tweet, None if the tweet is not a poll
+pub fn read_poll<R: Runtime>(host: &mut R, tweet_id: &u64) -> Result<Option<Poll>> {
+    query::get_poll(&mut HostReader(host), tweet_id)
 }
 
-/// Check if the user has a like a tweet
-pub fn is_liked<R: Runtime>(
-    host: &mut R,
-    public_key_hash: &PublicKeyHash,
-    tweet_id: &u64,
-) -> Result<bool> {
-    let path = account_likes_path(public_key_hash, tweet_id)?;
+/// Check if the tweet is a poll
+pub fn is_poll<R: Runtime>(host: &mut R, tweet_id: &u64) -> Result<bool> {
+    let path = tweet_poll_closes_at_level_path(tweet_id)?;
     exists(host, &path)
 }
 
-/// Add a tweet in the "written" path of an account
-pub fn add_written_tweet_to_account<R: Runtime>(
-    host: &mut R,
-    public_key_hash: &PublicKeyHash,
-    tweet_id: &u64,
-) -> Result<()> {
-    let path = account_written_tweet_path(public_key_hash, tweet_id)?;
-    store_flag(host, &path)
+/// Add a vote to the tally of an option of a poll
+pub fn increment_poll_tally<R: Runtime>(host: &mut R, tweet_id: &u64, option: &u32) -> Result<()> {
+    let path = tweet_poll_tally_path(tweet_id, option)?;
+    let tally = read_u64(host, &path)?.unwrap_or_default();
+    let _ = store_u64(host, &path, &(tally + 1))?;
+    Ok(())
 }
 
-/// Add a tweet in the "owned" path of an account
-pub fn add_owned_tweet_to_account<R: Runtime>(
+/// Create a flag in the user account that indicates that the user has voted on the given poll
+pub fn set_vote_flag<R: Runtime>(
     host: &mut R,
     public_key_hash: &PublicKeyHash,
     tweet_id: &u64,
 ) -> Result<()> {
-    let path = account_owned_tweet_path(public_key_hash, tweet_id)?;
+    let path = account_votes_path(public_key_hash, tweet_id)?;
     store_flag(host, &path)
 }
 
-/// Checks if the user is owner of the tweet
-pub fn is_owner<R: Runtime>(
+/// Check if the account has already voted on the given poll
+pub fn has_voted<R: Runtime>(
     host: &mut R,
     public_key_hash: &PublicKeyHash,
     tweet_id: &u64,
-) -> Result<()> {
-    let path = account_owned_tweet_path(public_key_hash, tweet_id)?;
-    let is_present = exists(host, &path)?;
+) -> Result<bool> {
+    let path = account_votes_path(public_key_hash, tweet_id)?;
+    exists(host, &path)
+}
 
-    match is_present {
-        true => Ok(()),
-        false => Err(Error::NotOwner),
+/// Read the ids of the polls closing at the given level
+pub fn read_closing_polls<R: Runtime>(host: &mut R, level: &u32) -> Result<Vec<u64>> {
+    let count = read_u64(host, &closing_polls_count_path(level)?)?.unwrap_or_default();
+    let mut tweet_ids = Vec::new();
+    for index in 0..count {
+        let tweet_id = read_u64(host, &closing_poll_path(level, &index)?)?
+            .ok_or(Error::StateDeserializarion)?;
+        tweet_ids.push(tweet_id);
     }
+    Ok(tweet_ids)
 }
 
-/// Transfer a tweet from a user to another one
-/// Does not check if the user owns the tweet
-pub fn transfer<R: Runtime>(
-    host: &mut R,
-    public_key_hash: &PublicKeyHash,
-    tweet_id: &u64,
-    destination: &PublicKeyHash,
-) -> Result<()> {
-    let from = account_owned_tweet_path(public_key_hash, tweet_id)?;
-    let to = account_owned_tweet_path(destination, tweet_id)?;
-    host.store_move(&from, &to).map_err(Error::from)
+/// Copy the tallies of a closed poll to /polls/results/{tweet_id}
+///
+/// The results are stored once, like a receipt, and never change afterwards
+pub fn freeze_poll_results<R: Runtime>(host: &mut R, tweet_id: &u64) -> Result<()> {
+    let votes_path = poll_results_field_path(tweet_id, "/votes")?;
+    if exists(host, &votes_path)? {
+        return Ok(());
+    }
+    let count =
+        read_u32(host, &tweet_poll_options_count_path(tweet_id)?)?.ok_or(Error::NotAPoll)?;
+    let mut votes = 0;
+    for option in 0..count {
+        let tally = read_u64(host, &tweet_poll_tally_path(tweet_id, &option)?)?.unwrap_or_default();
+        let path = poll_results_field_path(tweet_id, &format!("/{}", option))?;
+        let _ = store_u64(host, &path, &tally)?;
+        votes += tally;
+    }
+    let _ = store_u64(host, &votes_path, &votes)?;
+    Ok(())
 }
 
-// Stores a receipt under /receipt/{hash}
-pub fn store_receipt<'a, R: Runtime>(host: &mut R, receipt: &'a Receipt) -> Result<&'a Receipt> {
-    let success_path = receipt_success_path(receipt)?;
-
-    store_bool(host, &success_path, receipt.success())?;
+/// Count a like of a tweet in the bucket of the level
+///
+/// The bucket keeps the list of its tweets, at /trending/buckets/{level}/{index},
+/// and their likes, at /trending/buckets/{level}/likes/{tweet_id}
+///
+/// Returns the likes of the tweet during the trending window
+pub fn add_trending_like<R: Runtime>(host: &mut R, level: &u32, tweet_id: &u64) -> Result<u64> {
+    let likes_path = trending_bucket_field_path(level, &format!("/likes/{}", tweet_id))?;
+    let likes = match read_u64(host, &likes_path)? {
+        Some(likes) => likes,
+        None => {
+            let count_path = trending_bucket_field_path(level, "/count")?;
+            let index = read_u64(host, &count_path)?.unwrap_or_default();
+            let index_path = trending_bucket_field_path(level, &format!("/{}", index))?;
+            let _ = store_u64(host, &index_path, tweet_id)?;
+            let _ = store_u64(host, &count_path, &(index + 1))?;
+            0
+        }
+    };
+    let _ = store_u64(host, &likes_path, &(likes + 1))?;
 
-    Ok(receipt)
+    let window_path = trending_likes_path(tweet_id)?;
+    let window_likes = read_u64(host, &window_path)?.unwrap_or_default() + 1;
+    let _ = store_u64(host, &window_path, &window_likes)?;
+    Ok(window_likes)
 }
 
-/// Returns Ok if the tweet is not collected
-pub fn is_not_collected<R: Runtime>(host: &mut R, tweet_id: &u64) -> Result<()> {
-    let tweet_collected_block_path = tweet_collected_block_path(tweet_id)?;
-    let is_present = exists(host, &tweet_collected_block_path)?;
+/// Remove the bucket of a level from the trending window
+///
+/// Only the tweets liked during the level are read
+///
+/// Returns the tweets of the bucket with their remaining likes during the window
+pub fn expire_trending_bucket<R: Runtime>(host: &mut R, level: &u32) -> Result<Vec<(u64, u64)>> {
+    let count = read_u64(host, &trending_bucket_field_path(level, "/count")?)?.unwrap_or_default();
+    let mut expired = Vec::new();
+    for index in 0..count {
+        let index_path = trending_bucket_field_path(level, &format!("/{}", index))?;
+        let tweet_id = read_u64(host, &index_path)?.ok_or(Error::StateDeserializarion)?;
+        let likes_path = trending_bucket_field_path(level, &format!("/likes/{}", tweet_id))?;
+        let likes = read_u64(host, &likes_path)?.unwrap_or_default();
 
-    match is_present {
-        true => Err(Error::TweetAlreadyCollected),
-        false => Ok(()),
+        let window_path = trending_likes_path(&tweet_id)?;
+        let window_likes = read_u64(host, &window_path)?
+            .unwrap_or_default()
+            .saturating_sub(likes);
+        match window_likes {
+            0 => delete(host, &window_path)?,
+            _ => {
+                let _ = store_u64(host, &window_path, &window_likes)?;
+            }
+        }
+        expired.push((tweet_id, window_likes));
     }
+    let bucket_path = trending_bucket_path(level)?;
+    if exists(host, &bucket_path)? {
+        delete(host, &bucket_path)?;
+    }
+    Ok(expired)
 }
 
-/// Set the block when the tweet has been collected
-pub fn set_collected_block<R: Runtime>(
+/// Compute the path of the metrics of a level
+/// /metrics/{level}
+pub(crate) fn metrics_path(level: &u32) -> Result<OwnedPath> {
+    let path: Vec<u8> = format!("/{}", level).into();
+    let path = OwnedPath::try_from(path).map_err(Error::from)?;
+    concat(&METRICS, &path).map_err(Error::from)
+}
+
+/// Read the metrics recorded at a level
+pub fn read_level_metrics<R: Runtime>(host: &mut R, level: &u32) -> Result<Option<LevelMetrics>> {
+    query::get_level_metrics(&mut HostReader(host), level)
+}
+
+/// Store the metrics of a level
+pub fn store_level_metrics<R: Runtime>(
     host: &mut R,
-    tweet_id: &u64,
-    previous_block: &u32,
+    level: &u32,
+    metrics: &LevelMetrics,
 ) -> Result<()> {
-    let tweet_collected_block_path = tweet_collected_block_path(tweet_id)?;
-    let _ = store_u32(host, &tweet_collected_block_path, previous_block)?;
+    store_bytes(host, &metrics_path(level)?, &metrics.encode())
+}
+
+/// Read the candidates of the trending top
+pub fn read_trending_candidates<R: Runtime>(host: &mut R) -> Result<TopTweets> {
+    query::get_trending_candidates(&mut HostReader(host))
+}
+
+/// Store the candidates of the trending top
+pub fn store_top_tweets<R: Runtime>(host: &mut R, top: &TopTweets) -> Result<()> {
+    store_bytes(host, &TRENDING_TOP, &top.encode())
+}
+
+/// Create the retweets counter of a tweet posted before the retweets
+pub fn init_retweets_counter<R: Runtime>(host: &mut R, tweet_id: &u64) -> Result<()> {
+    let path = tweet_retweets_path(tweet_id)?;
+    if !exists(host, &tweet_author_path(tweet_id)?)? || exists(host, &path)? {
+        return Ok(());
+    }
+    let _ = store_u64(host, &path, &0)?;
     Ok(())
 }
 
-/// Indicates that a tweet is beeing collected by the given user
-pub fn add_collecting_tweet_to_account<R: Runtime>(
-    host: &mut R,
-    public_key_hash: &PublicKeyHash,
+/// Read the owner of a tweet
+///
+/// The tweets transferred before the owners were stored have no known owner
+pub fn read_tweet_owner<R: Runtime>(host: &mut R, tweet_id: &u64) -> Result<Option<PublicKeyHash>> {
+    query::get_tweet_owner(&mut HostReader(host), tweet_id)
+}
+
+/// Store the owner of a tweet posted before the owners were stored
+///
+/// The owner was only indexed by account, the author is the owner if they still own the tweet
+/// The kernel cannot list the accounts to find the owner of a transferred tweet,
+/// it returns an error until the owner is written in /tweets/{id}/owner
+pub fn init_tweet_owner<R: Runtime>(host: &mut R, tweet_id: &u64) -> Result<()> {
+    let path = tweet_owner_path(tweet_id)?;
+    if exists(host, &path)? {
+        return Ok(());
+    }
+    let author = match read_string(host, &tweet_author_path(tweet_id)?)? {
+        None => return Ok(()),
+        Some(author) => PublicKeyHash::from_b58(&author)?,
+    };
+    match exists(host, &account_owned_tweet_path(&author, tweet_id)?)? {
+        true => store_string(host, &path, &author).map(|_| ()),
+        false => Err(Error::UnknownTweetOwner),
+    }
+}
+
+/// Read the merkle root of the tweets
+pub fn read_merkle_root<R: Runtime>(host: &mut R) -> Result<Node> {
+    query::get_merkle_root(&mut HostReader(host))
+}
+
+/// Store the leaf of a tweet and update the nodes of its path up to the root
+///
+/// Returns the new root
+pub fn store_merkle_leaf<R: Runtime>(host: &mut R, tweet_id: &u64, leaf: &Node) -> Result<Node> {
+    if tweet_id >> MERKLE_TREE_DEPTH != 0 {
+        return Err(Error::MerkleTreeFull);
+    }
+    let empty = merkle::empty_nodes();
+    let mut hash = *leaf;
+    let mut index = *tweet_id;
+    for (height, empty) in empty.iter().take(MERKLE_TREE_DEPTH).enumerate() {
+        store_bytes(host, &merkle_node_path(&height, &index)?, &hash)?;
+        let sibling = match read_bytes(host, &merkle_node_path(&height, &(index ^ 1))?)? {
+            None => *empty,
+            Some(sibling) => merkle::decode_node(&sibling)?,
+        };
+        hash = match index & 1 {
+            0 => merkle::node(&hash, &sibling),
+            _ => merkle::node(&sibling, &hash),
+        };
+        index >>= 1;
+    }
+    store_bytes(host, &MERKLE_ROOT, &hash)?;
+    Ok(hash)
+}
+
+/// Update the leaf of a tweet in the merkle tree of the tweets
+///
+/// Called each time the author, the owner, the content, the likes or the retweets of a tweet change
+pub fn commit_tweet<R: Runtime>(host: &mut R, tweet_id: &u64) -> Result<()> {
+    let tweet = match read_tweet(host, tweet_id)? {
+        None => return Ok(()),
+        Some(tweet) => tweet,
+    };
+    let owner = read_tweet_owner(host, tweet_id)?;
+    let leaf = merkle::leaf(tweet_id, &tweet, owner.as_ref());
+    store_merkle_leaf(host, tweet_id, &leaf).map(|_| ())
+}
+
+/// Create a flag in the user account that indicates that the user has retweeted the given tweet
+pub fn set_retweet_flag<R: Runtime>(
+    host: &mut R,
+    public_key_hash: &PublicKeyHash,
+    tweet_id: &u64,
+) -> Result<()> {
+    let path = account_retweets_path(public_key_hash, tweet_id)?;
+    store_flag(host, &path)
+}
+
+/// Check if the account has already retweeted the given tweet
+pub fn is_retweeted<R: Runtime>(
+    host: &mut R,
+    public_key_hash: &PublicKeyHash,
+    tweet_id: &u64,
+) -> Result<bool> {
+    let path = account_retweets_path(public_key_hash, tweet_id)?;
+    exists(host, &path)
+}
+
+/// Create a flag in the user account that indicates that the user has liked the given tweet
+pub fn set_like_flag<R: Runtime>(
+    host: &mut R,
+    public_key_hash: &PublicKeyHash,
+    tweet_id: &u64,
+) -> Result<()> {
+    let path = account_likes_path(public_key_hash, tweet_id)?;
+    store_flag(host, &path)
+}
+
+/// Check if the user has a like a tweet
+pub fn is_liked<R: Runtime>(
+    host: &mut R,
+    public_key_hash: &PublicKeyHash,
+    tweet_id: &u64,
+) -> Result<bool> {
+    let path = account_likes_path(public_key_hash, tweet_id)?;
+    exists(host, &path)
+}
+
+/// Add a tweet in the "written" path of an account
+pub fn add_written_tweet_to_account<R: Runtime>(
+    host: &mut R,
+    public_key_hash: &PublicKeyHash,
+    tweet_id: &u64,
+) -> Result<()> {
+    let path = account_written_tweet_path(public_key_hash, tweet_id)?;
+    store_flag(host, &path)?;
+    push_account_tweet_index(host, public_key_hash, WRITTEN_INDEX, tweet_id)
+}
+
+/// Add a tweet in the "owned" path of an account
+///
+/// The account becomes the owner of the tweet
+pub fn add_owned_tweet_to_account<R: Runtime>(
+    host: &mut R,
+    public_key_hash: &PublicKeyHash,
+    tweet_id: &u64,
+) -> Result<()> {
+    let path = account_owned_tweet_path(public_key_hash, tweet_id)?;
+    store_flag(host, &path)?;
+    let _ = store_string(host, &tweet_owner_path(tweet_id)?, public_key_hash)?;
+    push_account_tweet_index(host, public_key_hash, OWNED_INDEX, tweet_id)
+}
+
+/// Append a tweet to an index of the tweets of an account
+fn push_account_tweet_index<R: Runtime>(
+    host: &mut R,
+    public_key_hash: &PublicKeyHash,
+    kind: &str,
+    tweet_id: &u64,
+) -> Result<()> {
+    let count_path = account_tweet_index_count_path(public_key_hash, kind)?;
+    let index = read_u64(host, &count_path)?.unwrap_or_default();
+    let path = account_tweet_index_path(public_key_hash, kind, &index)?;
+    let _ = store_u64(host, &path, tweet_id)?;
+    let _ = store_u64(host, &count_path, &(index + 1))?;
+    Ok(())
+}
+
+/// Read the entries of an index of the tweets of an account
+fn read_account_tweet_index<R: Runtime>(
+    host: &mut R,
+    public_key_hash: &PublicKeyHash,
+    kind: &str,
+) -> Result<Vec<u64>> {
+    let count_path = account_tweet_index_count_path(public_key_hash, kind)?;
+    let count = read_u64(host, &count_path)?.unwrap_or_default();
+    let mut tweet_ids = Vec::new();
+    for index in 0..count {
+        let path = account_tweet_index_path(public_key_hash, kind, &index)?;
+        let tweet_id = read_u64(host, &path)?.ok_or(Error::StateDeserializarion)?;
+        tweet_ids.push(tweet_id);
+    }
+    Ok(tweet_ids)
+}
+
+/// Read the ids of the tweets written by an account, and the tweets it has retweeted
+///
+/// Only reads the index of the account, the other tweets are not visited
+pub fn read_written_tweets<R: Runtime>(
+    host: &mut R,
+    public_key_hash: &PublicKeyHash,
+) -> Result<Vec<u64>> {
+    read_account_tweet_index(host, public_key_hash, WRITTEN_INDEX)
+}
+
+/// Read the ids of the tweets owned by an account
+///
+/// The index keeps the tweets that have been transferred away, they are filtered out
+pub fn read_owned_tweets<R: Runtime>(
+    host: &mut R,
+    public_key_hash: &PublicKeyHash,
+) -> Result<Vec<u64>> {
+    let mut tweet_ids = Vec::new();
+    for tweet_id in read_account_tweet_index(host, public_key_hash, OWNED_INDEX)? {
+        if exists(host, &account_owned_tweet_path(public_key_hash, &tweet_id)?)? {
+            tweet_ids.push(tweet_id);
+        }
+    }
+    // A tweet transferred back to the account is indexed twice
+    tweet_ids.sort_unstable();
+    tweet_ids.dedup();
+    Ok(tweet_ids)
+}
+
+/// Add a tweet posted before the indexes of the accounts to the index of its author and of its owner
+///
+/// Only the author and the owner can be found from the tweet, the retweets are not indexed
+pub fn index_account_tweets<R: Runtime>(host: &mut R, tweet_id: &u64) -> Result<()> {
+    if let Some(author) = read_string(host, &tweet_author_path(tweet_id)?)? {
+        let author = PublicKeyHash::from_b58(&author)?;
+        if exists(host, &account_written_tweet_path(&author, tweet_id)?)? {
+            push_account_tweet_index(host, &author, WRITTEN_INDEX, tweet_id)?;
+        }
+    }
+    if let Some(owner) = read_tweet_owner(host, tweet_id)? {
+        if exists(host, &account_owned_tweet_path(&owner, tweet_id)?)? {
+            push_account_tweet_index(host, &owner, OWNED_INDEX, tweet_id)?;
+        }
+    }
+    Ok(())
+}
+
+/// Checks if the user is owner of the tweet
+pub fn is_owner<R: Runtime>(
+    host: &mut R,
+    public_key_hash: &PublicKeyHash,
+    tweet_id: &u64,
+) -> Result<()> {
+    let path = account_owned_tweet_path(public_key_hash, tweet_id)?;
+    let is_present = exists(host, &path)?;
+
+    match is_present {
+        true => Ok(()),
+        false => Err(Error::NotOwner),
+    }
+}
+
+/// Transfer a tweet from a user to another one
+/// Does not check if the user owns the tweet
+pub fn transfer<R: Runtime>(
+    host: &mut R,
+    public_key_hash: &PublicKeyHash,
+    tweet_id: &u64,
+    destination: &PublicKeyHash,
+) -> Result<()> {
+    let from = account_owned_tweet_path(public_key_hash, tweet_id)?;
+    let to = account_owned_tweet_path(destination, tweet_id)?;
+    host.store_move(&from, &to).map_err(Error::from)?;
+    let _ = store_string(host, &tweet_owner_path(tweet_id)?, destination)?;
+    push_account_tweet_index(host, destination, OWNED_INDEX, tweet_id)
+}
+
+// Stores a receipt under /receipt/{hash}
+pub fn store_receipt<'a, R: Runtime>(host: &mut R, receipt: &'a Receipt) -> Result<&'a Receipt> {
+    let success_path = receipt_success_path(receipt.hash())?;
+
+    store_bool(host, &success_path, receipt.success())?;
+
+    // The receipt of a deferred message is replaced once the message is replayed
+    let error_path = receipt_error_path(receipt.hash())?;
+    match receipt.error() {
+        Some(error) => {
+            let _ = store_string(host, &error_path, error)?;
+        }
+        None if exists(host, &error_path)? => delete(host, &error_path)?,
+        None => {}
+    }
+
+    Ok(receipt)
+}
+
+/// Set the block when the tweet has been collected
+pub fn set_collected_block<R: Runtime>(
+    host: &mut R,
+    tweet_id: &u64,
+    previous_block: &u32,
+) -> Result<()> {
+    let tweet_collected_block_path = tweet_collected_block_path(tweet_id)?;
+    let _ = store_u32(host, &tweet_collected_block_path, previous_block)?;
+    Ok(())
+}
+
+/// Indicates that a tweet is beeing collected by the given user
+pub fn add_collecting_tweet_to_account<R: Runtime>(
+    host: &mut R,
+    public_key_hash: &PublicKeyHash,
     tweet_id: &u64,
 ) -> Result<()> {
     let account_collecting_path = account_collecting_path(public_key_hash, tweet_id)?;
     store_flag(host, &account_collecting_path)
 }
+
+/// Indicates that the outbox message of a collected tweet has been written
+///
+/// The tweet is moved from the "collecting" path to the "collected" path of the account
+pub fn set_collected_flag<R: Runtime>(
+    host: &mut R,
+    public_key_hash: &PublicKeyHash,
+    tweet_id: &u64,
+) -> Result<()> {
+    let from = account_collecting_path(public_key_hash, tweet_id)?;
+    let to = account_collected_path(public_key_hash, tweet_id)?;
+    host.store_move(&from, &to).map_err(Error::from)
+}
+
+/// Returns true if no outbox message is waiting in the queue
+pub fn is_outbox_queue_empty<R: Runtime>(host: &mut R) -> Result<bool> {
+    let head = read_u64(host, &OUTBOX_QUEUE_HEAD)?.unwrap_or_default();
+    let tail = read_u64(host, &OUTBOX_QUEUE_TAIL)?.unwrap_or_default();
+    Ok(head == tail)
+}
+
+/// Add an outbox message at the end of the queue
+///
+/// /outbox/queue/{index}/payload
+/// /outbox/queue/{index}/owner and /outbox/queue/{index}/tweet_id for collects
+pub fn push_outbox_queue<R: Runtime>(host: &mut R, output: &PendingOutput) -> Result<()> {
+    let tail = read_u64(host, &OUTBOX_QUEUE_TAIL)?.unwrap_or_default();
+
+    let payload_path = outbox_queue_field_path(&tail, "/payload")?;
+    store_bytes(host, &payload_path, &output.payload)?;
+
+    if let Some((owner, tweet_id)) = &output.collect {
+        let owner_path = outbox_queue_field_path(&tail, "/owner")?;
+        let tweet_id_path = outbox_queue_field_path(&tail, "/tweet_id")?;
+        let _ = store_string(host, &owner_path, owner)?;
+        let _ = store_u64(host, &tweet_id_path, tweet_id)?;
+    }
+
+    let _ = store_u64(host, &OUTBOX_QUEUE_TAIL, &(tail + 1))?;
+    Ok(())
+}
+
+/// Read the first outbox message of the queue, without removing it
+pub fn peek_outbox_queue<R: Runtime>(host: &mut R) -> Result<Option<PendingOutput>> {
+    if is_outbox_queue_empty(host)? {
+        return Ok(None);
+    }
+    let head = read_u64(host, &OUTBOX_QUEUE_HEAD)?.unwrap_or_default();
+
+    let payload_path = outbox_queue_field_path(&head, "/payload")?;
+    let owner_path = outbox_queue_field_path(&head, "/owner")?;
+    let tweet_id_path = outbox_queue_field_path(&head, "/tweet_id")?;
+
+    let payload = read_bytes(host, &payload_path)?.ok_or(Error::StateDeserializarion)?;
+    let owner = match read_string(host, &owner_path)? {
+        None => None,
+        Some(str) => Some(PublicKeyHash::from_b58(&str)?),
+    };
+    let tweet_id = read_u64(host, &tweet_id_path)?;
+
+    let collect = match (owner, tweet_id) {
+        (Some(owner), Some(tweet_id)) => Some((owner, tweet_id)),
+        _ => None,
+    };
+    Ok(Some(PendingOutput { payload, collect }))
+}
+
+/// Remove the first outbox message of the queue
+pub fn pop_outbox_queue<R: Runtime>(host: &mut R) -> Result<()> {
+    if is_outbox_queue_empty(host)? {
+        return Ok(());
+    }
+    let head = read_u64(host, &OUTBOX_QUEUE_HEAD)?.unwrap_or_default();
+
+    let entry_path: Vec<u8> = format!("/{}", head).into();
+    let entry_path = OwnedPath::try_from(entry_path).map_err(Error::from)?;
+    let entry_path = concat(&OUTBOX_QUEUE, &entry_path).map_err(Error::from)?;
+    delete(host, &entry_path)?;
+
+    let _ = store_u64(host, &OUTBOX_QUEUE_HEAD, &(head + 1))?;
+    Ok(())
+}
+
+/// Returns the index of the next message written to the outbox of the given level
+///
+/// The counter is reset at each level
+pub fn increment_outbox_counter<R: Runtime>(host: &mut R, level: &u32) -> Result<u64> {
+    let counter = match read_u32(host, &OUTBOX_LEVEL)? {
+        Some(outbox_level) if &outbox_level == level => {
+            read_u64(host, &OUTBOX_COUNTER)?.unwrap_or_default()
+        }
+        _ => 0,
+    };
+    let _ = store_u32(host, &OUTBOX_LEVEL, level)?;
+    let _ = store_u64(host, &OUTBOX_COUNTER, &(counter + 1))?;
+    Ok(counter)
+}
+
+/// Store the outbox level and index of the collect message of a tweet
+pub fn set_collect_outbox<R: Runtime>(
+    host: &mut R,
+    tweet_id: &u64,
+    outbox_level: &u32,
+    outbox_index: &u64,
+) -> Result<()> {
+    let level_path = tweet_collect_outbox_level_path(tweet_id)?;
+    let index_path = tweet_collect_outbox_index_path(tweet_id)?;
+    let _ = store_u32(host, &level_path, outbox_level)?;
+    let _ = store_u64(host, &index_path, outbox_index)?;
+    Ok(())
+}
+
+/// Indicates that the L1 contract has minted the tweet
+pub fn set_collect_finalized<R: Runtime>(host: &mut R, tweet_id: &u64) -> Result<()> {
+    let path = tweet_collect_finalized_path(tweet_id)?;
+    store_flag(host, &path)
+}
+
+/// Read the state of the collect of a tweet
+pub fn read_collect_state<R: Runtime>(host: &mut R, tweet_id: &u64) -> Result<CollectState> {
+    let finalized_path = tweet_collect_finalized_path(tweet_id)?;
+    if exists(host, &finalized_path)? {
+        return Ok(CollectState::Finalized);
+    }
+
+    let level_path = tweet_collect_outbox_level_path(tweet_id)?;
+    let index_path = tweet_collect_outbox_index_path(tweet_id)?;
+    let level = read_u32(host, &level_path)?;
+    let index = read_u64(host, &index_path)?;
+    if let (Some(level), Some(index)) = (level, index) {
+        return Ok(CollectState::Written { level, index });
+    }
+
+    let collected_block_path = tweet_collected_block_path(tweet_id)?;
+    match exists(host, &collected_block_path)? {
+        true => Ok(CollectState::Queued),
+        false => Ok(CollectState::NotCollected),
+    }
+}
+
+/// Delete all the information of the collect of a tweet
+///
+/// The tweet can be collected again
+pub fn reset_collect<R: Runtime>(
+    host: &mut R,
+    public_key_hash: &PublicKeyHash,
+    tweet_id: &u64,
+) -> Result<()> {
+    let paths = [
+        tweet_collected_block_path(tweet_id)?,
+        tweet_collect_path(tweet_id)?,
+        account_collecting_path(public_key_hash, tweet_id)?,
+        account_collected_path(public_key_hash, tweet_id)?,
+    ];
+    for path in paths.iter() {
+        if exists(host, path)? {
+            delete(host, path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Read the configuration of the kernel
+///
+/// The missing values are replaced by the default ones
+pub fn read_config<R: Runtime>(host: &mut R) -> Result<Config> {
+    let window = read_u32(host, &CONFIG_RATE_LIMIT_WINDOW)?.unwrap_or(DEFAULT_RATE_LIMIT_WINDOW);
+    let max_posts = read_u64(host, &CONFIG_MAX_POSTS)?.unwrap_or(DEFAULT_MAX_POSTS);
+    let max_likes = read_u64(host, &CONFIG_MAX_LIKES)?.unwrap_or(DEFAULT_MAX_LIKES);
+    let posting_fee = read_u64(host, &CONFIG_POSTING_FEE)?.unwrap_or(DEFAULT_POSTING_FEE);
+    let nonce_window = read_u64(host, &CONFIG_NONCE_WINDOW)?
+        .unwrap_or(DEFAULT_NONCE_WINDOW)
+        .clamp(1, MAX_NONCE_WINDOW);
+    let commitment_period =
+        read_u32(host, &CONFIG_COMMITMENT_PERIOD)?.unwrap_or(DEFAULT_COMMITMENT_PERIOD);
+    let registry = read_string(host, &CONFIG_REGISTRY)?;
+    Ok(Config {
+        rate_limit: RateLimit {
+            window,
+            max_posts,
+            max_likes,
+        },
+        posting_fee,
+        nonce_window,
+        commitment_period,
+        registry,
+    })
+}
+
+/// Read the quota of an account
+pub fn read_quota<R: Runtime>(host: &mut R, public_key_hash: &PublicKeyHash) -> Result<Quota> {
+    let window = read_u32(host, &quota_field_path(public_key_hash, "window")?)?;
+    let posts = read_u64(host, &quota_field_path(public_key_hash, "posts")?)?;
+    let likes = read_u64(host, &quota_field_path(public_key_hash, "likes")?)?;
+    Ok(Quota {
+        window: window.unwrap_or_default(),
+        posts: posts.unwrap_or_default(),
+        likes: likes.unwrap_or_default(),
+    })
+}
+
+/// Store the quota of an account
+pub fn store_quota<R: Runtime>(
+    host: &mut R,
+    public_key_hash: &PublicKeyHash,
+    quota: &Quota,
+) -> Result<()> {
+    let Quota {
+        window,
+        posts,
+        likes,
+    } = quota;
+    let _ = store_u32(host, &quota_field_path(public_key_hash, "window")?, window)?;
+    let _ = store_u64(host, &quota_field_path(public_key_hash, "posts")?, posts)?;
+    let _ = store_u64(host, &quota_field_path(public_key_hash, "likes")?, likes)?;
+    Ok(())
+}
+
+/// Read the prepaid balance of an account, in mutez
+pub fn read_balance<R: Runtime>(host: &mut R, public_key_hash: &PublicKeyHash) -> Result<u64> {
+    let path = balance_path(public_key_hash)?;
+    read_u64(host, &path).map(Option::unwrap_or_default)
+}
+
+/// Store the prepaid balance of an account, in mutez
+pub fn store_balance<R: Runtime>(
+    host: &mut R,
+    public_key_hash: &PublicKeyHash,
+    balance: &u64,
+) -> Result<()> {
+    let path = balance_path(public_key_hash)?;
+    let _ = store_u64(host, &path, balance)?;
+    Ok(())
+}
+
+/// Read the admin of the kernel
+///
+/// The admin is set by the installer, there is no admin otherwise
+pub fn read_admin<R: Runtime>(host: &mut R) -> Result<Option<PublicKeyHash>> {
+    let admin = read_string(host, &CONFIG_ADMIN)?;
+    match admin {
+        None => Ok(None),
+        Some(str) => PublicKeyHash::from_b58(&str).map(Some),
+    }
+}
+
+/// Replace the content of a tweet by a redacted marker
+///
+/// The revealed body and the previous versions are deleted
+/// The author, the owner and the likes of the tweet are kept
+pub fn hide_tweet_content<R: Runtime>(host: &mut R, tweet_id: &u64) -> Result<()> {
+    let content_path = tweet_content_path(tweet_id)?;
+    let body_path = tweet_body_path(tweet_id)?;
+    let history_path = tweet_field_path(tweet_id, "/history")?;
+    let hidden_path = tweet_hidden_path(tweet_id)?;
+    let _ = store_string(host, &content_path, &REDACTED_CONTENT)?;
+    if exists(host, &body_path)? {
+        delete(host, &body_path)?;
+    }
+    if exists(host, &history_path)? {
+        delete(host, &history_path)?;
+    }
+    store_flag(host, &hidden_path)
+}
+
+/// Check if a tweet has been hidden by the moderation
+pub fn is_hidden<R: Runtime>(host: &mut R, tweet_id: &u64) -> Result<bool> {
+    let path = tweet_hidden_path(tweet_id)?;
+    exists(host, &path)
+}
+
+/// Check if the body of a tweet has been revealed from the preimages
+pub fn has_tweet_body<R: Runtime>(host: &mut R, tweet_id: &u64) -> Result<bool> {
+    let path = tweet_body_path(tweet_id)?;
+    exists(host, &path)
+}
+
+/// Replace the content of a tweet
+///
+/// The previous content is kept at /tweets/{id}/history/{n} with the level of the edit
+pub fn edit_tweet_content<R: Runtime>(
+    host: &mut R,
+    tweet_id: &u64,
+    content: &String,
+    level: &u32,
+) -> Result<()> {
+    let content_path = tweet_content_path(tweet_id)?;
+    let previous = read_string(host, &content_path)?.ok_or(Error::TweetNotFound)?;
+
+    let count_path = tweet_history_count_path(tweet_id)?;
+    let version = read_u64(host, &count_path)?.unwrap_or_default();
+    let _ = store_string(
+        host,
+        &tweet_version_content_path(tweet_id, &version)?,
+        &previous,
+    )?;
+    let _ = store_u32(host, &tweet_version_level_path(tweet_id, &version)?, level)?;
+    let _ = store_u64(host, &count_path, &(version + 1))?;
+
+    let _ = store_string(host, &content_path, content)?;
+    Ok(())
+}
+
+/// Add an account to the ban list
+pub fn ban_account<R: Runtime>(host: &mut R, public_key_hash: &PublicKeyHash) -> Result<()> {
+    let path = banned_account_path(public_key_hash)?;
+    store_flag(host, &path)
+}
+
+/// Remove an account from the ban list
+pub fn unban_account<R: Runtime>(host: &mut R, public_key_hash: &PublicKeyHash) -> Result<()> {
+    let path = banned_account_path(public_key_hash)?;
+    if exists(host, &path)? {
+        delete(host, &path)?;
+    }
+    Ok(())
+}
+
+/// Check if an account is in the ban list
+pub fn is_banned<R: Runtime>(host: &mut R, public_key_hash: &PublicKeyHash) -> Result<bool> {
+    let path = banned_account_path(public_key_hash)?;
+    exists(host, &path)
+}
+
+/// Read the number of events appended during the level
+pub fn read_events_count<R: Runtime>(host: &mut R, level: &u32) -> Result<u64> {
+    let path = events_count_path(level)?;
+    Ok(read_u64(host, &path)?.unwrap_or_default())
+}
+
+/// Append an event to the log of the level
+pub fn append_event<R: Runtime>(host: &mut R, level: &u32, event: &Event) -> Result<()> {
+    let index = read_events_count(host, level)?;
+    let path = event_path(level, &index)?;
+    store_bytes(host, &path, &event.encode())?;
+    let _ = store_u64(host, &events_count_path(level)?, &(index + 1))?;
+    Ok(())
+}
+
+/// Store a session key of an account
+///
+/// A previous authorization of the same key is replaced
+/// The allowed operations are stored as flags under /allowed/{operation}
+pub fn store_session_key<R: Runtime>(
+    host: &mut R,
+    account: &PublicKeyHash,
+    key: &PublicKeyHash,
+    session_key: &SessionKey,
+) -> Result<()> {
+    revoke_session_key(host, account, key)?;
+    let expiry_path = session_key_field_path(account, key, "/expiry")?;
+    let _ = store_u32(host, &expiry_path, &session_key.expiry_level)?;
+    for operation in &session_key.allowed_ops {
+        let path = session_key_field_path(account, key, &format!("/allowed/{}", operation.name()))?;
+        store_flag(host, &path)?;
+    }
+    Ok(())
+}
+
+/// Read a session key of an account
+///
+/// Returns None if the key has never been authorized or has been revoked
+pub fn read_session_key<R: Runtime>(
+    host: &mut R,
+    account: &PublicKeyHash,
+    key: &PublicKeyHash,
+) -> Result<Option<SessionKey>> {
+    let expiry_path = session_key_field_path(account, key, "/expiry")?;
+    let expiry_level = match read_u32(host, &expiry_path)? {
+        None => return Ok(None),
+        Some(expiry_level) => expiry_level,
+    };
+    let mut allowed_ops = Vec::new();
+    for operation in SessionOperation::all() {
+        let path = session_key_field_path(account, key, &format!("/allowed/{}", operation.name()))?;
+        if exists(host, &path)? {
+            allowed_ops.push(operation);
+        }
+    }
+    Ok(Some(SessionKey {
+        expiry_level,
+        allowed_ops,
+    }))
+}
+
+/// Delete a session key of an account
+pub fn revoke_session_key<R: Runtime>(
+    host: &mut R,
+    account: &PublicKeyHash,
+    key: &PublicKeyHash,
+) -> Result<()> {
+    let path = session_key_path(account, key)?;
+    if exists(host, &path)? {
+        delete(host, &path)?;
+    }
+    Ok(())
+}
+
+/// Check if any state is stored for the account
+pub fn account_exists<R: Runtime>(host: &mut R, public_key_hash: &PublicKeyHash) -> Result<bool> {
+    let path = account_path(public_key_hash)?;
+    exists(host, &path)
+}
+
+/// Read the account an address has been migrated to
+pub fn read_migrated_to<R: Runtime>(
+    host: &mut R,
+    public_key_hash: &PublicKeyHash,
+) -> Result<Option<PublicKeyHash>> {
+    query::get_migrated_to(&mut HostReader(host), public_key_hash)
+}
+
+/// Follow the forwarding records of an address
+///
+/// Returns the address itself if it has never been migrated
+pub fn forwarded_account<R: Runtime>(
+    host: &mut R,
+    public_key_hash: &PublicKeyHash,
+) -> Result<PublicKeyHash> {
+    let mut public_key_hash = public_key_hash.clone();
+    while let Some(migrated_to) = read_migrated_to(host, &public_key_hash)? {
+        public_key_hash = migrated_to;
+    }
+    Ok(public_key_hash)
+}
+
+/// Move the state of an account to a new address
+///
+/// The whole subtree of the account is moved, the session keys are dropped,
+/// the author of the written tweets and the owner of the owned tweets are updated
+/// and a forwarding record is left at the old address
+pub fn migrate_account<R: Runtime>(
+    host: &mut R,
+    from: &PublicKeyHash,
+    to: &PublicKeyHash,
+    written_tweets: &[u64],
+    owned_tweets: &[u64],
+) -> Result<()> {
+    let from_path = account_path(from)?;
+    let to_path = account_path(to)?;
+    host.store_move(&from_path, &to_path).map_err(Error::from)?;
+
+    let session_keys_path = account_field_path(to, "/session_keys")?;
+    if exists(host, &session_keys_path)? {
+        delete(host, &session_keys_path)?;
+    }
+
+    // The retweets are in the written index too, they keep their author
+    let from_address = from.to_string();
+    for tweet_id in written_tweets {
+        let author_path = tweet_author_path(tweet_id)?;
+        if read_string(host, &author_path)?.as_ref() == Some(&from_address) {
+            let _ = store_string(host, &author_path, to)?;
+        }
+    }
+    for tweet_id in owned_tweets {
+        let _ = store_string(host, &tweet_owner_path(tweet_id)?, to)?;
+    }
+
+    let migrated_to_path = migrated_to_path(from)?;
+    let _ = store_string(host, &migrated_to_path, to)?;
+    Ok(())
+}
+
+/// Store the signers of a multisig account
+///
+/// The previous signers are replaced
+/// The threshold and the count are stored as u32, each signer at /signers/{index}
+pub fn store_signers<R: Runtime>(
+    host: &mut R,
+    account: &PublicKeyHash,
+    signers: &Signers,
+) -> Result<()> {
+    let signers_path = signers_field_path(account, "")?;
+    if exists(host, &signers_path)? {
+        delete(host, &signers_path)?;
+    }
+    let count = signers.signers.len() as u32;
+    let _ = store_u32(
+        host,
+        &signers_field_path(account, "/threshold")?,
+        &signers.threshold,
+    )?;
+    let _ = store_u32(host, &signers_field_path(account, "/count")?, &count)?;
+    for (index, signer) in signers.signers.iter().enumerate() {
+        let path = signers_field_path(account, &format!("/{}", index))?;
+        let _ = store_string(host, &path, signer)?;
+    }
+    Ok(())
+}
+
+/// Read the signers of an account
+///
+/// Returns None if the account is controlled by a single key
+pub fn read_signers<R: Runtime>(host: &mut R, account: &PublicKeyHash) -> Result<Option<Signers>> {
+    let threshold = match read_u32(host, &signers_field_path(account, "/threshold")?)? {
+        None => return Ok(None),
+        Some(threshold) => threshold,
+    };
+    let count = read_u32(host, &signers_field_path(account, "/count")?)?.unwrap_or_default();
+    let mut signers = Vec::new();
+    for index in 0..count {
+        let path = signers_field_path(account, &format!("/{}", index))?;
+        let signer = read_string(host, &path)?.ok_or(Error::StateDeserializarion)?;
+        signers.push(PublicKeyHash::from_b58(&signer)?);
+    }
+    Ok(Some(Signers { threshold, signers }))
+}
+
+/// Read the version of the layout of the durable storage
+///
+/// A storage without version has been written before the schema versions
+pub fn read_schema_version<R: Runtime>(host: &mut R) -> Result<u64> {
+    Ok(read_u64(host, &SCHEMA_VERSION)?.unwrap_or_default())
+}
+
+/// Store the version of the layout of the durable storage
+pub fn store_schema_version<R: Runtime>(host: &mut R, version: &u64) -> Result<()> {
+    store_u64(host, &SCHEMA_VERSION, version).map(|_| ())
+}
+
+/// Read where the pending migration has stopped
+pub fn read_migration_cursor<R: Runtime>(host: &mut R) -> Result<u64> {
+    Ok(read_u64(host, &SCHEMA_CURSOR)?.unwrap_or_default())
+}
+
+/// Store where the pending migration has stopped
+pub fn store_migration_cursor<R: Runtime>(host: &mut R, cursor: &u64) -> Result<()> {
+    store_u64(host, &SCHEMA_CURSOR, cursor).map(|_| ())
+}
+
+/// Delete the cursor of a completed migration
+pub fn delete_migration_cursor<R: Runtime>(host: &mut R) -> Result<()> {
+    if exists(host, &SCHEMA_CURSOR)? {
+        delete(host, &SCHEMA_CURSOR)?;
+    }
+    Ok(())
+}
+
+/// Read the pause of the kernel, None when the kernel is running
+pub fn read_pause_mode<R: Runtime>(host: &mut R) -> Result<Option<PauseMode>> {
+    match read_bytes(host, &CONFIG_PAUSE)? {
+        None => Ok(None),
+        Some(bytes) => match bytes.as_slice() {
+            [byte] => PauseMode::from_byte(*byte)
+                .map(Some)
+                .ok_or(Error::StateDeserializarion),
+            _ => Err(Error::StateDeserializarion),
+        },
+    }
+}
+
+/// Pause the kernel
+pub fn store_pause_mode<R: Runtime>(host: &mut R, mode: &PauseMode) -> Result<()> {
+    store_bytes(host, &CONFIG_PAUSE, &[mode.to_byte()])
+}
+
+/// Remove the pause of the kernel
+pub fn delete_pause_mode<R: Runtime>(host: &mut R) -> Result<()> {
+    if exists(host, &CONFIG_PAUSE)? {
+        delete(host, &CONFIG_PAUSE)?;
+    }
+    Ok(())
+}
+
+/// Compute the path of a deferred message
+/// /deferred/queue/{index}
+fn deferred_message_path(index: &u64) -> Result<OwnedPath> {
+    let path: Vec<u8> = format!("/{}", index).into();
+    let path = OwnedPath::try_from(path).map_err(Error::from)?;
+    concat(&DEFERRED_QUEUE, &path).map_err(Error::from)
+}
+
+/// Check if messages are waiting to be replayed
+pub fn is_deferred_queue_empty<R: Runtime>(host: &mut R) -> Result<bool> {
+    Ok(read_deferred_queue_len(host)? == 0)
+}
+
+/// Read the number of messages waiting to be replayed
+pub fn read_deferred_queue_len<R: Runtime>(host: &mut R) -> Result<u64> {
+    let head = read_u64(host, &DEFERRED_QUEUE_HEAD)?.unwrap_or_default();
+    let tail = read_u64(host, &DEFERRED_QUEUE_TAIL)?.unwrap_or_default();
+    Ok(tail - head)
+}
+
+/// Append a message to the deferred queue, as in the inbox
+pub fn push_deferred_message<R: Runtime>(host: &mut R, message: &Message) -> Result<()> {
+    let json = serde_json_wasm::to_string(message).map_err(|_| Error::StateDeserializarion)?;
+    let tail = read_u64(host, &DEFERRED_QUEUE_TAIL)?.unwrap_or_default();
+    store_bytes(host, &deferred_message_path(&tail)?, json.as_bytes())?;
+    let _ = store_u64(host, &DEFERRED_QUEUE_TAIL, &(tail + 1))?;
+    Ok(())
+}
+
+/// Remove the first message of the deferred queue and return it
+pub fn pop_deferred_message<R: Runtime>(host: &mut R) -> Result<Option<Message>> {
+    if is_deferred_queue_empty(host)? {
+        return Ok(None);
+    }
+    let head = read_u64(host, &DEFERRED_QUEUE_HEAD)?.unwrap_or_default();
+    let path = deferred_message_path(&head)?;
+    let json = read_string(host, &path)?.ok_or(Error::StateDeserializarion)?;
+    let message = serde_json_wasm::from_str(&json).map_err(|_| Error::StateDeserializarion)?;
+    delete(host, &path)?;
+    let _ = store_u64(host, &DEFERRED_QUEUE_HEAD, &(head + 1))?;
+    Ok(Some(message))
+}
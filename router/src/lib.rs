@@ -0,0 +1,319 @@
+//! Dispatch of the inbox messages of a kernel
+//!
+//! A `Router` reads the messages of the shared inbox and dispatches each of them,
+//! once, to the handler registered for its kind:
+//!
+//! - the external messages starting with a magic byte, or any external message
+//! - the transfers addressed to this rollup by a given L1 contract, or any transfer to this rollup
+//! - the start of level, info per level and end of level messages
+//!
+//! ```ignore
+//! let router = Router::<_, _, MichelsonUnit>::new()
+//!     .external(MAGIC_BYTE, |host, info, data| debug_msg!(host, "{}: {:?}\n", info.level, data))
+//!     .start_of_level(|host, info| debug_msg!(host, "Start of level {}\n", info.level));
+//! router.run(host)?;
+//! ```
+//!
+//! The messages without handler, like the transfers addressed to another rollup,
+//! are `Dispatch::Unhandled`. The messages that cannot be parsed with the Michelson type
+//! of the rollup are `Dispatch::Malformed`. In both cases no handler is called.
+
+use tezos_smart_rollup::{
+    host::RuntimeError,
+    inbox::{InboxMessage, InfoPerLevel, InternalInboxMessage, Transfer},
+    michelson::{Michelson, MichelsonUnit},
+    prelude::*,
+};
+
+/// The position of a message in the inbox
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MessageInfo {
+    pub level: u32,
+    pub id: u32,
+}
+
+/// The outcome of the dispatch of a message
+#[derive(Debug, PartialEq, Eq)]
+pub enum Dispatch<T> {
+    /// The message has been handled, with the value returned by its handler
+    Handled(T),
+    /// No handler is registered for the message, or the transfer is addressed to another rollup
+    Unhandled,
+    /// The message cannot be parsed with the Michelson type of the rollup
+    Malformed,
+}
+
+type ExternalHandler<R, T> = fn(&mut R, &MessageInfo, &[u8]) -> T;
+type TransferHandler<R, T, Expr> = fn(&mut R, &MessageInfo, Transfer<Expr>) -> T;
+type LevelHandler<R, T> = fn(&mut R, &MessageInfo) -> T;
+type InfoPerLevelHandler<R, T> = fn(&mut R, &MessageInfo, InfoPerLevel) -> T;
+
+/// The handlers of the inbox messages of a kernel
+///
+/// R is the host, T the value returned by the handlers
+/// and Expr the Michelson type of the transfers to the rollup
+pub struct Router<R, T, Expr: Michelson = MichelsonUnit> {
+    externals: Vec<(u8, ExternalHandler<R, T>)>,
+    any_external: Option<ExternalHandler<R, T>>,
+    transfers: Vec<(String, TransferHandler<R, T, Expr>)>,
+    any_transfer: Option<TransferHandler<R, T, Expr>>,
+    start_of_level: Option<LevelHandler<R, T>>,
+    info_per_level: Option<InfoPerLevelHandler<R, T>>,
+    end_of_level: Option<LevelHandler<R, T>>,
+}
+
+impl<R: Runtime, T, Expr: Michelson> Default for Router<R, T, Expr> {
+    fn default() -> Self {
+        Router {
+            externals: Vec::new(),
+            any_external: None,
+            transfers: Vec::new(),
+            any_transfer: None,
+            start_of_level: None,
+            info_per_level: None,
+            end_of_level: None,
+        }
+    }
+}
+
+impl<R: Runtime, T, Expr: Michelson> Router<R, T, Expr> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Handle the external messages starting with the magic byte
+    ///
+    /// The handler receives the payload following the magic byte
+    pub fn external(mut self, magic_byte: u8, handler: ExternalHandler<R, T>) -> Self {
+        self.externals.push((magic_byte, handler));
+        self
+    }
+
+    /// Handle the external messages without a registered magic byte
+    ///
+    /// The handler receives the whole payload
+    pub fn any_external(mut self, handler: ExternalHandler<R, T>) -> Self {
+        self.any_external = Some(handler);
+        self
+    }
+
+    /// Handle the transfers sent to this rollup by the given KT1 contract
+    pub fn transfer_from(mut self, sender: &str, handler: TransferHandler<R, T, Expr>) -> Self {
+        self.transfers.push((sender.to_string(), handler));
+        self
+    }
+
+    /// Handle the transfers to this rollup from the contracts without a registered handler
+    pub fn any_transfer(mut self, handler: TransferHandler<R, T, Expr>) -> Self {
+        self.any_transfer = Some(handler);
+        self
+    }
+
+    pub fn start_of_level(mut self, handler: LevelHandler<R, T>) -> Self {
+        self.start_of_level = Some(handler);
+        self
+    }
+
+    pub fn info_per_level(mut self, handler: InfoPerLevelHandler<R, T>) -> Self {
+        self.info_per_level = Some(handler);
+        self
+    }
+
+    pub fn end_of_level(mut self, handler: LevelHandler<R, T>) -> Self {
+        self.end_of_level = Some(handler);
+        self
+    }
+
+    /// Read the next message of the inbox and dispatch it
+    ///
+    /// Returns None once the inbox is empty
+    pub fn dispatch(
+        &self,
+        host: &mut R,
+    ) -> Result<Option<(Dispatch<T>, MessageInfo)>, RuntimeError> {
+        let message = match host.read_input()? {
+            None => return Ok(None),
+            Some(message) => message,
+        };
+        let info = MessageInfo {
+            level: message.level,
+            id: message.id,
+        };
+        let dispatch = match InboxMessage::<Expr>::parse(message.as_ref()) {
+            Err(_) => Dispatch::Malformed,
+            Ok((remaining, _)) if !remaining.is_empty() => Dispatch::Malformed,
+            Ok((_, InboxMessage::External(payload))) => {
+                self.dispatch_external(host, &info, payload)
+            }
+            Ok((_, InboxMessage::Internal(internal))) => {
+                self.dispatch_internal(host, &info, internal)?
+            }
+        };
+        Ok(Some((dispatch, info)))
+    }
+
+    /// Dispatch all the messages of the inbox, the values returned by the handlers are dropped
+    pub fn run(&self, host: &mut R) -> Result<(), RuntimeError> {
+        while self.dispatch(host)?.is_some() {}
+        Ok(())
+    }
+
+    fn dispatch_external(&self, host: &mut R, info: &MessageInfo, payload: &[u8]) -> Dispatch<T> {
+        let registered = payload.split_first().and_then(|(magic_byte, data)| {
+            self.externals
+                .iter()
+                .find(|(registered, _)| registered == magic_byte)
+                .map(|(_, handler)| (handler, data))
+        });
+        match (registered, self.any_external) {
+            (Some((handler, data)), _) => Dispatch::Handled(handler(host, info, data)),
+            (None, Some(handler)) => Dispatch::Handled(handler(host, info, payload)),
+            (None, None) => Dispatch::Unhandled,
+        }
+    }
+
+    fn dispatch_internal(
+        &self,
+        host: &mut R,
+        info: &MessageInfo,
+        internal: InternalInboxMessage<Expr>,
+    ) -> Result<Dispatch<T>, RuntimeError> {
+        let dispatch = match internal {
+            InternalInboxMessage::StartOfLevel => {
+                self.start_of_level.map(|handler| handler(host, info))
+            }
+            InternalInboxMessage::InfoPerLevel(info_per_level) => self
+                .info_per_level
+                .map(|handler| handler(host, info, info_per_level)),
+            InternalInboxMessage::EndOfLevel => {
+                self.end_of_level.map(|handler| handler(host, info))
+            }
+            InternalInboxMessage::Transfer(transfer) => {
+                let sender = transfer.sender.to_base58_check();
+                let handler = self
+                    .transfers
+                    .iter()
+                    .find(|(registered, _)| registered == &sender)
+                    .map(|(_, handler)| *handler)
+                    .or(self.any_transfer);
+                match handler {
+                    None => None,
+                    Some(handler) => {
+                        // The inbox is shared by all the rollups
+                        let own_address = host.reveal_metadata()?.address();
+                        match transfer.destination.hash() == &own_address {
+                            true => Some(handler(host, info, transfer)),
+                            false => None,
+                        }
+                    }
+                }
+            }
+        };
+        Ok(dispatch.map_or(Dispatch::Unhandled, Dispatch::Handled))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tezos_crypto_rs::hash::HashType::ContractKt1Hash;
+    use tezos_smart_rollup::{
+        michelson::{MichelsonInt, MichelsonUnit},
+        testing::prelude::{MockHost, TransferMetadata},
+        types::{PublicKeyHash, SmartRollupAddress},
+    };
+
+    use super::{Dispatch, Router};
+
+    const MAGIC_BYTE: u8 = 0x1a;
+    const SENDER: &str = "KT1EfTusMLoeCAAGd9MZJn5yKzFr6kJU5U91";
+    const OTHER_SENDER: &str = "KT1RycYvM4EVs6BAXWEsGXaAaRqiMP53KT4w";
+    const SOURCE: &str = "tz1SodoUsWVe1Yey9eMFbqRUtNpBWfir5NRr";
+    const OTHER_ADDR: &str = "sr1RYurGZtN8KNSpkMcCt9CgWeUaNkzsAfXf";
+
+    struct External(Vec<u8>);
+
+    impl tezos_data_encoding::enc::BinWriter for External {
+        fn bin_write(&self, output: &mut Vec<u8>) -> tezos_data_encoding::enc::BinResult {
+            output.extend_from_slice(&self.0);
+            Ok(())
+        }
+    }
+
+    fn router() -> Router<MockHost, Vec<u8>, MichelsonUnit> {
+        Router::new()
+            .external(MAGIC_BYTE, |_, _, data| data.to_vec())
+            .transfer_from(SENDER, |_, _, _| b"transfer".to_vec())
+    }
+
+    fn metadata() -> TransferMetadata {
+        let sender = ContractKt1Hash.b58check_to_hash(SENDER).unwrap();
+        let source = PublicKeyHash::from_b58check(SOURCE).unwrap();
+        TransferMetadata::new(sender, source)
+    }
+
+    fn next(
+        router: &Router<MockHost, Vec<u8>, MichelsonUnit>,
+        host: &mut MockHost,
+    ) -> Dispatch<Vec<u8>> {
+        router.dispatch(host).unwrap().unwrap().0
+    }
+
+    #[test]
+    fn test_dispatch_externals() {
+        let mut host = MockHost::default();
+        host.add_external(External(vec![MAGIC_BYTE, 0x01, 0x02]));
+        host.add_external(External(vec![0x00, 0x01]));
+        host.add_external(External(vec![]));
+
+        let router = router();
+        assert_eq!(
+            Dispatch::Handled(vec![0x01, 0x02]),
+            next(&router, &mut host)
+        );
+        assert_eq!(Dispatch::Unhandled, next(&router, &mut host));
+        assert_eq!(Dispatch::Unhandled, next(&router, &mut host));
+        assert!(router.dispatch(&mut host).unwrap().is_none());
+
+        // Without magic byte, the whole payload is given to the handler
+        let mut host = MockHost::default();
+        host.add_external(External(vec![0x00, 0x01]));
+        let router = router.any_external(|_, _, payload| payload.to_vec());
+        assert_eq!(
+            Dispatch::Handled(vec![0x00, 0x01]),
+            next(&router, &mut host)
+        );
+    }
+
+    #[test]
+    fn test_dispatch_transfers() {
+        let mut host = MockHost::default();
+        host.add_transfer(MichelsonUnit, &metadata());
+        let mut other_rollup = metadata();
+        other_rollup.override_destination(SmartRollupAddress::from_b58check(OTHER_ADDR).unwrap());
+        host.add_transfer(MichelsonUnit, &other_rollup);
+        // The rollup only receives units
+        host.add_transfer(MichelsonInt::from(32), &metadata());
+
+        let router = router();
+        assert_eq!(
+            Dispatch::Handled(b"transfer".to_vec()),
+            next(&router, &mut host)
+        );
+        assert_eq!(Dispatch::Unhandled, next(&router, &mut host));
+        assert_eq!(Dispatch::Malformed, next(&router, &mut host));
+
+        // The transfers from the other contracts are given to the catch-all handler
+        let other_sender = ContractKt1Hash.b58check_to_hash(OTHER_SENDER).unwrap();
+        let source = PublicKeyHash::from_b58check(SOURCE).unwrap();
+        let other_sender = TransferMetadata::new(other_sender, source);
+        host.add_transfer(MichelsonUnit, &other_sender);
+        host.add_transfer(MichelsonUnit, &other_sender);
+
+        assert_eq!(Dispatch::Unhandled, next(&router, &mut host));
+        let router = router.any_transfer(|_, _, _| b"any transfer".to_vec());
+        assert_eq!(
+            Dispatch::Handled(b"any transfer".to_vec()),
+            next(&router, &mut host)
+        );
+    }
+}
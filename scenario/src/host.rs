@@ -0,0 +1,181 @@
+//! The host running the kernels of the scenarios
+
+use std::cell::Cell;
+use std::ptr;
+
+use tezos_smart_rollup::{
+    core_unsafe::{smart_rollup_core::ReadInputMessageInfo, SmartRollupCore},
+    host,
+    inbox::InfoPerLevel,
+    storage::path::RefPath,
+    testing::prelude::MockHost,
+};
+
+/// Flag written by the kernel to be run again at the same level
+const REBOOT_FLAG: RefPath = RefPath::assert_from(b"/kernel/env/reboot");
+
+/// The maximum number of times a kernel is run at the same level
+const MAXIMUM_REBOOTS_PER_LEVEL: usize = 1000;
+
+/// The MockHost, reading its inputs from the inbox of the scenario
+///
+/// Only transfers and external messages can be added to the inbox of the MockHost,
+/// the scenario host gives the kernel the messages of the level as listed. The other
+/// host functions are the ones of the MockHost.
+pub struct ScenarioHost {
+    host: MockHost,
+    inbox: Vec<Vec<u8>>,
+    next_input: Cell<usize>,
+}
+
+impl Default for ScenarioHost {
+    fn default() -> Self {
+        Self {
+            host: MockHost::default(),
+            inbox: Vec::new(),
+            next_input: Cell::new(0),
+        }
+    }
+}
+
+impl ScenarioHost {
+    /// The level of the next run
+    pub fn level(&self) -> u32 {
+        self.host.level()
+    }
+
+    /// The info of the level of the next run
+    pub fn info_per_level(&self) -> &InfoPerLevel {
+        self.host.info_per_level()
+    }
+
+    /// The outbox written at the given level
+    pub fn outbox_at(&self, level: u32) -> Vec<Vec<u8>> {
+        self.host.outbox_at(level)
+    }
+
+    /// Runs the kernel, and runs it again while it asks for a reboot, against the inbox
+    pub(crate) fn run_level(&mut self, inbox: Vec<Vec<u8>>, entry: fn(&mut Self)) {
+        self.inbox = inbox;
+        self.next_input.set(0);
+        for _ in 0..MAXIMUM_REBOOTS_PER_LEVEL {
+            entry(self);
+            if !matches!(host::Runtime::store_has(self, &REBOOT_FLAG), Ok(Some(_))) {
+                break;
+            }
+            host::Runtime::store_delete(self, &REBOOT_FLAG)
+                .expect("The reboot flag should be deleted");
+        }
+        // The kernel has already read the inbox, the MockHost only moves to the next level
+        self.host.run_level(|_| ());
+    }
+}
+
+unsafe impl SmartRollupCore for ScenarioHost {
+    unsafe fn read_input(
+        &self,
+        message_info: *mut ReadInputMessageInfo,
+        dst: *mut u8,
+        max_bytes: usize,
+    ) -> i32 {
+        let id = self.next_input.get();
+        match self.inbox.get(id) {
+            Some(message) => {
+                self.next_input.set(id + 1);
+                let length = usize::min(max_bytes, message.len());
+                let info = ReadInputMessageInfo {
+                    level: self.host.level() as i32,
+                    id: id as i32,
+                };
+                ptr::write(message_info, info);
+                ptr::copy_nonoverlapping(message.as_ptr(), dst, length);
+                length as i32
+            }
+            None => 0,
+        }
+    }
+
+    unsafe fn write_output(&self, src: *const u8, num_bytes: usize) -> i32 {
+        self.host.write_output(src, num_bytes)
+    }
+
+    unsafe fn write_debug(&self, src: *const u8, num_bytes: usize) {
+        self.host.write_debug(src, num_bytes)
+    }
+
+    unsafe fn store_has(&self, path: *const u8, path_len: usize) -> i32 {
+        self.host.store_has(path, path_len)
+    }
+
+    unsafe fn store_read(
+        &self,
+        path: *const u8,
+        path_len: usize,
+        offset: usize,
+        dst: *mut u8,
+        max_bytes: usize,
+    ) -> i32 {
+        self.host.store_read(path, path_len, offset, dst, max_bytes)
+    }
+
+    unsafe fn store_write(
+        &self,
+        path: *const u8,
+        path_len: usize,
+        offset: usize,
+        src: *const u8,
+        num_bytes: usize,
+    ) -> i32 {
+        self.host
+            .store_write(path, path_len, offset, src, num_bytes)
+    }
+
+    unsafe fn store_delete(&self, path: *const u8, len: usize) -> i32 {
+        self.host.store_delete(path, len)
+    }
+
+    unsafe fn store_list_size(&self, path: *const u8, path_len: usize) -> i64 {
+        self.host.store_list_size(path, path_len)
+    }
+
+    unsafe fn store_move(
+        &self,
+        from_path: *const u8,
+        from_path_len: usize,
+        to_path: *const u8,
+        to_path_len: usize,
+    ) -> i32 {
+        self.host
+            .store_move(from_path, from_path_len, to_path, to_path_len)
+    }
+
+    unsafe fn store_copy(
+        &self,
+        from_path: *const u8,
+        from_path_len: usize,
+        to_path: *const u8,
+        to_path_len: usize,
+    ) -> i32 {
+        self.host
+            .store_copy(from_path, from_path_len, to_path, to_path_len)
+    }
+
+    unsafe fn reveal_preimage(
+        &self,
+        hash_addr: *const u8,
+        hash_len: usize,
+        destination_addr: *mut u8,
+        max_bytes: usize,
+    ) -> i32 {
+        self.host
+            .reveal_preimage(hash_addr, hash_len, destination_addr, max_bytes)
+    }
+
+    unsafe fn store_value_size(&self, path: *const u8, path_len: usize) -> i32 {
+        self.host.store_value_size(path, path_len)
+    }
+
+    unsafe fn reveal_metadata(&self, destination_addr: *mut u8, max_bytes: usize) -> i32 {
+        self.host.reveal_metadata(destination_addr, max_bytes)
+    }
+}
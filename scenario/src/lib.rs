@@ -0,0 +1,597 @@
+//! Scenario tests of kernels on the MockHost
+//!
+//! A scenario is a RON file listing the levels to run. Each level gives the inputs
+//! of its inbox and the expected durable storage, outbox and debug output once
+//! the kernel has run the level:
+//!
+//! ```ron
+//! Scenario(
+//!     levels: [
+//!         Level(
+//!             inputs: [External("00")],
+//!             storage: {"/counter": Some(Hex("0000000000000001"))},
+//!             debug: ["Message from the user."],
+//!         ),
+//!     ],
+//! )
+//! ```
+//!
+//! The scenarios of a kernel live in its `scenarios` directory and are run by a test
+//! without the libtest harness, so that the debug output of the kernel can be captured:
+//!
+//! ```toml
+//! [[test]]
+//! name = "scenarios"
+//! harness = false
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+use gag::BufferRedirect;
+use serde::Deserialize;
+use tezos_crypto_rs::hash::ContractKt1Hash;
+use tezos_data_encoding::enc::BinWriter;
+mod host;
+
+pub use host::ScenarioHost;
+
+use tezos_smart_rollup::{
+    core_unsafe::MAX_FILE_CHUNK_SIZE,
+    inbox::{InboxMessage, InternalInboxMessage, Transfer},
+    michelson::{
+        Michelson as MichelsonExpr, MichelsonBytes, MichelsonInt, MichelsonString, MichelsonUnit,
+    },
+    outbox::{OutboxMessageTransaction, OutboxMessageTransactionBatch},
+    prelude::*,
+    storage::path::OwnedPath,
+    types::{Contract, Entrypoint, PublicKeyHash, SmartRollupAddress},
+};
+
+/// The levels run by a scenario, after the initial storage is written
+#[derive(Deserialize, Debug)]
+pub struct Scenario {
+    /// Values written to the durable storage before the first level
+    #[serde(default)]
+    pub storage: BTreeMap<String, Value>,
+    pub levels: Vec<Level>,
+}
+
+/// The inputs of a level and what is expected once the kernel has run it
+///
+/// The start, info and end of level messages are added around the inputs, unless the
+/// inputs list some of them, then the inbox of the level is the inputs as listed
+#[derive(Deserialize, Debug)]
+pub struct Level {
+    #[serde(default)]
+    pub inputs: Vec<Input>,
+    /// The expected values of the durable storage, None when the path has to be absent
+    #[serde(default)]
+    pub storage: BTreeMap<String, Option<Value>>,
+    /// The expected messages of the outbox of the level, not checked when omitted
+    #[serde(default)]
+    pub outbox: Option<Vec<OutboxMessage>>,
+    /// Lines expected in the debug output of the level, in this order
+    #[serde(default)]
+    pub debug: Vec<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub enum Input {
+    /// The hex encoded payload of an external message, without the external tag
+    External(String),
+    /// A transfer from a L1 contract to the rollup
+    Transfer {
+        payload: Michelson,
+        /// The KT1 contract sending the transfer
+        sender: String,
+        /// The tz1 account that sent the L1 operation
+        source: String,
+        /// The destination rollup, the rollup of the MockHost when omitted
+        #[serde(default)]
+        destination: Option<String>,
+    },
+    StartOfLevel,
+    /// The info of the level given by the MockHost
+    InfoPerLevel,
+    EndOfLevel,
+}
+
+/// The payload of a transfer or the parameters of a transaction
+#[derive(Deserialize, Debug)]
+pub enum Michelson {
+    Int(i32),
+    String(String),
+    /// Hex encoded bytes
+    Bytes(String),
+}
+
+/// A value of the durable storage
+#[derive(Deserialize, Debug)]
+pub enum Value {
+    Hex(String),
+    String(String),
+    /// An u64 encoded in big endian
+    U64(u64),
+}
+
+impl Value {
+    fn to_bytes(&self) -> Result<Vec<u8>, Failure> {
+        match self {
+            Value::Hex(hex) => decode_hex(hex),
+            Value::String(string) => Ok(string.as_bytes().to_vec()),
+            Value::U64(value) => Ok(value.to_be_bytes().to_vec()),
+        }
+    }
+}
+
+/// An expected message of the outbox
+#[derive(Deserialize, Debug)]
+pub enum OutboxMessage {
+    /// The hex encoded message
+    Exact(String),
+    /// Hex encoded bytes contained in the message
+    Contains(String),
+    /// A batch of a single transaction to a L1 contract
+    Transaction {
+        /// The KT1 contract called
+        destination: String,
+        /// The entrypoint called, the default entrypoint when omitted
+        #[serde(default)]
+        entrypoint: Option<String>,
+        parameters: Michelson,
+    },
+}
+
+/// The reason of the failure of a scenario
+#[derive(Debug)]
+pub enum Failure {
+    File(String),
+    Parse(String),
+    InvalidInput(String),
+    /// A level, starting at 0, does not meet its expectations
+    Level(usize, String),
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Failure::File(error) => write!(f, "cannot read the scenario: {}", error),
+            Failure::Parse(error) => write!(f, "invalid scenario: {}", error),
+            Failure::InvalidInput(error) => write!(f, "invalid input: {}", error),
+            Failure::Level(level, error) => write!(f, "level {}: {}", level, error),
+        }
+    }
+}
+
+fn decode_hex(hex: &str) -> Result<Vec<u8>, Failure> {
+    hex::decode(hex).map_err(|_| Failure::InvalidInput(format!("{} is not hex", hex)))
+}
+
+fn path(path: &str) -> Result<OwnedPath, Failure> {
+    OwnedPath::try_from(path.as_bytes().to_vec())
+        .map_err(|_| Failure::InvalidInput(format!("{} is not a path", path)))
+}
+
+fn inbox_message<Expr: MichelsonExpr>(message: InboxMessage<Expr>) -> Result<Vec<u8>, Failure> {
+    let mut bytes = Vec::new();
+    message
+        .serialize(&mut bytes)
+        .map_err(|_| Failure::InvalidInput("cannot encode an inbox message".into()))?;
+    Ok(bytes)
+}
+
+fn transfer<Expr: MichelsonExpr>(
+    payload: Expr,
+    sender: ContractKt1Hash,
+    source: PublicKeyHash,
+    destination: SmartRollupAddress,
+) -> Result<Vec<u8>, Failure> {
+    inbox_message(InboxMessage::Internal(InternalInboxMessage::Transfer(
+        Transfer {
+            payload,
+            sender,
+            source,
+            destination,
+        },
+    )))
+}
+
+impl Input {
+    fn is_level_message(&self) -> bool {
+        matches!(
+            self,
+            Input::StartOfLevel | Input::InfoPerLevel | Input::EndOfLevel
+        )
+    }
+
+    fn to_bytes(&self, host: &ScenarioHost) -> Result<Vec<u8>, Failure> {
+        match self {
+            Input::External(payload) => inbox_message(InboxMessage::<MichelsonUnit>::External(
+                &decode_hex(payload)?,
+            )),
+            Input::Transfer {
+                payload,
+                sender,
+                source,
+                destination,
+            } => {
+                let sender = ContractKt1Hash::from_base58_check(sender)
+                    .map_err(|_| Failure::InvalidInput(format!("invalid sender {}", sender)))?;
+                let source = PublicKeyHash::from_b58check(source)
+                    .map_err(|_| Failure::InvalidInput(format!("invalid source {}", source)))?;
+                let destination = match destination {
+                    Some(destination) => {
+                        SmartRollupAddress::from_b58check(destination).map_err(|_| {
+                            Failure::InvalidInput(format!("invalid destination {}", destination))
+                        })?
+                    }
+                    None => {
+                        let metadata = host.reveal_metadata().map_err(|_| {
+                            Failure::InvalidInput("the rollup address is unknown".into())
+                        })?;
+                        SmartRollupAddress::new(metadata.address())
+                    }
+                };
+                match payload {
+                    Michelson::Int(int) => {
+                        transfer(MichelsonInt::from(*int), sender, source, destination)
+                    }
+                    Michelson::String(string) => {
+                        transfer(MichelsonString(string.clone()), sender, source, destination)
+                    }
+                    Michelson::Bytes(bytes) => transfer(
+                        MichelsonBytes(decode_hex(bytes)?),
+                        sender,
+                        source,
+                        destination,
+                    ),
+                }
+            }
+            Input::StartOfLevel => inbox_message(InboxMessage::<MichelsonUnit>::Internal(
+                InternalInboxMessage::StartOfLevel,
+            )),
+            Input::InfoPerLevel => inbox_message(InboxMessage::<MichelsonUnit>::Internal(
+                InternalInboxMessage::InfoPerLevel(host.info_per_level().clone()),
+            )),
+            Input::EndOfLevel => inbox_message(InboxMessage::<MichelsonUnit>::Internal(
+                InternalInboxMessage::EndOfLevel,
+            )),
+        }
+    }
+}
+
+impl Level {
+    /// The messages of the inbox of the level
+    fn inbox(&self, host: &ScenarioHost) -> Result<Vec<Vec<u8>>, Failure> {
+        let mut inbox = Vec::new();
+        let level_messages = self.inputs.iter().any(Input::is_level_message);
+        if !level_messages {
+            inbox.push(Input::StartOfLevel.to_bytes(host)?);
+            inbox.push(Input::InfoPerLevel.to_bytes(host)?);
+        }
+        for input in &self.inputs {
+            inbox.push(input.to_bytes(host)?);
+        }
+        if !level_messages {
+            inbox.push(Input::EndOfLevel.to_bytes(host)?);
+        }
+        Ok(inbox)
+    }
+}
+
+fn read_value(host: &mut ScenarioHost, path: &OwnedPath) -> Option<Vec<u8>> {
+    let _ = host.store_has(path).ok()??;
+    let size = host.store_value_size(path).ok()?;
+    let mut value = Vec::with_capacity(size);
+    while value.len() < size {
+        let length = usize::min(MAX_FILE_CHUNK_SIZE, size - value.len());
+        let mut chunk = host.store_read(path, value.len(), length).ok()?;
+        value.append(&mut chunk);
+    }
+    Some(value)
+}
+
+/// Runs the level and returns its debug output
+///
+/// The debug output of the MockHost is written to stderr, it is captured during the run
+fn run_level(host: &mut ScenarioHost, inbox: Vec<Vec<u8>>, entry: fn(&mut ScenarioHost)) -> String {
+    let mut redirect = BufferRedirect::stderr()
+        .expect("The debug output should be captured, stderr is already redirected");
+    host.run_level(inbox, entry);
+    let mut debug = String::new();
+    redirect
+        .read_to_string(&mut debug)
+        .expect("The debug output should be read");
+    debug
+}
+
+fn check_storage(
+    host: &mut ScenarioHost,
+    expected: &BTreeMap<String, Option<Value>>,
+) -> Result<(), String> {
+    for (key, value) in expected {
+        let actual = read_value(host, &path(key).map_err(|err| err.to_string())?);
+        let value = match value {
+            Some(value) => Some(value.to_bytes().map_err(|err| err.to_string())?),
+            None => None,
+        };
+        if actual != value {
+            return Err(format!(
+                "{} is {:?}, expected {:?}",
+                key,
+                actual.map(hex::encode),
+                value.map(hex::encode)
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn outbox_transaction<Expr: MichelsonExpr>(
+    parameters: Expr,
+    destination: Contract,
+    entrypoint: Entrypoint,
+) -> Result<Vec<u8>, String> {
+    let transaction = OutboxMessageTransaction {
+        parameters,
+        destination,
+        entrypoint,
+    };
+    let batch = OutboxMessageTransactionBatch::from(vec![transaction]);
+    let mut bytes = Vec::new();
+    tezos_smart_rollup::outbox::OutboxMessage::AtomicTransactionBatch(batch)
+        .bin_write(&mut bytes)
+        .map_err(|_| "cannot encode the transaction".to_string())?;
+    Ok(bytes)
+}
+
+impl OutboxMessage {
+    fn matches(&self, message: &[u8]) -> Result<bool, String> {
+        let hex = hex::encode(message);
+        match self {
+            OutboxMessage::Exact(expected) => Ok(hex == expected.to_lowercase()),
+            OutboxMessage::Contains(expected) => Ok(hex.contains(&expected.to_lowercase())),
+            OutboxMessage::Transaction {
+                destination,
+                entrypoint,
+                parameters,
+            } => {
+                let destination = Contract::from_b58check(destination)
+                    .map_err(|_| format!("invalid destination {}", destination))?;
+                let entrypoint = match entrypoint {
+                    Some(entrypoint) => Entrypoint::try_from(entrypoint.clone())
+                        .map_err(|_| format!("invalid entrypoint {}", entrypoint))?,
+                    None => Entrypoint::default(),
+                };
+                let expected = match parameters {
+                    Michelson::Int(int) => {
+                        outbox_transaction(MichelsonInt::from(*int), destination, entrypoint)?
+                    }
+                    Michelson::String(string) => outbox_transaction(
+                        MichelsonString(string.clone()),
+                        destination,
+                        entrypoint,
+                    )?,
+                    Michelson::Bytes(bytes) => {
+                        let bytes = decode_hex(bytes).map_err(|err| err.to_string())?;
+                        outbox_transaction(MichelsonBytes(bytes), destination, entrypoint)?
+                    }
+                };
+                Ok(message == expected.as_slice())
+            }
+        }
+    }
+}
+
+fn check_outbox(outbox: &[Vec<u8>], expected: &[OutboxMessage]) -> Result<(), String> {
+    if outbox.len() != expected.len() {
+        return Err(format!(
+            "the outbox has {} messages, expected {}",
+            outbox.len(),
+            expected.len()
+        ));
+    }
+    for (index, (message, expected)) in outbox.iter().zip(expected).enumerate() {
+        if !expected.matches(message)? {
+            return Err(format!(
+                "the outbox message {} is {}, expected {:?}",
+                index,
+                hex::encode(message),
+                expected
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn check_debug(debug: &str, expected: &[String]) -> Result<(), String> {
+    let mut output = debug;
+    for line in expected {
+        match output.find(line.as_str()) {
+            Some(index) => output = &output[index + line.len()..],
+            None => return Err(format!("{:?} is missing from the debug output", line)),
+        }
+    }
+    Ok(())
+}
+
+/// Runs a scenario against the entry of a kernel
+pub fn run(entry: fn(&mut ScenarioHost), scenario: &Scenario) -> Result<(), Failure> {
+    let mut host = ScenarioHost::default();
+    for (key, value) in &scenario.storage {
+        host.store_write(&path(key)?, &value.to_bytes()?, 0)
+            .map_err(|_| Failure::InvalidInput(format!("cannot write {}", key)))?;
+    }
+
+    for (index, level) in scenario.levels.iter().enumerate() {
+        let inbox = level.inbox(&host)?;
+        let level_number = host.level();
+        let debug = run_level(&mut host, inbox, entry);
+
+        let outbox = host.outbox_at(level_number);
+        let result = check_storage(&mut host, &level.storage)
+            .and_then(|()| match &level.outbox {
+                Some(expected) => check_outbox(&outbox, expected),
+                None => Ok(()),
+            })
+            .and_then(|()| check_debug(&debug, &level.debug));
+        result.map_err(|error| Failure::Level(index, error))?;
+    }
+    Ok(())
+}
+
+/// Reads and runs a scenario file
+pub fn run_file(entry: fn(&mut ScenarioHost), file: &Path) -> Result<(), Failure> {
+    let scenario = fs::read_to_string(file).map_err(|err| Failure::File(err.to_string()))?;
+    let scenario: Scenario =
+        ron::from_str(&scenario).map_err(|err| Failure::Parse(err.to_string()))?;
+    run(entry, &scenario)
+}
+
+/// Runs the scenarios of a directory, the .ron files, in the order of their names
+///
+/// It is the main function of a test without harness, the process exits with 1 if a scenario fails
+pub fn main(entry: fn(&mut ScenarioHost), directory: &str) {
+    let mut files: Vec<_> = fs::read_dir(directory)
+        .expect("The scenarios directory should be readable")
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .map_or(false, |extension| extension == "ron")
+        })
+        .collect();
+    files.sort();
+
+    println!("\nrunning {} scenarios", files.len());
+    let mut failed = 0;
+    for file in &files {
+        let name = file.file_stem().unwrap_or_default().to_string_lossy();
+        match run_file(entry, file) {
+            Ok(()) => println!("scenario {} ... ok", name),
+            Err(failure) => {
+                println!("scenario {} ... FAILED: {}", name, failure);
+                failed += 1;
+            }
+        }
+    }
+    println!(
+        "\nscenario result: {} passed; {} failed\n",
+        files.len() - failed,
+        failed
+    );
+    if failed > 0 {
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_debug, check_outbox, Michelson, OutboxMessage, Scenario, ScenarioHost};
+
+    #[test]
+    fn test_parse_scenario() {
+        let scenario: Scenario = ron::from_str(
+            r#"Scenario(
+                storage: {"/config": String("on")},
+                levels: [
+                    Level(
+                        inputs: [
+                            External("00"),
+                            Transfer(
+                                payload: Int(32),
+                                sender: "KT1EfTusMLoeCAAGd9MZJn5yKzFr6kJU5U91",
+                                source: "tz1SodoUsWVe1Yey9eMFbqRUtNpBWfir5NRr",
+                            ),
+                        ],
+                        storage: {"/counter": Some(U64(1)), "/absent": None},
+                        outbox: Some([Contains("0020")]),
+                        debug: ["Hello"],
+                    ),
+                    Level(inputs: [StartOfLevel, InfoPerLevel, EndOfLevel]),
+                    Level(
+                        outbox: Some([
+                            Transaction(
+                                destination: "KT1RycYvM4EVs6BAXWEsGXaAaRqiMP53KT4w",
+                                parameters: Int(32),
+                            ),
+                        ]),
+                    ),
+                ],
+            )"#,
+        )
+        .unwrap();
+        assert_eq!(3, scenario.levels.len());
+        assert_eq!(2, scenario.levels[0].inputs.len());
+        assert_eq!(3, scenario.levels[1].inputs.len());
+        assert!(scenario.levels[1].outbox.is_none());
+    }
+
+    #[test]
+    fn test_level_inbox() {
+        let host = ScenarioHost::default();
+        let scenario: Scenario = ron::from_str(
+            r#"Scenario(
+                levels: [
+                    Level(inputs: [External("00")]),
+                    Level(inputs: [External("00"), EndOfLevel]),
+                ],
+            )"#,
+        )
+        .unwrap();
+
+        // The start, info and end of level messages are added around the inputs
+        let inbox = scenario.levels[0].inbox(&host).unwrap();
+        assert_eq!(4, inbox.len());
+        assert_eq!(vec![0x00, 0x01], inbox[0]);
+        assert_eq!(vec![0x00, 0x03], inbox[1][..2].to_vec());
+        assert_eq!(vec![0x01, 0x00], inbox[2]);
+        assert_eq!(vec![0x00, 0x02], inbox[3]);
+
+        // Unless the inputs list some of them
+        let inbox = scenario.levels[1].inbox(&host).unwrap();
+        assert_eq!(vec![vec![0x01, 0x00], vec![0x00, 0x02]], inbox);
+    }
+
+    #[test]
+    fn test_check_debug_in_order() {
+        let debug = "Hello kernel\nMessage from the user.\nBye\n";
+        let expected = |lines: &[&str]| {
+            lines
+                .iter()
+                .map(|line| line.to_string())
+                .collect::<Vec<_>>()
+        };
+        assert!(check_debug(debug, &expected(&["Hello", "Bye"])).is_ok());
+        assert!(check_debug(debug, &expected(&["Bye", "Hello"])).is_err());
+    }
+
+    #[test]
+    fn test_check_outbox() {
+        let outbox = vec![vec![0x00, 0x00, 0x20, 0x01]];
+        assert!(check_outbox(&outbox, &[OutboxMessage::Contains("0020".into())]).is_ok());
+        assert!(check_outbox(&outbox, &[OutboxMessage::Exact("00002001".into())]).is_ok());
+        assert!(check_outbox(&outbox, &[OutboxMessage::Exact("0020".into())]).is_err());
+        assert!(check_outbox(&outbox, &[]).is_err());
+    }
+
+    #[test]
+    fn test_check_outbox_transaction() {
+        let transaction = |parameters| OutboxMessage::Transaction {
+            destination: "KT1RycYvM4EVs6BAXWEsGXaAaRqiMP53KT4w".into(),
+            entrypoint: None,
+            parameters,
+        };
+        // A batch of one transaction of the int 32 to the default entrypoint of the contract
+        let outbox = vec![hex::decode(
+            "0000000023002001becc2e32eca0e6fe70de0e3fb884e8f6aefffa3d000000000764656661756c74",
+        )
+        .unwrap()];
+        assert!(check_outbox(&outbox, &[transaction(Michelson::Int(32))]).is_ok());
+        assert!(check_outbox(&outbox, &[transaction(Michelson::Int(33))]).is_err());
+        assert!(check_outbox(&outbox, &[transaction(Michelson::String("32".into()))]).is_err());
+    }
+}